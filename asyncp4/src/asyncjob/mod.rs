@@ -1,10 +1,11 @@
-use crate::error::Result;
+use crate::{error::Result, sync::CancelToken};
 use crossbeam_channel::Sender;
 use std::sync::{Arc, Mutex, RwLock};
 
 pub struct RunParams<T: Copy + Send, P: Clone + Send + Sync> {
     sender: Sender<T>,
     progress: Arc<RwLock<P>>,
+    cancel: CancelToken,
 }
 
 impl<T: Copy + Send, P: Clone + Send + Sync> RunParams<T, P> {
@@ -17,6 +18,17 @@ impl<T: Copy + Send, P: Clone + Send + Sync> RunParams<T, P> {
         *(self.progress.write()?) = p;
         Ok(())
     }
+
+    /// Set once the job has been cancelled, its result is dropped then.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// To be handed to [`crate::sync::CancellableRunner`] so that a
+    /// running `p4` process is killed on cancellation.
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel
+    }
 }
 
 pub trait AsyncJob: Send + Sync + Clone {
@@ -40,6 +52,8 @@ pub struct AsyncSingleJob<J: AsyncJob> {
     progress: Arc<RwLock<J::Progress>>,
     sender: Sender<J::Notification>,
     pending: Arc<Mutex<()>>,
+    /// cancels the job currently running
+    running: Arc<Mutex<Option<CancelToken>>>,
 }
 
 impl<J: 'static + AsyncJob> AsyncSingleJob<J> {
@@ -48,6 +62,7 @@ impl<J: 'static + AsyncJob> AsyncSingleJob<J> {
             next: Arc::new(Mutex::new(None)),
             last: Arc::new(Mutex::new(None)),
             pending: Arc::new(Mutex::new(())),
+            running: Arc::new(Mutex::new(None)),
             progress: Arc::new(RwLock::new(J::Progress::default())),
             sender,
         }
@@ -57,15 +72,23 @@ impl<J: 'static + AsyncJob> AsyncSingleJob<J> {
         self.pending.try_lock().is_err()
     }
 
+    /// Drops the queued job and cancels the running one, so that neither
+    /// sends a notification.
     pub fn cancel(&mut self) -> bool {
+        let mut cancelled = false;
+
         if let Ok(mut next) = self.next.lock() {
-            if next.is_some() {
-                *next = None;
-                return true;
+            cancelled = next.take().is_some();
+        }
+
+        if let Ok(running) = self.running.lock() {
+            if let Some(cancel) = running.as_ref() {
+                cancel.cancel();
+                cancelled = true;
             }
         }
 
-        false
+        cancelled
     }
 
     pub fn take_last(&self) -> Option<J> {
@@ -104,16 +127,25 @@ impl<J: 'static + AsyncJob> AsyncSingleJob<J> {
         {
             let _pending = self.pending.lock()?;
 
-            let notification = task.run(RunParams {
+            let cancel = CancelToken::default();
+            *self.running.lock()? = Some(cancel.clone());
+
+            let result = task.run(RunParams {
                 progress: self.progress.clone(),
                 sender: self.sender.clone(),
-            })?;
+                cancel: cancel.clone(),
+            });
 
-            if let Ok(mut last) = self.last.lock() {
-                *last = Some(task);
-            }
+            *self.running.lock()? = None;
+            let notification = result?;
+
+            if !cancel.is_cancelled() {
+                if let Ok(mut last) = self.last.lock() {
+                    *last = Some(task);
+                }
 
-            self.sender.send(notification)?;
+                self.sender.send(notification)?;
+            }
         }
 
         self.check_for_job();
@@ -243,9 +275,9 @@ mod test {
 
         wait_for_job(&job);
 
-        println!("recv");
-        receiver.recv().unwrap();
-        println!("received");
+        // the running job ignores cancellation, but its result is dropped
+        assert!(receiver.is_empty());
+        assert!(job.take_last().is_none());
 
         assert_eq!(task.v.load(std::sync::atomic::Ordering::SeqCst), 2);
     }
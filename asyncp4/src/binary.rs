@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asyncjob::{AsyncJob, RunParams},
+    error::Result,
+    sync::{self, CancellableRunner, ChangedBytes, P4Path, P4Runner},
+    AsyncP4Notification,
+};
+
+/// Path of the file and its first changed region.
+type ChangedBytesResult = Result<(P4Path, ChangedBytes)>;
+
+enum JobState {
+    Request(P4Path, usize),
+    Response(ChangedBytesResult),
+}
+
+/// Loads the first changed bytes of an opened file for its hex dump.
+#[derive(Clone)]
+pub struct AsyncChangedBytesJob {
+    runner: Arc<dyn P4Runner>,
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncChangedBytesJob {
+    pub fn new(runner: Arc<dyn P4Runner>, path: P4Path, max_len: usize) -> Self {
+        Self {
+            runner,
+            state: Arc::new(Mutex::new(Some(JobState::Request(path, max_len)))),
+        }
+    }
+
+    /// The changed bytes along with the path of their file.
+    pub fn result(&self) -> Option<ChangedBytesResult> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(result)) = state.take() {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncChangedBytesJob {
+    type Notification = AsyncP4Notification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> Result<Self::Notification> {
+        let runner = CancellableRunner::new(self.runner.as_ref(), params.cancel_token());
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(path, max_len) => JobState::Response(
+                sync::get_changed_bytes(&runner, &path, max_len).map(|bytes| (path, bytes)),
+            ),
+            JobState::Response(result) => JobState::Response(result),
+        });
+
+        Ok(AsyncP4Notification::ChangedBytes)
+    }
+}
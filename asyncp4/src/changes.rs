@@ -0,0 +1,136 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asyncjob::{AsyncJob, RunParams},
+    error::Result,
+    sync::{self, CancellableRunner, Changelist, P4Runner},
+    AsyncP4Notification,
+};
+
+/// Which changelists `p4 changes` should list.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChangelistsParams {
+    Pending {
+        user: Option<String>,
+        client: Option<String>,
+    },
+    /// a page of at most `max` submitted changelists below `before`
+    Submitted { max: usize, before: Option<u32> },
+}
+
+impl ChangelistsParams {
+    fn get_changelists(&self, runner: &dyn P4Runner) -> Result<Vec<Changelist>> {
+        match self {
+            Self::Pending { user, client } => {
+                sync::get_pending_changelists(runner, user.clone(), client.clone())
+            }
+            Self::Submitted { max, before } => {
+                sync::get_submitted_changelists_page(runner, *max, *before)
+            }
+        }
+    }
+}
+
+enum JobState {
+    Request(ChangelistsParams),
+    Response(Result<(ChangelistsParams, Vec<Changelist>)>),
+}
+
+/// Lists changelists.
+#[derive(Clone)]
+pub struct AsyncChangelistsJob {
+    runner: Arc<dyn P4Runner>,
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncChangelistsJob {
+    pub fn new(runner: Arc<dyn P4Runner>, params: ChangelistsParams) -> Self {
+        Self {
+            runner,
+            state: Arc::new(Mutex::new(Some(JobState::Request(params)))),
+        }
+    }
+
+    /// The loaded changelists along with the params they were requested with.
+    pub fn result(&self) -> Option<Result<(ChangelistsParams, Vec<Changelist>)>> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(result)) = state.take() {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncChangelistsJob {
+    type Notification = AsyncP4Notification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> Result<Self::Notification> {
+        let runner = CancellableRunner::new(self.runner.as_ref(), params.cancel_token());
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(params) => JobState::Response(
+                params
+                    .get_changelists(&runner)
+                    .map(|changelists| (params, changelists)),
+            ),
+            JobState::Response(result) => JobState::Response(result),
+        });
+
+        Ok(AsyncP4Notification::Changelists)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asyncjob::AsyncSingleJob,
+        sync::{FixtureRunner, P4Output},
+    };
+    use crossbeam_channel::unbounded;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_changelists_job() {
+        let runner = FixtureRunner::default().with(
+            &[
+                "changes",
+                "-l",
+                "-t",
+                "-s",
+                "submitted",
+                "-m",
+                "2",
+                "//...@<10",
+            ],
+            P4Output::success(
+                "... change 9\n... status submitted\n... user bob\n... desc Fix\n\n\
+                 ... change 7\n... status submitted\n... user al\n... desc Add\n",
+            ),
+        );
+        let (sender, receiver) = unbounded();
+        let mut job = AsyncSingleJob::new(sender);
+
+        let params = ChangelistsParams::Submitted {
+            max: 2,
+            before: Some(10),
+        };
+        job.spawn(AsyncChangelistsJob::new(Arc::new(runner), params.clone()));
+
+        assert_eq!(receiver.recv().unwrap(), AsyncP4Notification::Changelists);
+
+        let (result_params, changelists) = job.take_last().unwrap().result().unwrap().unwrap();
+        assert_eq!(result_params, params);
+        assert_eq!(
+            changelists.iter().map(|c| c.number).collect::<Vec<_>>(),
+            vec![9, 7]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asyncjob::{AsyncJob, RunParams},
+    error::Result,
+    sync::{self, CancellableRunner, Changelist, File, P4Runner},
+    AsyncP4Notification,
+};
+
+/// Changelist number, the changelist and its files.
+type DescribeResult = Result<(u32, (Changelist, Vec<File>))>;
+
+enum JobState {
+    Request(u32),
+    Response(DescribeResult),
+}
+
+/// Loads a changelist and its files through `p4 describe`.
+#[derive(Clone)]
+pub struct AsyncDescribeJob {
+    runner: Arc<dyn P4Runner>,
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncDescribeJob {
+    pub fn new(runner: Arc<dyn P4Runner>, number: u32) -> Self {
+        Self {
+            runner,
+            state: Arc::new(Mutex::new(Some(JobState::Request(number)))),
+        }
+    }
+
+    /// The described changelist along with its number.
+    pub fn result(&self) -> Option<DescribeResult> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(result)) = state.take() {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncDescribeJob {
+    type Notification = AsyncP4Notification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> Result<Self::Notification> {
+        let runner = CancellableRunner::new(self.runner.as_ref(), params.cancel_token());
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(number) => JobState::Response(
+                sync::describe_changelist_files(&runner, number)
+                    .map(|described| (number, described)),
+            ),
+            JobState::Response(result) => JobState::Response(result),
+        });
+
+        Ok(AsyncP4Notification::Describe)
+    }
+}
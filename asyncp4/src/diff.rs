@@ -0,0 +1,268 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asyncjob::{AsyncJob, RunParams},
+    error::Result,
+    sync::{self, CancellableRunner, DiffOptions, FileDiff, P4Path, P4Runner},
+    AsyncP4Notification,
+};
+
+/// Which version of a file is compared to the one it is based on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiffSource {
+    /// the opened file, through `p4 diff`
+    Opened,
+    /// the file shelved in a changelist, through `p4 describe -S`
+    Shelved(u32),
+    /// the file submitted in a changelist, through `p4 describe`
+    Submitted(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiffParams {
+    /// depot path, or local path of an opened file
+    pub path: P4Path,
+    pub source: DiffSource,
+    pub options: DiffOptions,
+}
+
+type ChangelistDiffs = Arc<Vec<(P4Path, FileDiff)>>;
+type CacheKey = (DiffSource, DiffOptions);
+
+/// The file diffs of the changelist described last. `p4 describe` can't be
+/// limited to a single file, so the diff jobs of a tab share this to
+/// describe a changelist once rather than for every file selected.
+#[derive(Clone, Default)]
+pub struct DiffCache {
+    entry: Arc<Mutex<Option<(CacheKey, ChangelistDiffs)>>>,
+}
+
+impl DiffCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the cached diffs, e.g. after a changelist was reshelved.
+    pub fn clear(&self) {
+        if let Ok(mut entry) = self.entry.lock() {
+            *entry = None;
+        }
+    }
+
+    fn get_or_load(
+        &self,
+        key: CacheKey,
+        load: impl FnOnce() -> Result<Vec<(P4Path, FileDiff)>>,
+    ) -> Result<ChangelistDiffs> {
+        if let Some((_, diffs)) = self.entry.lock()?.as_ref().filter(|(k, _)| *k == key) {
+            return Ok(diffs.clone());
+        }
+
+        // not locked while loading, the job may be cancelled meanwhile
+        let diffs = Arc::new(load()?);
+        *self.entry.lock()? = Some((key, diffs.clone()));
+
+        Ok(diffs)
+    }
+}
+
+impl DiffParams {
+    fn get_diff(&self, runner: &dyn P4Runner, cache: &DiffCache) -> Result<FileDiff> {
+        let (changelist, shelved) = match self.source {
+            DiffSource::Opened => return sync::get_diff(runner, &self.path, self.options),
+            DiffSource::Shelved(changelist) => (changelist, true),
+            DiffSource::Submitted(changelist) => (changelist, false),
+        };
+
+        let diffs = cache.get_or_load((self.source, self.options), || {
+            sync::get_changelist_diff(runner, changelist, shelved, self.options)
+        })?;
+
+        Ok(diffs
+            .iter()
+            .find(|(path, _)| *path == self.path)
+            .map(|(_, diff)| diff.clone())
+            .unwrap_or_default())
+    }
+}
+
+enum JobState {
+    Request(DiffParams),
+    Response(Result<(DiffParams, FileDiff)>),
+}
+
+/// Loads the diff of one file.
+#[derive(Clone)]
+pub struct AsyncDiffJob {
+    runner: Arc<dyn P4Runner>,
+    cache: DiffCache,
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncDiffJob {
+    pub fn new(runner: Arc<dyn P4Runner>, params: DiffParams) -> Self {
+        Self {
+            runner,
+            cache: DiffCache::default(),
+            state: Arc::new(Mutex::new(Some(JobState::Request(params)))),
+        }
+    }
+
+    /// Shares the changelist diffs described by earlier jobs.
+    pub fn with_cache(mut self, cache: DiffCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// The finished diff along with the params it was requested with.
+    pub fn result(&self) -> Option<Result<(DiffParams, FileDiff)>> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(result)) = state.take() {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncDiffJob {
+    type Notification = AsyncP4Notification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> Result<Self::Notification> {
+        let runner = CancellableRunner::new(self.runner.as_ref(), params.cancel_token());
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(params) => JobState::Response(
+                params
+                    .get_diff(&runner, &self.cache)
+                    .map(|diff| (params, diff)),
+            ),
+            JobState::Response(result) => JobState::Response(result),
+        });
+
+        Ok(AsyncP4Notification::Diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asyncjob::AsyncSingleJob,
+        sync::{FixtureRunner, P4Output},
+    };
+    use crossbeam_channel::unbounded;
+
+    #[test]
+    fn test_diff_job() {
+        let runner = FixtureRunner::default()
+            .with(
+                &["fstat", "-Ol", "-T", "fileSize", "//depot/a.c#have"],
+                P4Output::success("... fileSize 2\n"),
+            )
+            .with(
+                &["fstat", "-T", "clientFile", "//depot/a.c"],
+                P4Output::success("... clientFile /ws/a.c\n"),
+            )
+            .with(
+                &["diff", "-du3", "//depot/a.c"],
+                P4Output::success("==== //depot/a.c#1 - /ws/a.c ====\n@@ -1 +1 @@\n-a\n+b\n"),
+            );
+        let (sender, receiver) = unbounded();
+        let mut job = AsyncSingleJob::new(sender);
+
+        job.spawn(AsyncDiffJob::new(
+            Arc::new(runner),
+            DiffParams {
+                path: "//depot/a.c".into(),
+                source: DiffSource::Opened,
+                options: DiffOptions::default(),
+            },
+        ));
+
+        assert_eq!(receiver.recv().unwrap(), AsyncP4Notification::Diff);
+
+        let (params, diff) = job.take_last().unwrap().result().unwrap().unwrap();
+        assert_eq!(params.path, "//depot/a.c");
+        assert_eq!(diff.hunks.len(), 1);
+    }
+
+    /// `p4 fstat` of file sizes finding nothing.
+    fn without_sizes(runner: FixtureRunner, revisions: &[&str]) -> FixtureRunner {
+        runner.with(
+            &[&["fstat", "-Ol", "-T", "depotFile,fileSize"], revisions].concat(),
+            P4Output::failure("no such file(s).\n"),
+        )
+    }
+
+    #[test]
+    fn test_shelved_diff() {
+        let runner = FixtureRunner::default().with(
+            &["describe", "-du3", "-S", "12"],
+            P4Output::success(
+                "Change 12 by bob@ws on 2023/07/22 10:00:00 *pending*\n\n\tWip\n\n\
+                 Shelved files ...\n\n... //depot/a.c#1 edit\n... //depot/b.c#3 edit\n\n\
+                 Differences ...\n\n\
+                 ==== //depot/a.c#1 (text) ====\n\n@@ -1 +1 @@\n-a\n+b\n\n\
+                 ==== //depot/b.c#3 (text) ====\n\n@@ -1 +1,2 @@\n c\n+d\n\n",
+            ),
+        );
+        let runner = without_sizes(runner, &["//depot/a.c@=12", "//depot/b.c@=12"]);
+        let runner = without_sizes(runner, &["//depot/a.c#1", "//depot/b.c#3"]);
+        let params = DiffParams {
+            path: "//depot/b.c".into(),
+            source: DiffSource::Shelved(12),
+            options: DiffOptions::default(),
+        };
+
+        let diff = params.get_diff(&runner, &DiffCache::new()).unwrap();
+        assert_eq!(diff.hunks.len(), 1);
+        assert_eq!(diff.hunks[0].lines[2].content.as_ref(), "d");
+    }
+
+    #[test]
+    fn test_changelist_described_once() {
+        let runner = FixtureRunner::default().with(
+            &["describe", "-du3", "120"],
+            P4Output::success(
+                "Change 120 by bob@ws on 2023/07/22 10:00:00\n\n\tFix it\n\n\
+                 Affected files ...\n\n... //depot/a.c#2 edit\n... //depot/b.c#1 add\n\n\
+                 Differences ...\n\n\
+                 ==== //depot/a.c#2 (text) ====\n\n@@ -1 +1 @@\n-a\n+b\n\n\
+                 ==== //depot/b.c#1 (text) ====\n\n@@ -0,0 +1 @@\n+c\n\n",
+            ),
+        );
+        let runner = without_sizes(runner, &["//depot/a.c@=120", "//depot/b.c@=120"]);
+        let runner = without_sizes(runner, &["//depot/a.c#1"]);
+        let describes = || {
+            runner
+                .calls()
+                .iter()
+                .filter(|c| c.name() == "describe")
+                .count()
+        };
+        let cache = DiffCache::new();
+        let params = |path: &str| DiffParams {
+            path: path.into(),
+            source: DiffSource::Submitted(120),
+            options: DiffOptions::default(),
+        };
+
+        let a = params("//depot/a.c").get_diff(&runner, &cache).unwrap();
+        let b = params("//depot/b.c").get_diff(&runner, &cache).unwrap();
+
+        assert_eq!(describes(), 1);
+        assert_eq!(a.hunks[0].lines[2].content.as_ref(), "b");
+        assert!(b.untracked);
+
+        cache.clear();
+        params("//depot/b.c").get_diff(&runner, &cache).unwrap();
+        assert_eq!(describes(), 2);
+    }
+}
@@ -1,7 +1,68 @@
-use std::string::FromUtf8Error;
+use std::{fmt, string::FromUtf8Error, time::Duration};
 
 use thiserror::Error;
 
+/// Severity of a server message, as reported by `p4 -G`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Empty,
+    Info,
+    Warning,
+    Failed,
+    Fatal,
+}
+
+impl From<i32> for Severity {
+    fn from(value: i32) -> Self {
+        match value {
+            i32::MIN..=0 => Self::Empty,
+            1 => Self::Info,
+            2 => Self::Warning,
+            3 => Self::Failed,
+            _ => Self::Fatal,
+        }
+    }
+}
+
+impl Severity {
+    pub(crate) fn is_warning_text(text: &str) -> bool {
+        const WARNINGS: &[&str] = &[
+            "no such file(s)",
+            "file(s) not on client",
+            "file(s) not opened on this client",
+            "file(s) not opened in that changelist",
+            "file(s) up-to-date",
+            "no file(s) at that changelist number",
+        ];
+
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .all(|line| WARNINGS.iter().any(|w| line.contains(w)))
+    }
+}
+
+/// A message the server sent back instead of (or alongside) results.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct P4Message {
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl P4Message {
+    pub fn new(severity: Severity, text: impl Into<String>) -> Self {
+        Self {
+            severity,
+            text: text.into().trim_end().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for P4Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("`{0}`")]
@@ -12,6 +73,88 @@ pub enum Error {
 
     #[error("uft8 error:{0}")]
     Utf8Conversion(#[from] FromUtf8Error),
+
+    #[error("parse error:{0}")]
+    Parse(String),
+
+    #[error("p4 {0} timed out after {1:?}")]
+    Timeout(String, Duration),
+
+    #[error("cancelled")]
+    Cancelled,
+
+    #[error("not logged in: {0}")]
+    NotLoggedIn(P4Message),
+
+    #[error("unknown client: {0}")]
+    UnknownClient(P4Message),
+
+    #[error("file(s) not on client: {0}")]
+    FileNotOnClient(P4Message),
+
+    #[error("no such changelist: {0}")]
+    NoSuchChangelist(P4Message),
+
+    #[error("permission denied: {0}")]
+    PermissionDenied(P4Message),
+
+    #[error("connection refused: {0}")]
+    ConnectionRefused(P4Message),
+
+    #[error("ssl trust required: {0}")]
+    SslTrustRequired(P4Message),
+
+    #[error("p4 error: {0}")]
+    P4(P4Message),
+}
+
+impl Error {
+    /// Sorts a server message into the matching variant by its text.
+    pub fn from_message(message: P4Message) -> Self {
+        let text = message.text.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| text.contains(p));
+
+        if has(&[
+            "p4passwd) invalid or unset",
+            "session has expired",
+            "session was logged out",
+            "please login again",
+        ]) {
+            Self::NotLoggedIn(message)
+        } else if has(&["authenticity of", "p4 trust", "fingerprint for the key"]) {
+            Self::SslTrustRequired(message)
+        } else if has(&[
+            "connect to server failed",
+            "connection refused",
+            "tcp connect to",
+        ]) {
+            Self::ConnectionRefused(message)
+        } else if has(&["unknown - use 'client' command"]) {
+            Self::UnknownClient(message)
+        } else if has(&["not on client", "not opened on this client"]) {
+            Self::FileNotOnClient(message)
+        } else if text.starts_with("change ") && text.ends_with(" unknown.") {
+            Self::NoSuchChangelist(message)
+        } else if has(&["don't have permission", "no permission for"]) {
+            Self::PermissionDenied(message)
+        } else {
+            Self::P4(message)
+        }
+    }
+
+    pub fn message(&self) -> Option<&P4Message> {
+        match self {
+            Self::NotLoggedIn(m)
+            | Self::UnknownClient(m)
+            | Self::FileNotOnClient(m)
+            | Self::NoSuchChangelist(m)
+            | Self::PermissionDenied(m)
+            | Self::ConnectionRefused(m)
+            | Self::SslTrustRequired(m)
+            | Self::P4(m) => Some(m),
+            _ => None,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -27,3 +170,58 @@ impl<T> From<crossbeam_channel::SendError<T>> for Error {
         Self::Generic(format!("send error: {error}"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(text: &str) -> Error {
+        Error::from_message(P4Message::new(Severity::Failed, text))
+    }
+
+    #[test]
+    fn test_classify() {
+        assert!(matches!(
+            classify("Perforce password (P4PASSWD) invalid or unset.\n"),
+            Error::NotLoggedIn(_)
+        ));
+        assert!(matches!(
+            classify("Your session has expired, please login again."),
+            Error::NotLoggedIn(_)
+        ));
+        assert!(matches!(
+            classify("Client 'bob-ws' unknown - use 'client' command to create it."),
+            Error::UnknownClient(_)
+        ));
+        assert!(matches!(
+            classify("//depot/a.c - file(s) not on client."),
+            Error::FileNotOnClient(_)
+        ));
+        assert!(matches!(
+            classify("Change 99 unknown."),
+            Error::NoSuchChangelist(_)
+        ));
+        assert!(matches!(
+            classify("You don't have permission for this operation."),
+            Error::PermissionDenied(_)
+        ));
+        assert!(matches!(
+            classify("Perforce client error:\n\tConnect to server failed; check $P4PORT.\n\tTCP connect to perforce:1666 failed.\n\tconnect: 127.0.0.1:1666: Connection refused"),
+            Error::ConnectionRefused(_)
+        ));
+        assert!(matches!(
+            classify("The authenticity of '10.0.0.1:1666' can't be established,\nthis may be your first attempt to connect to this P4PORT.\nTo allow connection use the 'p4 trust' command."),
+            Error::SslTrustRequired(_)
+        ));
+        assert!(matches!(classify("Something else."), Error::P4(_)));
+    }
+
+    #[test]
+    fn test_message_kept() {
+        let err = classify("Change 99 unknown.\n");
+
+        let message = err.message().unwrap();
+        assert_eq!(message.severity, Severity::Failed);
+        assert_eq!(message.text, "Change 99 unknown.");
+    }
+}
@@ -0,0 +1,83 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asyncjob::{AsyncJob, RunParams},
+    error::Result,
+    sync::{self, CancellableRunner, File, FileSpec, P4Runner},
+    AsyncP4Notification,
+};
+
+enum JobState {
+    Request(FileSpec),
+    Response(Result<(FileSpec, Vec<File>)>),
+}
+
+/// Loads files through `p4 fstat`.
+#[derive(Clone)]
+pub struct AsyncFilesJob {
+    runner: Arc<dyn P4Runner>,
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncFilesJob {
+    pub fn new(runner: Arc<dyn P4Runner>, spec: FileSpec) -> Self {
+        Self {
+            runner,
+            state: Arc::new(Mutex::new(Some(JobState::Request(spec)))),
+        }
+    }
+
+    /// The loaded files along with the spec they were requested with.
+    pub fn result(&self) -> Option<Result<(FileSpec, Vec<File>)>> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(result)) = state.take() {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncFilesJob {
+    type Notification = AsyncP4Notification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> Result<Self::Notification> {
+        let runner = CancellableRunner::new(self.runner.as_ref(), params.cancel_token());
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(spec) => {
+                JobState::Response(sync::get_files(&runner, &spec).map(|files| (spec, files)))
+            }
+            JobState::Response(result) => JobState::Response(result),
+        });
+
+        Ok(AsyncP4Notification::Files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asyncjob::AsyncSingleJob, sync::FixtureRunner};
+    use crossbeam_channel::unbounded;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_files_job_error() {
+        let runner = FixtureRunner::default();
+        let (sender, receiver) = unbounded();
+        let mut job = AsyncSingleJob::new(sender);
+
+        job.spawn(AsyncFilesJob::new(Arc::new(runner), FileSpec::Opened(3)));
+
+        // failures still notify so that the error can be picked up
+        assert_eq!(receiver.recv().unwrap(), AsyncP4Notification::Files);
+        assert!(job.take_last().unwrap().result().unwrap().is_err());
+    }
+}
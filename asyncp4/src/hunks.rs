@@ -0,0 +1,62 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asyncjob::{AsyncJob, RunParams},
+    error::Result,
+    sync::{self, CancellableRunner, Hunk, P4Path, P4Runner},
+    AsyncP4Notification,
+};
+
+enum JobState {
+    Request(P4Path, Hunk),
+    Response(Result<P4Path>),
+}
+
+/// Reverts a hunk of an opened file in its local copy.
+#[derive(Clone)]
+pub struct AsyncDiscardHunkJob {
+    runner: Arc<dyn P4Runner>,
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncDiscardHunkJob {
+    pub fn new(runner: Arc<dyn P4Runner>, path: P4Path, hunk: Hunk) -> Self {
+        Self {
+            runner,
+            state: Arc::new(Mutex::new(Some(JobState::Request(path, hunk)))),
+        }
+    }
+
+    /// The path of the file the hunk was discarded from.
+    pub fn result(&self) -> Option<Result<P4Path>> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(result)) = state.take() {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncDiscardHunkJob {
+    type Notification = AsyncP4Notification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> Result<Self::Notification> {
+        let runner = CancellableRunner::new(self.runner.as_ref(), params.cancel_token());
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(path, hunk) => {
+                JobState::Response(sync::discard_hunk(&runner, &path, &hunk).map(|()| path))
+            }
+            JobState::Response(result) => JobState::Response(result),
+        });
+
+        Ok(AsyncP4Notification::DiscardHunk)
+    }
+}
@@ -1,5 +1,33 @@
 pub mod asyncjob;
+mod binary;
+mod changes;
+mod describe;
+mod diff;
 mod error;
+mod files;
+mod hunks;
+mod login;
 pub mod sync;
 
-pub use crate::error::{Error, Result};
+pub use crate::{
+    binary::AsyncChangedBytesJob,
+    changes::{AsyncChangelistsJob, ChangelistsParams},
+    describe::AsyncDescribeJob,
+    diff::{AsyncDiffJob, DiffCache, DiffParams, DiffSource},
+    error::{Error, P4Message, Result, Severity},
+    files::AsyncFilesJob,
+    hunks::AsyncDiscardHunkJob,
+    login::AsyncLoginJob,
+};
+
+/// Sent by async jobs once their result can be picked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsyncP4Notification {
+    Changelists,
+    Describe,
+    Files,
+    Diff,
+    DiscardHunk,
+    ChangedBytes,
+    Login,
+}
@@ -0,0 +1,59 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    asyncjob::{AsyncJob, RunParams},
+    error::Result,
+    sync::{self, CancellableRunner, P4Runner},
+    AsyncP4Notification,
+};
+
+enum JobState {
+    Request(String),
+    Response(Result<()>),
+}
+
+/// Logs in to the server through `p4 login`.
+#[derive(Clone)]
+pub struct AsyncLoginJob {
+    runner: Arc<dyn P4Runner>,
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncLoginJob {
+    pub fn new(runner: Arc<dyn P4Runner>, password: String) -> Self {
+        Self {
+            runner,
+            state: Arc::new(Mutex::new(Some(JobState::Request(password)))),
+        }
+    }
+
+    pub fn result(&self) -> Option<Result<()>> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(result)) = state.take() {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncLoginJob {
+    type Notification = AsyncP4Notification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> Result<Self::Notification> {
+        let runner = CancellableRunner::new(self.runner.as_ref(), params.cancel_token());
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(password) => JobState::Response(sync::login(&runner, &password)),
+            JobState::Response(result) => JobState::Response(result),
+        });
+
+        Ok(AsyncP4Notification::Login)
+    }
+}
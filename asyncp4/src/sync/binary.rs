@@ -0,0 +1,137 @@
+use std::{fs, io};
+
+use super::{
+    command::P4Command,
+    file::{get_files, FileSpec},
+    path::P4Path,
+    runner::P4Runner,
+};
+use crate::{Error, Result, Severity};
+
+/// Bytes are compared in rows of this size, so a region starts on a row.
+const ROW_SIZE: usize = 16;
+
+/// The first changed region of a file in the revision it was opened from
+/// and in its local copy.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ChangedBytes {
+    /// offset of the first byte of `old` and `new` in their files
+    pub offset: usize,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+/// Compares the opened file `path` byte by byte with the revision it was
+/// opened from, keeping at most `max_len` bytes of each side.
+pub fn get_changed_bytes(
+    runner: &dyn P4Runner,
+    path: &P4Path,
+    max_len: usize,
+) -> Result<ChangedBytes> {
+    let old = P4Command::new("print")
+        .raw()
+        .arg("-q")
+        .arg(path.with_suffix("#have"))
+        .run_raw(runner);
+
+    let old = match old {
+        Ok(old) => old,
+        // files opened for add have no revision yet
+        Err(e) if e.message().is_some_and(|m| m.severity <= Severity::Warning) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let local_path = get_files(runner, &FileSpec::Path(path.clone()))?
+        .into_iter()
+        .next()
+        .and_then(|f| f.local_path)
+        .ok_or_else(|| Error::Generic(format!("{path} has no local file")))?;
+
+    let new = match fs::read(local_path) {
+        Ok(new) => new,
+        // files opened for delete
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(changed_region(&old, &new, max_len))
+}
+
+fn changed_region(old: &[u8], new: &[u8], max_len: usize) -> ChangedBytes {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let offset = prefix - prefix % ROW_SIZE;
+    let side = |bytes: &[u8]| {
+        let end = (bytes.len() - suffix).min(offset + max_len);
+        bytes[offset..end].to_vec()
+    };
+
+    ChangedBytes {
+        offset,
+        old: side(old),
+        new: side(new),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{FixtureRunner, P4Output};
+    use pretty_assertions::assert_eq;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_changed_region() {
+        let old = (0..64).collect::<Vec<u8>>();
+        let mut new = old.clone();
+        new[20] = 0xff;
+        new.insert(40, 0xfe);
+
+        let region = changed_region(&old, &new, 1024);
+
+        assert_eq!(region.offset, 16);
+        assert_eq!(region.old, old[16..40]);
+        assert_eq!(region.new, new[16..41]);
+
+        let region = changed_region(&old, &new, 8);
+        assert_eq!(region.old, old[16..24]);
+
+        assert_eq!(changed_region(&old, &old, 8).old, Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_get_changed_bytes() {
+        let file = NamedTempFile::new().unwrap();
+        let local = file.path().to_str().unwrap();
+        fs::write(local, b"GIF89b").unwrap();
+
+        let runner = FixtureRunner::default()
+            .with(
+                &["print", "-q", "//depot/a.gif#have"],
+                P4Output::success("GIF89a"),
+            )
+            .with(
+                &["fstat", "//depot/a.gif"],
+                P4Output::success(format!(
+                    "... depotFile //depot/a.gif\n... clientFile {local}\n... action edit\n"
+                )),
+            );
+
+        let region = get_changed_bytes(&runner, &"//depot/a.gif".into(), 1024).unwrap();
+
+        assert_eq!(
+            region,
+            ChangedBytes {
+                offset: 0,
+                old: b"GIF89a".to_vec(),
+                new: b"GIF89b".to_vec(),
+            }
+        );
+    }
+}
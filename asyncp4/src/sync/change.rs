@@ -1,87 +1,290 @@
-use std::{process::Command, str};
+use std::str::FromStr;
 
+use super::{command::P4Command, file::File, record::Record, runner::P4Runner};
 use crate::{Error, Result};
 
-use regex::Regex;
-
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum ChangelistStatus {
+    #[default]
     None,
     Pending,
+    Shelved,
     Submitted,
 }
 
+impl FromStr for ChangelistStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "shelved" => Ok(Self::Shelved),
+            "submitted" => Ok(Self::Submitted),
+            _ => Err(Error::Parse(format!("unknown changelist status `{s}`"))),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct Changelist {
-    changelist: u32,
-    // files: Vec<File>,
-    status: ChangelistStatus,
+    /// `0` is the default changelist.
+    pub number: u32,
+    pub status: ChangelistStatus,
+    pub description: String,
+    pub user: String,
+    pub client: String,
+    /// seconds since the unix epoch
+    pub time: i64,
+    /// a pending changelist that also has shelved files
+    pub has_shelved: bool,
+    /// only known when loaded through `p4 describe`
+    pub file_count: Option<usize>,
 }
 
 impl Changelist {
     pub fn new() -> Self {
-        Self {
-            changelist: 0,
-            // files: Vec::new(),
-            status: ChangelistStatus::None,
-        }
+        Self::default()
+    }
+
+    pub fn from_record(record: &Record) -> Result<Self> {
+        Ok(Self {
+            number: record.parse("change")?,
+            status: record.parse("status")?,
+            description: record
+                .get("desc")
+                .unwrap_or_default()
+                .trim_end()
+                .to_string(),
+            user: record.get("user").unwrap_or_default().to_string(),
+            client: record.get("client").unwrap_or_default().to_string(),
+            time: record.parse_opt("time")?.unwrap_or_default(),
+            has_shelved: record.contains_key("shelved"),
+            file_count: None,
+        })
     }
 
-    // pub fn file<'a>(&'a mut self, f: File) -> &'a mut Changelist {
-    //     self.files.push(f);
-    //     self
-    // }
+    pub fn is_default(&self) -> bool {
+        self.number == 0
+    }
+
+    /// First non-empty line of the description.
+    pub fn short_description(&self) -> &str {
+        self.description
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty())
+            .unwrap_or_default()
+    }
 }
 
-pub fn get_pending_changelists(user: Option<String>, client: Option<String>) -> Result<Vec<u32>> {
-    get_changelists_impl(user, client, ChangelistStatus::Pending)
+pub fn get_pending_changelists(
+    runner: &dyn P4Runner,
+    user: Option<String>,
+    client: Option<String>,
+) -> Result<Vec<Changelist>> {
+    get_changelists_impl(runner, user, client, ChangelistStatus::Pending)
 }
 
-pub fn get_submitted_changelists(user: Option<String>, client: Option<String>) -> Result<Vec<u32>> {
-    get_changelists_impl(user, client, ChangelistStatus::Submitted)
+pub fn get_submitted_changelists(
+    runner: &dyn P4Runner,
+    user: Option<String>,
+    client: Option<String>,
+) -> Result<Vec<Changelist>> {
+    get_changelists_impl(runner, user, client, ChangelistStatus::Submitted)
+}
+
+/// A page of at most `max` submitted changelists, newest first, below
+/// changelist `before` so that pages continue where the last one ended.
+pub fn get_submitted_changelists_page(
+    runner: &dyn P4Runner,
+    max: usize,
+    before: Option<u32>,
+) -> Result<Vec<Changelist>> {
+    let mut cmd = P4Command::new("changes")
+        .args(["-l", "-t", "-s", "submitted", "-m"])
+        .arg(max.to_string());
+    if let Some(before) = before {
+        cmd = cmd.arg(format!("//...@<{before}"));
+    }
+
+    cmd.run(runner)?
+        .iter()
+        .map(Changelist::from_record)
+        .collect()
 }
 
 fn get_changelists_impl(
+    runner: &dyn P4Runner,
     user: Option<String>,
     client: Option<String>,
     status: ChangelistStatus,
-) -> Result<Vec<u32>> {
-    let mut cmd = Command::new("p4");
-    cmd.arg("changes");
+) -> Result<Vec<Changelist>> {
+    let mut cmd = P4Command::new("changes").args(["-l", "-t"]);
     if let Some(u) = user {
-        cmd.arg("-u");
-        cmd.arg(u);
+        cmd = cmd.args(["-u", u.as_str()]);
     }
     if let Some(c) = client {
-        cmd.arg("-c");
-        cmd.arg(c);
+        cmd = cmd.args(["-c", c.as_str()]);
     }
     match status {
-        ChangelistStatus::Pending => {
-            cmd.arg("-s");
-            cmd.arg("pending");
-        }
-        ChangelistStatus::Submitted => {
-            cmd.arg("-s");
-            cmd.arg("submitted");
-        }
-        _ => {}
+        ChangelistStatus::Pending => cmd = cmd.args(["-s", "pending"]),
+        ChangelistStatus::Shelved => cmd = cmd.args(["-s", "shelved"]),
+        ChangelistStatus::Submitted => cmd = cmd.args(["-s", "submitted"]),
+        ChangelistStatus::None => {}
     }
 
-    let output = cmd.output().map_err(|e| Error::from(e))?;
-
-    let mut result = Vec::new();
-    for line in str::from_utf8(output.stdout.as_slice())
-        .unwrap()
-        // FIXME: replace unwrap with map err
-        .split('\n')
-        .filter(|l| !l.is_empty())
-    {
-        // FIXME: replace unwrap with map err
-        let re = Regex::new(r"Change ([0-9]+)").unwrap();
-        if let Some(caps) = re.captures(line) {
-            let cl = String::from(&caps[1]).parse::<u32>().unwrap();
-            result.push(cl);
-        }
+    cmd.run(runner)?
+        .iter()
+        .map(Changelist::from_record)
+        .collect()
+}
+
+/// Loads a single changelist through `p4 describe`, including its file count.
+pub fn describe_changelist(runner: &dyn P4Runner, number: u32) -> Result<Changelist> {
+    describe_changelist_files(runner, number).map(|(changelist, _)| changelist)
+}
+
+/// Like [`describe_changelist`], along with the files the changelist
+/// affects.
+pub fn describe_changelist_files(
+    runner: &dyn P4Runner,
+    number: u32,
+) -> Result<(Changelist, Vec<File>)> {
+    let records = P4Command::new("describe")
+        .arg("-s")
+        .arg(number.to_string())
+        .run(runner)?;
+
+    let record = records
+        .first()
+        .ok_or_else(|| Error::Parse(format!("no description for change {number}")))?;
+
+    let mut changelist = Changelist::from_record(record)?;
+    let files = record
+        .indexed_records()
+        .iter()
+        .filter(|f| f.contains_key("depotFile"))
+        .map(|f| File::from_describe_record(f, number))
+        .collect::<Result<Vec<_>>>()?;
+    changelist.file_count = Some(files.len());
+
+    Ok((changelist, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{FileAction, FixtureRunner, P4Output};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_pending_changelists() {
+        let runner = FixtureRunner::default().with(
+            &["changes", "-l", "-t", "-u", "bob", "-s", "pending"],
+            P4Output::success(
+                "... change 12\n... status pending\n\n... change 9\n... status pending\n",
+            ),
+        );
+
+        let changelists = get_pending_changelists(&runner, Some("bob".into()), None).unwrap();
+
+        assert_eq!(
+            changelists.iter().map(|c| c.number).collect::<Vec<_>>(),
+            vec![12, 9]
+        );
     }
 
-    Ok(result)
+    #[test]
+    fn test_changelist_fields() {
+        let runner = FixtureRunner::default().with(
+            &["changes", "-l", "-t", "-s", "submitted"],
+            P4Output::success(
+                "... change 120\n... time 1690000000\n... user bob\n... client bob-ws\n... status submitted\n... changeType public\n... path //depot/main/...\n... desc \n  Fix the frobnicator\n\n  Details follow.\n\n",
+            ),
+        );
+
+        let changelists = get_submitted_changelists(&runner, None, None).unwrap();
+
+        assert_eq!(
+            changelists,
+            vec![Changelist {
+                number: 120,
+                status: ChangelistStatus::Submitted,
+                description: "\n  Fix the frobnicator\n\n  Details follow.".to_string(),
+                user: "bob".to_string(),
+                client: "bob-ws".to_string(),
+                time: 1_690_000_000,
+                has_shelved: false,
+                file_count: None,
+            }]
+        );
+        assert_eq!(changelists[0].short_description(), "Fix the frobnicator");
+    }
+
+    #[test]
+    fn test_describe() {
+        let runner = FixtureRunner::default().with(
+            &["describe", "-s", "7"],
+            P4Output::success(
+                "... change 7\n... user bob\n... client ws\n... time 1\n... desc wip\n\n... status pending\n... shelved \n... depotFile0 //depot/a.c\n... action0 edit\n... depotFile1 //depot/b.c\n... action1 add\n",
+            ),
+        );
+
+        let (changelist, files) = describe_changelist_files(&runner, 7).unwrap();
+
+        assert_eq!(changelist.status, ChangelistStatus::Pending);
+        assert!(changelist.has_shelved);
+        assert_eq!(changelist.file_count, Some(2));
+        assert_eq!(files[1].depot_path, "//depot/b.c");
+        assert_eq!(files[1].current_action(), Some(&FileAction::Add));
+        assert_eq!(files[1].head_change, Some(7));
+    }
+
+    #[test]
+    fn test_submitted_page() {
+        let runner = FixtureRunner::default().with(
+            &[
+                "changes",
+                "-l",
+                "-t",
+                "-s",
+                "submitted",
+                "-m",
+                "2",
+                "//...@<120",
+            ],
+            P4Output::success(
+                "... change 119\n... status submitted\n\n... change 117\n... status submitted\n",
+            ),
+        );
+
+        let changelists = get_submitted_changelists_page(&runner, 2, Some(120)).unwrap();
+
+        assert_eq!(
+            changelists.iter().map(|c| c.number).collect::<Vec<_>>(),
+            vec![119, 117]
+        );
+    }
+
+    #[test]
+    fn test_failed_listing() {
+        let runner = FixtureRunner::default().with(
+            &["changes", "-l", "-t", "-c", "gone", "-s", "pending"],
+            P4Output::failure("Client 'gone' unknown - use 'client' command to create it.\n"),
+        );
+
+        let err = get_pending_changelists(&runner, None, Some("gone".into())).unwrap_err();
+
+        assert!(matches!(err, Error::UnknownClient(_)));
+    }
+
+    #[test]
+    fn test_malformed_changelist() {
+        let runner = FixtureRunner::default().with(
+            &["changes", "-l", "-t", "-s", "submitted"],
+            P4Output::success("... change abc\n... status submitted\n"),
+        );
+
+        assert!(get_submitted_changelists(&runner, None, None).is_err());
+    }
 }
@@ -0,0 +1,178 @@
+use std::{ffi::OsString, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    marshal,
+    record::{Record, RecordCode},
+    runner::{P4Output, P4Runner},
+    ztag,
+};
+use crate::{Error, P4Message, Result, Severity};
+
+/// Structured output format requested from `p4`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputFormat {
+    /// `p4 -ztag`
+    #[default]
+    Tagged,
+    /// `p4 -G`
+    Marshal,
+}
+
+impl OutputFormat {
+    pub fn flag(self) -> &'static str {
+        match self {
+            Self::Tagged => "-ztag",
+            Self::Marshal => "-G",
+        }
+    }
+}
+
+/// A `p4` invocation without the global output format flag, which is added
+/// by the [`P4Runner`] executing it unless the command is [`P4Command::raw`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct P4Command {
+    args: Vec<OsString>,
+    stdin: Option<Vec<u8>>,
+    raw: bool,
+}
+
+impl P4Command {
+    pub fn new(command: &str) -> Self {
+        Self {
+            args: vec![OsString::from(command)],
+            stdin: None,
+            raw: false,
+        }
+    }
+
+    /// Asks for plain text output, e.g. for diffs which are not tagged.
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+
+    pub fn is_raw(&self) -> bool {
+        self.raw
+    }
+
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn stdin(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    pub fn get_args(&self) -> &[OsString] {
+        &self.args
+    }
+
+    /// The `p4` command, e.g. `describe`.
+    pub fn name(&self) -> String {
+        self.args[0].to_string_lossy().into_owned()
+    }
+
+    pub fn get_stdin(&self) -> Option<&[u8]> {
+        self.stdin.as_deref()
+    }
+
+    pub fn run(&self, runner: &dyn P4Runner) -> Result<Vec<Record>> {
+        let output = runner.run(self)?;
+
+        let records = match runner.output_format() {
+            OutputFormat::Tagged => ztag::parse(&output.stdout),
+            OutputFormat::Marshal => marshal::parse(&output.stdout)?,
+        };
+
+        let (errors, records): (Vec<_>, Vec<_>) = records
+            .into_iter()
+            .partition(|r| r.code() == RecordCode::Error);
+
+        let messages = errors
+            .iter()
+            .map(|r| {
+                P4Message::new(
+                    r.parse::<i32>("severity")
+                        .map_or(Severity::Failed, Severity::from),
+                    r.get("data").unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        self.check(&output, messages, records.is_empty())?;
+
+        Ok(records)
+    }
+
+    /// Runs a [`P4Command::raw`] command and returns its plain stdout.
+    pub fn run_raw(&self, runner: &dyn P4Runner) -> Result<Vec<u8>> {
+        let output = runner.run(self)?;
+
+        self.check(&output, Vec::new(), output.stdout.is_empty())?;
+
+        Ok(output.stdout)
+    }
+
+    fn check(&self, output: &P4Output, mut messages: Vec<P4Message>, empty: bool) -> Result<()> {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.trim().is_empty() {
+            // tagged output carries no severity, so known empty-result
+            // warnings have to be recognized by their text
+            let severity = if output.status == 0 || Severity::is_warning_text(&stderr) {
+                Severity::Warning
+            } else {
+                Severity::Failed
+            };
+            messages.push(P4Message::new(severity, stderr));
+        }
+
+        if output.status != 0 && messages.is_empty() {
+            messages.push(P4Message::new(
+                Severity::Failed,
+                format!("{self} exited with status {}", output.status),
+            ));
+        }
+
+        // warnings only fail the command if they are all it produced, e.g.
+        // `p4 changes` on an unknown client
+        let failed = messages.iter().any(|m| m.severity >= Severity::Failed)
+            || (empty && !messages.is_empty());
+
+        if failed {
+            let message = messages
+                .into_iter()
+                .max_by_key(|m| m.severity)
+                .unwrap_or_else(|| P4Message::new(Severity::Failed, ""));
+            return Err(Error::from_message(message));
+        }
+
+        for message in &messages {
+            log::warn!("p4 {}: {}", self.name(), message);
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for P4Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "p4")?;
+        for arg in &self.args {
+            write!(f, " {}", arg.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_PORT: &str = "perforce:1666";
+
+/// Where the value of a [`Setting`] was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SettingSource {
+    Config(PathBuf),
+    Environment,
+    Enviro(PathBuf),
+    Default,
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(path) => write!(f, "config '{}'", path.display()),
+            Self::Environment => write!(f, "environment"),
+            Self::Enviro(path) => write!(f, "set '{}'", path.display()),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub value: String,
+    pub source: SettingSource,
+}
+
+/// The connection `p4` would use from a given directory.
+///
+/// Like `p4 set`, values come from the first of: the nearest `P4CONFIG`
+/// file above the directory, the environment, the `P4ENVIRO` file and
+/// finally the built-in defaults.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionSettings {
+    pub port: Setting,
+    pub user: Option<Setting>,
+    pub client: Option<Setting>,
+}
+
+impl ConnectionSettings {
+    pub fn resolve(cwd: &Path) -> Self {
+        let home = env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(PathBuf::from);
+
+        Self::resolve_with(cwd, |key| env::var(key).ok(), home.as_deref())
+    }
+
+    fn resolve_with(cwd: &Path, env: impl Fn(&str) -> Option<String>, home: Option<&Path>) -> Self {
+        let enviro_path = env("P4ENVIRO")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|h| h.join(".p4enviro")));
+        let enviro = enviro_path
+            .as_ref()
+            .map(|p| (p.clone(), read_settings_file(p)));
+
+        let config_name = env("P4CONFIG").or_else(|| {
+            enviro
+                .as_ref()
+                .and_then(|(_, values)| values.get("P4CONFIG").cloned())
+        });
+        let config = config_name.and_then(|name| find_config(cwd, &name));
+
+        let lookup = |key: &str| -> Option<Setting> {
+            if let Some((path, values)) = &config {
+                if let Some(value) = values.get(key) {
+                    return Some(Setting {
+                        value: value.clone(),
+                        source: SettingSource::Config(path.clone()),
+                    });
+                }
+            }
+
+            if let Some(value) = env(key).filter(|v| !v.is_empty()) {
+                return Some(Setting {
+                    value,
+                    source: SettingSource::Environment,
+                });
+            }
+
+            enviro.as_ref().and_then(|(path, values)| {
+                values.get(key).map(|value| Setting {
+                    value: value.clone(),
+                    source: SettingSource::Enviro(path.clone()),
+                })
+            })
+        };
+
+        let default = |value: Option<String>| {
+            value.map(|value| Setting {
+                value,
+                source: SettingSource::Default,
+            })
+        };
+
+        Self {
+            port: lookup("P4PORT").unwrap_or_else(|| Setting {
+                value: DEFAULT_PORT.to_string(),
+                source: SettingSource::Default,
+            }),
+            user: lookup("P4USER").or_else(|| default(env("USER").or_else(|| env("USERNAME")))),
+            client: lookup("P4CLIENT")
+                .or_else(|| default(env("HOSTNAME").or_else(|| env("COMPUTERNAME")))),
+        }
+    }
+
+    /// Global `p4` arguments pinning this connection.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["-p".to_string(), self.port.value.clone()];
+        if let Some(user) = &self.user {
+            args.extend(["-u".to_string(), user.value.clone()]);
+        }
+        if let Some(client) = &self.client {
+            args.extend(["-c".to_string(), client.value.clone()]);
+        }
+        args
+    }
+}
+
+impl fmt::Display for ConnectionSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn value(setting: &Option<Setting>) -> &str {
+            setting.as_ref().map_or("?", |s| s.value.as_str())
+        }
+
+        write!(
+            f,
+            "{}@{} {}",
+            value(&self.user),
+            value(&self.client),
+            self.port.value
+        )
+    }
+}
+
+fn find_config(cwd: &Path, name: &str) -> Option<(PathBuf, HashMap<String, String>)> {
+    let cwd = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+
+    cwd.ancestors().find_map(|dir| {
+        let path = dir.join(name);
+        path.is_file().then(|| {
+            let mut values = read_settings_file(&path);
+            // `$configdir` expands to the directory containing the file
+            for value in values.values_mut() {
+                *value = value.replace("$configdir", &dir.to_string_lossy());
+            }
+            (path, values)
+        })
+    })
+}
+
+fn read_settings_file(path: &Path) -> HashMap<String, String> {
+    fs::read_to_string(path)
+        .map(|data| {
+            data.lines()
+                .map(str::trim)
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(|line| line.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    fn env_from(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let dir = TempDir::new().unwrap();
+
+        let settings = ConnectionSettings::resolve_with(dir.path(), env_from(&[]), None);
+
+        assert_eq!(settings.port.value, DEFAULT_PORT);
+        assert_eq!(settings.port.source, SettingSource::Default);
+        assert_eq!(settings.user, None);
+    }
+
+    #[test]
+    fn test_precedence() {
+        let dir = TempDir::new().unwrap();
+        let workspace = dir.path().join("ws");
+        let nested = workspace.join("src/deep");
+        fs::create_dir_all(&nested).unwrap();
+
+        fs::write(
+            workspace.join(".p4config"),
+            "# workspace\nP4CLIENT=bob-ws\nP4PORT=ssl:$configdir:1666\n",
+        )
+        .unwrap();
+        let enviro = dir.path().join(".p4enviro");
+        fs::write(
+            &enviro,
+            "P4CONFIG=.p4config\nP4USER=from-enviro\nP4PORT=x:1\n",
+        )
+        .unwrap();
+
+        let settings = ConnectionSettings::resolve_with(
+            &nested,
+            env_from(&[("P4PORT", "env:1666"), ("USER", "os-user")]),
+            Some(dir.path()),
+        );
+
+        let config = workspace.canonicalize().unwrap().join(".p4config");
+        assert_eq!(settings.client.as_ref().unwrap().value, "bob-ws");
+        assert_eq!(
+            settings.client.unwrap().source,
+            SettingSource::Config(config.clone())
+        );
+        assert_eq!(
+            settings.port.value,
+            format!("ssl:{}:1666", workspace.canonicalize().unwrap().display())
+        );
+        assert_eq!(settings.port.source, SettingSource::Config(config));
+        assert_eq!(
+            settings.user,
+            Some(Setting {
+                value: "from-enviro".to_string(),
+                source: SettingSource::Enviro(enviro),
+            })
+        );
+    }
+
+    #[test]
+    fn test_args() {
+        let dir = TempDir::new().unwrap();
+
+        let settings = ConnectionSettings::resolve_with(
+            dir.path(),
+            env_from(&[("P4USER", "bob"), ("P4CLIENT", "ws")]),
+            None,
+        );
+
+        assert_eq!(
+            settings.args(),
+            vec!["-p", DEFAULT_PORT, "-u", "bob", "-c", "ws"]
+        );
+        assert_eq!(settings.to_string(), "bob@ws perforce:1666");
+    }
+}
@@ -1,24 +1,48 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    ops::Range,
+    path::PathBuf,
+};
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+use super::{
+    command::P4Command,
+    file::{FileBaseType, FileType},
+    path::P4Path,
+    runner::P4Runner,
+    word_diff::highlight_words,
+};
+use crate::{Result, Severity};
+
+pub(crate) const NO_NEWLINE: &str = "\\ No newline at end of file";
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub enum DiffLineType {
+    #[default]
     None,
     Header,
     Add,
     Delete,
 }
 
-impl Default for DiffLineType {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 #[derive(Default, Clone, Hash, Debug)]
 pub struct DiffLine {
     pub content: Box<str>,
     pub line_type: DiffLineType,
     pub position: DiffLinePosition,
+    /// Changed and unchanged parts of `content` if the line was paired with
+    /// its counterpart for word highlighting, empty otherwise.
+    pub segments: Vec<DiffSegment>,
+}
+
+/// A byte range of [`DiffLine::content`].
+#[derive(Clone, Default, Hash, Debug, PartialEq, Eq)]
+pub struct DiffSegment {
+    pub range: Range<usize>,
+    pub changed: bool,
 }
 
 #[derive(Clone, Copy, Default, Hash, Debug, PartialEq, Eq)]
@@ -27,7 +51,7 @@ pub struct DiffLinePosition {
     pub new_lineno: Option<u32>,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) struct HunkHeader {
     pub old_start: u32,
     pub old_lines: u32,
@@ -35,6 +59,43 @@ pub(crate) struct HunkHeader {
     pub new_lines: u32,
 }
 
+impl HunkHeader {
+    /// Parses `@@ -old_start,old_lines +new_start,new_lines @@`, where a
+    /// missing count means `1`.
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let ranges = line.strip_prefix("@@ -")?;
+        let (ranges, _) = ranges.split_once(" @@")?;
+        let (old, new) = ranges.split_once(" +")?;
+
+        let range = |r: &str| -> Option<(u32, u32)> {
+            match r.split_once(',') {
+                Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+                None => Some((r.parse().ok()?, 1)),
+            }
+        };
+
+        let (old_start, old_lines) = range(old)?;
+        let (new_start, new_lines) = range(new)?;
+
+        Some(Self {
+            old_start,
+            old_lines,
+            new_start,
+            new_lines,
+        })
+    }
+}
+
+impl std::fmt::Display for HunkHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "@@ -{},{} +{},{} @@",
+            self.old_start, self.old_lines, self.new_start, self.new_lines
+        )
+    }
+}
+
 #[derive(Default, Clone, Hash, Debug)]
 pub struct Hunk {
     pub header_hash: u64,
@@ -46,13 +107,1036 @@ pub struct FileDiff {
     pub hunks: Vec<Hunk>,
     pub lines: usize,
     pub untracked: bool,
+    pub binary: bool,
+    /// no lines because a side is larger than [`DiffOptions::max_size`]
+    pub too_large: bool,
+    /// old and new size in bytes
     pub sizes: (u64, u64),
     pub size_delta: i64,
 }
 
+/// How whitespace differences are treated.
+#[derive(Debug, Default, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiffWhitespace {
+    #[default]
+    Show,
+    /// `-db`
+    IgnoreChanges,
+    /// `-dw`
+    IgnoreAll,
+}
+
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DiffOptions {
-    pub ignore_whitespace: bool,
+    pub whitespace: DiffWhitespace,
     pub context: u32,
     pub interhunk_lines: u32,
+    /// Files larger than this many bytes are not diffed line by line.
+    pub max_size: u64,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            whitespace: DiffWhitespace::Show,
+            context: 3,
+            interhunk_lines: 0,
+            max_size: 1024 * 1024,
+        }
+    }
+}
+
+impl DiffOptions {
+    /// Asks the server for enough context that hunks closer than
+    /// `interhunk_lines` arrive joined, [`parse`] splits the rest again.
+    fn args(self) -> Vec<String> {
+        let mut args = vec![format!("-du{}", self.context + self.interhunk_lines)];
+        match self.whitespace {
+            DiffWhitespace::Show => {}
+            DiffWhitespace::IgnoreChanges => args.push("-db".to_string()),
+            DiffWhitespace::IgnoreAll => args.push("-dw".to_string()),
+        }
+        args
+    }
+}
+
+impl FileDiff {
+    /// Summarizes a diff of files this large instead of keeping its lines.
+    fn set_sizes(&mut self, (old, new): (u64, u64), max_size: u64) {
+        self.sizes = (old, new);
+        self.size_delta =
+            i64::try_from(new).unwrap_or(i64::MAX) - i64::try_from(old).unwrap_or(i64::MAX);
+
+        if old.max(new) > max_size {
+            self.hunks.clear();
+            self.lines = 0;
+            self.too_large = true;
+        }
+    }
+}
+
+/// `p4 diff` of an opened file against the revision it was opened from.
+pub fn get_diff(runner: &dyn P4Runner, path: &P4Path, options: DiffOptions) -> Result<FileDiff> {
+    // sizes come first, so files too large to show are never diffed
+    let old = get_have_size(runner, path)?;
+    let new = get_local_path(runner, path)?
+        .and_then(|p| fs::metadata(p).ok())
+        .map_or(0, |m| m.len());
+
+    let mut diff = if old.max(new) > options.max_size {
+        FileDiff::default()
+    } else {
+        match get_parsed_diff(runner, path, options)? {
+            Some(file) => file.diff,
+            None => return Ok(FileDiff::default()),
+        }
+    };
+
+    diff.set_sizes((old, new), options.max_size);
+
+    Ok(diff)
+}
+
+/// Local path of the opened file `path`.
+fn get_local_path(runner: &dyn P4Runner, path: &P4Path) -> Result<Option<PathBuf>> {
+    let records = P4Command::new("fstat")
+        .args(["-T", "clientFile"])
+        .arg(path)
+        .run(runner);
+
+    match records {
+        Ok(records) => Ok(records
+            .first()
+            .and_then(|r| r.get_bytes("clientFile"))
+            .map(|p| P4Path::new(p).to_path_buf())),
+        Err(e) if e.message().is_some_and(|m| m.severity <= Severity::Warning) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Size of the revision `path` was opened from, `0` for files opened for
+/// add.
+fn get_have_size(runner: &dyn P4Runner, path: &P4Path) -> Result<u64> {
+    let records = P4Command::new("fstat")
+        .args(["-Ol", "-T", "fileSize"])
+        .arg(path.with_suffix("#have"))
+        .run(runner);
+
+    match records {
+        Ok(records) => Ok(records
+            .first()
+            .map(|r| r.parse_opt::<u64>("fileSize"))
+            .transpose()?
+            .flatten()
+            .unwrap_or_default()),
+        // "no such file(s)"
+        Err(e) if e.message().is_some_and(|m| m.severity <= Severity::Warning) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// Like [`get_diff`] but keeping the local path, `None` for files without
+/// a diff.
+pub(crate) fn get_parsed_diff(
+    runner: &dyn P4Runner,
+    path: &P4Path,
+    options: DiffOptions,
+) -> Result<Option<ParsedFile>> {
+    let output = P4Command::new("diff")
+        .raw()
+        .args(options.args())
+        .arg(path)
+        .run_raw(runner);
+
+    let output = match output {
+        Ok(output) => output,
+        // "file(s) not opened on this client" and friends
+        Err(e) if e.message().is_some_and(|m| m.severity <= Severity::Warning) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    Ok(parse(&output, options).into_iter().next())
+}
+
+/// `p4 describe` diffs of every file in a changelist, keyed by depot path.
+/// Shelved files are described instead of submitted ones with `shelved`.
+pub fn get_changelist_diff(
+    runner: &dyn P4Runner,
+    changelist: u32,
+    shelved: bool,
+    options: DiffOptions,
+) -> Result<Vec<(P4Path, FileDiff)>> {
+    let mut cmd = P4Command::new("describe").raw().args(options.args());
+    if shelved {
+        cmd = cmd.arg("-S");
+    }
+
+    let output = cmd.arg(changelist.to_string()).run_raw(runner)?;
+    let files = parse(&output, options);
+
+    // `p4 describe` prints no sizes, so they are looked up on both sides
+    let new_sizes = get_sizes(
+        runner,
+        files
+            .iter()
+            .map(|f| f.depot_path.with_suffix(&format!("@={changelist}")))
+            .collect(),
+    )?;
+    let old_sizes = get_sizes(
+        runner,
+        files
+            .iter()
+            .filter_map(|f| {
+                // submitted files are described by their new revision,
+                // shelved ones by the revision they are based on
+                let base = if shelved {
+                    f.revision?
+                } else {
+                    f.revision?.checked_sub(1)?
+                };
+                (base > 0).then(|| f.depot_path.with_suffix(&format!("#{base}")))
+            })
+            .collect(),
+    )?;
+
+    Ok(files
+        .into_iter()
+        .map(|f| {
+            let mut diff = f.diff;
+            diff.set_sizes(
+                (
+                    old_sizes.get(&f.depot_path).copied().unwrap_or_default(),
+                    new_sizes.get(&f.depot_path).copied().unwrap_or_default(),
+                ),
+                options.max_size,
+            );
+            (f.depot_path, diff)
+        })
+        .collect())
+}
+
+/// Sizes of the file revisions `revisions` keyed by depot path, leaving out
+/// deleted and missing ones.
+fn get_sizes(runner: &dyn P4Runner, revisions: Vec<P4Path>) -> Result<HashMap<P4Path, u64>> {
+    if revisions.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let records = P4Command::new("fstat")
+        .args(["-Ol", "-T", "depotFile,fileSize"])
+        .args(revisions)
+        .run(runner);
+
+    let records = match records {
+        Ok(records) => records,
+        Err(e) if e.message().is_some_and(|m| m.severity <= Severity::Warning) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut sizes = HashMap::new();
+    for record in &records {
+        if let (Some(path), Some(size)) =
+            (record.get_bytes("depotFile"), record.parse_opt("fileSize")?)
+        {
+            sizes.insert(P4Path::new(path), size);
+        }
+    }
+
+    Ok(sizes)
+}
+
+pub(crate) struct ParsedFile {
+    pub depot_path: P4Path,
+    /// revision named by a `====` header
+    pub revision: Option<u32>,
+    pub local_path: Option<P4Path>,
+    pub diff: FileDiff,
+}
+
+struct RawLine {
+    line_type: DiffLineType,
+    content: String,
+    no_newline: bool,
+    // line numbers consumed before this line
+    old_before: u32,
+    new_before: u32,
+}
+
+impl RawLine {
+    fn position(&self) -> DiffLinePosition {
+        DiffLinePosition {
+            old_lineno: (self.line_type != DiffLineType::Add).then_some(self.old_before + 1),
+            new_lineno: (self.line_type != DiffLineType::Delete).then_some(self.new_before + 1),
+        }
+    }
+
+    fn size(&self) -> i64 {
+        let size = i64::try_from(self.content.len()).unwrap_or(i64::MAX);
+        if self.no_newline {
+            size
+        } else {
+            size + 1
+        }
+    }
+}
+
+struct RawHunk {
+    header: HunkHeader,
+    // the `@@` line as printed, including any function context
+    text: String,
+    lines: Vec<RawLine>,
+}
+
+#[derive(Default)]
+struct RawFile {
+    depot_path: P4Path,
+    revision: Option<u32>,
+    local_path: Option<P4Path>,
+    binary: bool,
+    hunks: Vec<RawHunk>,
+}
+
+/// Parses unified diffs as printed by `p4 diff -du` and `p4 describe -du`,
+/// with files introduced by a `====` header, `---`/`+++` headers or both.
+pub(crate) fn parse(output: &[u8], options: DiffOptions) -> Vec<ParsedFile> {
+    let output = output.strip_suffix(b"\n").unwrap_or(output);
+
+    let mut files: Vec<RawFile> = Vec::new();
+    // old and new lines still expected in the current hunk
+    let mut remaining = (0_u32, 0_u32);
+    // `---`/`+++` right after a `====` header name the same file
+    let mut after_banner = false;
+
+    for bytes in output.split(|b| *b == b'\n') {
+        // paths in headers are kept byte for byte, the rest is text
+        let content = String::from_utf8_lossy(bytes);
+        let content = content.as_ref();
+        // diff lines keep a carriage return that is part of the file
+        let line = content.strip_suffix('\r').unwrap_or(content);
+        let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+
+        if remaining != (0, 0) {
+            if let Some(file) = files.last_mut() {
+                if let Some(hunk) = file.hunks.last_mut() {
+                    push_line(&mut hunk.lines, content, &mut remaining);
+                    continue;
+                }
+            }
+        }
+
+        if let Some(header) = bytes.strip_prefix(b"==== ") {
+            files.push(parse_file_header(header));
+            after_banner = true;
+            continue;
+        }
+
+        if let Some(path) = bytes.strip_prefix(b"--- ") {
+            if !after_banner {
+                files.push(RawFile {
+                    depot_path: P4Path::new(unified_header_path(path)),
+                    ..RawFile::default()
+                });
+            }
+            after_banner = false;
+            continue;
+        }
+        after_banner = false;
+
+        if line.starts_with(NO_NEWLINE) {
+            if let Some(last) = files
+                .last_mut()
+                .and_then(|f| f.hunks.last_mut())
+                .and_then(|h| h.lines.last_mut())
+            {
+                last.no_newline = true;
+            }
+        } else if let Some(file) = files.last_mut() {
+            if let Some(path) = bytes.strip_prefix(b"+++ ") {
+                let path = unified_header_path(path);
+                // `p4 describe` names the depot file on both sides
+                if file.local_path.is_none() && !path.starts_with(b"//") {
+                    file.local_path = Some(P4Path::new(path));
+                }
+            } else if line.starts_with("Binary files ") {
+                file.binary = true;
+            } else if let Some(header) = HunkHeader::parse(line) {
+                remaining = (header.old_lines, header.new_lines);
+                file.hunks.push(RawHunk {
+                    header,
+                    text: line.to_string(),
+                    lines: Vec::new(),
+                });
+            }
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|file| ParsedFile {
+            diff: build_file_diff(&file, options),
+            depot_path: file.depot_path,
+            revision: file.revision,
+            local_path: file.local_path,
+        })
+        .collect()
+}
+
+fn push_line(lines: &mut Vec<RawLine>, line: &str, remaining: &mut (u32, u32)) {
+    if line.starts_with(NO_NEWLINE) {
+        if let Some(last) = lines.last_mut() {
+            last.no_newline = true;
+        }
+        return;
+    }
+
+    let (line_type, content) = match line.chars().next() {
+        Some('+') => (DiffLineType::Add, &line[1..]),
+        Some('-') => (DiffLineType::Delete, &line[1..]),
+        Some(' ') => (DiffLineType::None, &line[1..]),
+        // some tools strip the blank of empty context lines
+        _ => (DiffLineType::None, line),
+    };
+
+    let (old_before, new_before) = lines.last().map_or((0, 0), |l| {
+        (
+            l.old_before + u32::from(l.line_type != DiffLineType::Add),
+            l.new_before + u32::from(l.line_type != DiffLineType::Delete),
+        )
+    });
+
+    if line_type != DiffLineType::Add {
+        remaining.0 = remaining.0.saturating_sub(1);
+    }
+    if line_type != DiffLineType::Delete {
+        remaining.1 = remaining.1.saturating_sub(1);
+    }
+
+    lines.push(RawLine {
+        line_type,
+        content: content.to_string(),
+        no_newline: false,
+        old_before,
+        new_before,
+    });
+}
+
+/// `//depot/a.c#4 - /ws/a.c ====` from `p4 diff` or
+/// `//depot/a.c#4 (text) ====` from `p4 describe`.
+fn parse_file_header(header: &[u8]) -> RawFile {
+    let (inner, trailer) = rsplit_once(header, b" ====").unwrap_or((header, b""));
+
+    let (depot, local) = match split_once(inner, b" - ") {
+        Some((depot, local)) => (depot, Some(P4Path::new(local))),
+        None => (inner, None),
+    };
+
+    let filetype = rsplit_once(depot, b" (")
+        .and_then(|(_, t)| t.strip_suffix(b")"))
+        .and_then(|t| std::str::from_utf8(t).ok())
+        .and_then(|t| t.parse::<FileType>().ok());
+
+    let binary = split_once(trailer, b"binary").is_some()
+        || filetype.is_some_and(|t| {
+            matches!(
+                t.base,
+                FileBaseType::Binary | FileBaseType::Apple | FileBaseType::Resource
+            )
+        });
+
+    let (depot_path, revision) = match rsplit_once(depot, b"#") {
+        Some((path, rev)) => {
+            let rev = rev.split(|b| *b == b' ').next().unwrap_or(rev);
+            (
+                path,
+                std::str::from_utf8(rev).ok().and_then(|r| r.parse().ok()),
+            )
+        }
+        None => (depot, None),
+    };
+
+    RawFile {
+        depot_path: P4Path::new(depot_path),
+        revision,
+        local_path: local,
+        binary,
+        hunks: Vec::new(),
+    }
+}
+
+/// `//depot/a.c\t2023/07/22 10:00:00` from a `---` or `+++` header, with
+/// the date and any revision dropped.
+fn unified_header_path(header: &[u8]) -> &[u8] {
+    let path = header.split(|b| *b == b'\t').next().unwrap_or(header);
+    rsplit_once(path, b"#").map_or(path, |(path, _)| path)
+}
+
+/// Splits `bytes` around the first `separator`, like [`str::split_once`].
+fn split_once<'a>(bytes: &'a [u8], separator: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let i = bytes
+        .windows(separator.len())
+        .position(|w| w == separator)?;
+    Some((&bytes[..i], &bytes[i + separator.len()..]))
+}
+
+/// Splits `bytes` around the last `separator`, like [`str::rsplit_once`].
+fn rsplit_once<'a>(bytes: &'a [u8], separator: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let i = bytes
+        .windows(separator.len())
+        .rposition(|w| w == separator)?;
+    Some((&bytes[..i], &bytes[i + separator.len()..]))
+}
+
+fn build_file_diff(file: &RawFile, options: DiffOptions) -> FileDiff {
+    let mut diff = FileDiff {
+        binary: file.binary,
+        untracked: !file.hunks.is_empty()
+            && file
+                .hunks
+                .iter()
+                .all(|h| h.header.old_start == 0 && h.header.old_lines == 0),
+        ..FileDiff::default()
+    };
+
+    for hunk in &file.hunks {
+        let (header, lines) = (&hunk.header, &hunk.lines);
+        let offset = (
+            header
+                .old_start
+                .saturating_sub(u32::from(header.old_lines > 0)),
+            header
+                .new_start
+                .saturating_sub(u32::from(header.new_lines > 0)),
+        );
+
+        diff.size_delta += lines
+            .iter()
+            .map(|l| match l.line_type {
+                DiffLineType::Add => l.size(),
+                DiffLineType::Delete => -l.size(),
+                _ => 0,
+            })
+            .sum::<i64>();
+
+        for (i, range) in split_hunk(lines, options).into_iter().enumerate() {
+            diff.hunks
+                .push(build_hunk(&lines[range], offset, hunk, i == 0));
+        }
+    }
+
+    diff.lines = diff.hunks.iter().map(|h| h.lines.len()).sum();
+
+    diff
+}
+
+/// Regroups the changes of one server hunk so that at most `context` lines
+/// surround them, keeping changes no further apart than
+/// `2 * context + interhunk_lines` in the same hunk.
+fn split_hunk(lines: &[RawLine], options: DiffOptions) -> Vec<std::ops::Range<usize>> {
+    let context = options.context as usize;
+    let max_gap = 2 * context + options.interhunk_lines as usize;
+
+    let changes = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, l)| l.line_type != DiffLineType::None)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        match groups.last_mut() {
+            Some((_, last)) if i - *last - 1 <= max_gap => *last = i,
+            _ => groups.push((i, i)),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(first, last)| first.saturating_sub(context)..(last + context + 1).min(lines.len()))
+        .collect()
+}
+
+/// A hunk of `lines`, a part of the server hunk `raw`, the `first` of them
+/// keeping its function context.
+fn build_hunk(lines: &[RawLine], offset: (u32, u32), raw: &RawHunk, first: bool) -> Hunk {
+    let count = |t: DiffLineType| {
+        u32::try_from(lines.iter().filter(|l| l.line_type != t).count()).unwrap_or(u32::MAX)
+    };
+    let old_lines = count(DiffLineType::Add);
+    let new_lines = count(DiffLineType::Delete);
+
+    let (old_before, new_before) = lines
+        .first()
+        .map_or((0, 0), |l| (l.old_before, l.new_before));
+
+    let header = HunkHeader {
+        old_start: offset.0 + old_before + u32::from(old_lines > 0),
+        old_lines,
+        new_start: offset.1 + new_before + u32::from(new_lines > 0),
+        new_lines,
+    };
+
+    let header_line = if header == raw.header {
+        raw.text.clone()
+    } else if first {
+        let context = raw.text[2..].split_once("@@").map_or("", |(_, c)| c);
+        format!("{header}{context}")
+    } else {
+        header.to_string()
+    };
+
+    let mut hunk_lines = vec![DiffLine {
+        content: header_line.into(),
+        line_type: DiffLineType::Header,
+        ..DiffLine::default()
+    }];
+
+    for line in lines {
+        let position = line.position();
+        hunk_lines.push(DiffLine {
+            content: line.content.as_str().into(),
+            line_type: line.line_type,
+            position: DiffLinePosition {
+                old_lineno: position.old_lineno.map(|n| n + offset.0),
+                new_lineno: position.new_lineno.map(|n| n + offset.1),
+            },
+            ..DiffLine::default()
+        });
+
+        if line.no_newline {
+            hunk_lines.push(DiffLine {
+                content: NO_NEWLINE.into(),
+                ..DiffLine::default()
+            });
+        }
+    }
+
+    highlight_words(&mut hunk_lines);
+
+    Hunk {
+        header_hash: hash(&header),
+        lines: hunk_lines,
+    }
+}
+
+fn hash<T: Hash + ?Sized>(v: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    v.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{FixtureRunner, P4Output};
+    use pretty_assertions::assert_eq;
+
+    fn headers(diff: &FileDiff) -> Vec<String> {
+        diff.hunks
+            .iter()
+            .map(|h| h.lines[0].content.to_string())
+            .collect()
+    }
+
+    const SPREAD: &str = "==== //depot/a.c#4 - /ws/a.c ====\n\
+        @@ -1,12 +1,12 @@\n 1\n-2\n+two\n 3\n 4\n 5\n 6\n 7\n 8\n 9\n-10\n+ten\n 11\n 12\n";
+
+    #[test]
+    fn test_hunk_header() {
+        assert_eq!(
+            HunkHeader::parse("@@ -3 +3,2 @@ fn main()"),
+            Some(HunkHeader {
+                old_start: 3,
+                old_lines: 1,
+                new_start: 3,
+                new_lines: 2,
+            })
+        );
+        assert_eq!(HunkHeader::parse("@@ nonsense @@"), None);
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let files = parse(
+            b"==== //depot/a.c#4 - /ws/a.c ====\n@@ -4,3 +4,4 @@\n ctx\n-old\n+new\n+more\n ctx\n",
+            DiffOptions::default(),
+        );
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].depot_path, "//depot/a.c");
+        assert_eq!(files[0].local_path, Some("/ws/a.c".into()));
+
+        let diff = &files[0].diff;
+        assert_eq!(headers(diff), vec!["@@ -4,3 +4,4 @@"]);
+        assert_eq!(diff.lines, 6);
+
+        let positions = diff.hunks[0].lines[1..]
+            .iter()
+            .map(|l| (l.line_type, l.position.old_lineno, l.position.new_lineno))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![
+                (DiffLineType::None, Some(4), Some(4)),
+                (DiffLineType::Delete, Some(5), None),
+                (DiffLineType::Add, None, Some(5)),
+                (DiffLineType::Add, None, Some(6)),
+                (DiffLineType::None, Some(6), Some(7)),
+            ]
+        );
+        assert_eq!(diff.size_delta, 5);
+        assert_eq!(
+            diff.hunks[0].header_hash,
+            hash(&HunkHeader {
+                old_start: 4,
+                old_lines: 3,
+                new_start: 4,
+                new_lines: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn test_context_split() {
+        let options = DiffOptions {
+            context: 1,
+            ..DiffOptions::default()
+        };
+
+        let diff = &parse(SPREAD.as_bytes(), options)[0].diff;
+
+        assert_eq!(headers(diff), vec!["@@ -1,3 +1,3 @@", "@@ -9,3 +9,3 @@"]);
+        assert_eq!(diff.hunks[1].lines[1].content.as_ref(), "9");
+        assert_eq!(diff.hunks[1].lines[1].position.old_lineno, Some(9));
+    }
+
+    #[test]
+    fn test_interhunk_lines() {
+        let options = DiffOptions {
+            context: 1,
+            interhunk_lines: 5,
+            ..DiffOptions::default()
+        };
+
+        let diff = &parse(SPREAD.as_bytes(), options)[0].diff;
+
+        assert_eq!(headers(diff), vec!["@@ -1,11 +1,11 @@"]);
+    }
+
+    #[test]
+    fn test_function_context() {
+        let spread = SPREAD.replace("@@ -1,12 +1,12 @@", "@@ -1,12 +1,12 @@ int main()");
+
+        let options = DiffOptions {
+            interhunk_lines: 10,
+            ..DiffOptions::default()
+        };
+        let diff = &parse(spread.as_bytes(), options)[0].diff;
+        assert_eq!(headers(diff), vec!["@@ -1,12 +1,12 @@ int main()"]);
+
+        let options = DiffOptions {
+            context: 1,
+            ..DiffOptions::default()
+        };
+        let diff = &parse(spread.as_bytes(), options)[0].diff;
+        assert_eq!(
+            headers(diff),
+            vec!["@@ -1,3 +1,3 @@ int main()", "@@ -9,3 +9,3 @@"]
+        );
+
+        let diff = &parse(
+            b"==== //depot/a.c#4 - /ws/a.c ====\n@@ -3 +3,2 @@ fn main()\n-a\n+b\n+c\n",
+            DiffOptions::default(),
+        )[0]
+        .diff;
+        assert_eq!(headers(diff), vec!["@@ -3 +3,2 @@ fn main()"]);
+        assert_eq!(
+            diff.hunks[0].header_hash,
+            hash(&HunkHeader::parse("@@ -3,1 +3,2 @@").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_unified_file_headers() {
+        let files = parse(
+            b"--- //depot/a.c\t2023/07/22 10:00:00\n+++ /ws/a.c\t2023/07/22 10:05:00\n\
+              @@ -1 +1 @@\n-a\n+b\n\
+              --- //depot/b.c#2\t2023/07/22 10:00:00\n+++ /ws/b.c\t2023/07/22 10:05:00\n\
+              @@ -1,2 +1 @@\n--- x\n a\n",
+            DiffOptions::default(),
+        );
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].depot_path, "//depot/a.c");
+        assert_eq!(files[0].local_path, Some("/ws/a.c".into()));
+        assert_eq!(headers(&files[0].diff), vec!["@@ -1 +1 @@"]);
+
+        assert_eq!(files[1].depot_path, "//depot/b.c");
+        assert_eq!(files[1].local_path, Some("/ws/b.c".into()));
+        // a deleted line starting with `--` is content, not a header
+        assert_eq!(files[1].diff.hunks[0].lines[1].content.as_ref(), "-- x");
+        assert_eq!(files[1].diff.lines, 3);
+    }
+
+    #[test]
+    fn test_non_utf8_paths() {
+        let files = parse(
+            b"==== //depot/caf\xe9.c#2 (text) ====\n\n@@ -1 +1 @@\n-a\n+b\n\n\
+              --- //depot/na\xefve.c\t2023/07/22 10:00:00\n\
+              +++ /ws/na\xefve.c\t2023/07/22 10:00:00\n@@ -1 +1 @@\n-c\n+d\n",
+            DiffOptions::default(),
+        );
+
+        assert_eq!(files[0].depot_path.as_bytes(), b"//depot/caf\xe9.c");
+        assert_eq!(files[0].revision, Some(2));
+        assert_eq!(files[1].depot_path.as_bytes(), b"//depot/na\xefve.c");
+        assert_eq!(
+            files[1].local_path.as_ref().map(P4Path::as_bytes),
+            Some(b"/ws/na\xefve.c".as_slice())
+        );
+        assert_eq!(files[1].diff.hunks[0].lines[2].content.as_ref(), "d");
+    }
+
+    #[test]
+    fn test_banner_and_unified_headers() {
+        let files = parse(
+            b"==== //depot/a.c#4 (text) ====\n\
+              --- //depot/a.c\t2023/07/22 10:00:00\n+++ //depot/a.c\t2023/07/22 10:05:00\n\
+              @@ -1 +1 @@\n-a\n+b\n\
+              ==== //depot/b.c#1 (text) ====\n\
+              --- //depot/b.c\t2023/07/22 10:00:00\n+++ //depot/b.c\t2023/07/22 10:05:00\n\
+              @@ -0,0 +1 @@\n+new\n",
+            DiffOptions::default(),
+        );
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].depot_path, "//depot/a.c");
+        assert_eq!(files[0].local_path, None);
+        assert_eq!(files[0].diff.lines, 3);
+        assert_eq!(files[1].depot_path, "//depot/b.c");
+        assert!(files[1].diff.untracked);
+    }
+
+    #[test]
+    fn test_no_newline() {
+        let diff = &parse(
+            b"==== //depot/a.c#1 - /ws/a.c ====\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+a\n",
+            DiffOptions::default(),
+        )[0]
+        .diff;
+
+        let lines = &diff.hunks[0].lines;
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2].content.as_ref(), NO_NEWLINE);
+        assert_eq!(lines[3].line_type, DiffLineType::Add);
+        assert_eq!(diff.size_delta, 1);
+    }
+
+    #[test]
+    fn test_carriage_return() {
+        let diff = &parse(
+            b"==== //depot/a.c#1 - /ws/a.c ====\r\n@@ -1 +1 @@\n-a\n+a\r\n",
+            DiffOptions::default(),
+        )[0]
+        .diff;
+
+        assert_eq!(diff.hunks[0].lines[1].content.as_ref(), "a");
+        assert_eq!(diff.hunks[0].lines[2].content.as_ref(), "a\r");
+        assert_eq!(diff.size_delta, 1);
+    }
+
+    #[test]
+    fn test_describe() {
+        let runner = FixtureRunner::default()
+            .with(
+                &[
+                    "fstat",
+                    "-Ol",
+                    "-T",
+                    "depotFile,fileSize",
+                    "//depot/a.gif@=120",
+                    "//depot/new.c@=120",
+                ],
+                P4Output::success(
+                    "... depotFile //depot/a.gif\n... fileSize 7\n\n\
+                     ... depotFile //depot/new.c\n... fileSize 9\n",
+                ),
+            )
+            .with(
+                &[
+                    "fstat",
+                    "-Ol",
+                    "-T",
+                    "depotFile,fileSize",
+                    "//depot/a.gif#1",
+                ],
+                P4Output::success("... depotFile //depot/a.gif\n... fileSize 5\n"),
+            )
+            .with(
+                &["describe", "-du3", "120"],
+                P4Output::success(
+                    "Change 120 by bob@ws on 2023/07/22 10:00:00\n\n\tFix it\n\n\
+                 Affected files ...\n\n... //depot/a.gif#2 edit\n... //depot/new.c#1 add\n\n\
+                 Differences ...\n\n\
+                 ==== //depot/a.gif#2 (binary+l) ====\n\n\
+                 ==== //depot/new.c#1 (text) ====\n\n\
+                 @@ -0,0 +1,2 @@\n+int\n+main\n\n",
+                ),
+            );
+
+        let files = get_changelist_diff(&runner, 120, false, DiffOptions::default()).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "//depot/a.gif");
+        assert!(files[0].1.binary);
+        assert!(files[0].1.hunks.is_empty());
+        assert_eq!(files[0].1.sizes, (5, 7));
+        assert_eq!(files[0].1.size_delta, 2);
+
+        assert_eq!(files[1].0, "//depot/new.c");
+        assert!(files[1].1.untracked && !files[1].1.binary);
+        assert_eq!(headers(&files[1].1), vec!["@@ -0,0 +1,2 @@"]);
+        assert_eq!(files[1].1.hunks[0].lines[2].position.new_lineno, Some(2));
+        assert_eq!(files[1].1.sizes, (0, 9));
+    }
+
+    #[test]
+    fn test_shelved_too_large() {
+        let runner = FixtureRunner::default()
+            .with(
+                &["describe", "-du3", "-S", "12"],
+                P4Output::success(
+                    "Change 12 by bob@ws on 2023/07/22 10:00:00 *pending*\n\n\tWip\n\n\
+                     Shelved files ...\n\n... //depot/a.c#4 edit\n\n\
+                     Differences ...\n\n\
+                     ==== //depot/a.c#4 (text) ====\n\n@@ -1 +1 @@\n-a\n+b\n\n",
+                ),
+            )
+            .with(
+                &[
+                    "fstat",
+                    "-Ol",
+                    "-T",
+                    "depotFile,fileSize",
+                    "//depot/a.c@=12",
+                ],
+                P4Output::success("... depotFile //depot/a.c\n... fileSize 2\n"),
+            )
+            .with(
+                &["fstat", "-Ol", "-T", "depotFile,fileSize", "//depot/a.c#4"],
+                P4Output::success("... depotFile //depot/a.c\n... fileSize 2048\n"),
+            );
+        let options = DiffOptions {
+            max_size: 1024,
+            ..DiffOptions::default()
+        };
+
+        let files = get_changelist_diff(&runner, 12, true, options).unwrap();
+
+        assert!(files[0].1.too_large);
+        assert!(files[0].1.hunks.is_empty());
+        assert_eq!(files[0].1.sizes, (2048, 2));
+    }
+
+    /// `p4 fstat` output for the opened file `//depot/a.c`.
+    fn with_opened(runner: FixtureRunner, have_size: u64, local: &str) -> FixtureRunner {
+        runner
+            .with(
+                &["fstat", "-Ol", "-T", "fileSize", "//depot/a.c#have"],
+                P4Output::success(format!("... fileSize {have_size}\n")),
+            )
+            .with(
+                &["fstat", "-T", "clientFile", "//depot/a.c"],
+                P4Output::success(format!("... clientFile {local}\n")),
+            )
+    }
+
+    #[test]
+    fn test_diff_options_args() {
+        let runner = with_opened(FixtureRunner::default(), 0, "/nonexistent/a.c").with(
+            &["diff", "-du5", "-dw", "//depot/a.c"],
+            P4Output::success("==== //depot/a.c#4 - /nonexistent/a.c ====\n"),
+        );
+        let options = DiffOptions {
+            whitespace: DiffWhitespace::IgnoreAll,
+            context: 2,
+            interhunk_lines: 3,
+            ..DiffOptions::default()
+        };
+
+        let diff = get_diff(&runner, &"//depot/a.c".into(), options).unwrap();
+
+        assert!(diff.hunks.is_empty());
+        assert_eq!(diff.sizes, (0, 0));
+    }
+
+    #[test]
+    fn test_binary_sizes() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let local = file.path().to_str().unwrap();
+        fs::write(local, [0_u8; 4]).unwrap();
+
+        let runner = FixtureRunner::default()
+            .with(
+                &["diff", "-du3", "//depot/a.png"],
+                P4Output::success(format!("==== //depot/a.png#3 - {local} ==== (binary)\n")),
+            )
+            .with(
+                &["fstat", "-Ol", "-T", "fileSize", "//depot/a.png#have"],
+                P4Output::success("... fileSize 10\n"),
+            )
+            .with(
+                &["fstat", "-T", "clientFile", "//depot/a.png"],
+                P4Output::success(format!("... clientFile {local}\n")),
+            );
+
+        let diff = get_diff(&runner, &"//depot/a.png".into(), DiffOptions::default()).unwrap();
+
+        assert!(diff.binary);
+        assert_eq!(diff.sizes, (10, 4));
+        assert_eq!(diff.size_delta, -6);
+    }
+
+    #[test]
+    fn test_too_large() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let local = file.path().to_str().unwrap();
+        fs::write(local, "a\nb\n").unwrap();
+
+        let runner = with_opened(FixtureRunner::default(), 2, local);
+        let options = DiffOptions {
+            max_size: 3,
+            ..DiffOptions::default()
+        };
+
+        let diff = get_diff(&runner, &"//depot/a.c".into(), options).unwrap();
+
+        assert!(diff.too_large);
+        assert!(diff.hunks.is_empty());
+        assert_eq!(diff.sizes, (2, 4));
+        // never diffed
+        assert!(runner.calls().iter().all(|c| c.name() != "diff"));
+    }
+
+    #[test]
+    fn test_not_opened() {
+        let runner = FixtureRunner::default()
+            .with(
+                &["fstat", "-Ol", "-T", "fileSize", "//depot/a.c#have"],
+                P4Output::failure("//depot/a.c#have - no such file(s).\n"),
+            )
+            .with(
+                &["fstat", "-T", "clientFile", "//depot/a.c"],
+                P4Output::failure("//depot/a.c - no such file(s).\n"),
+            )
+            .with(
+                &["diff", "-du3", "//depot/a.c"],
+                P4Output::failure("//depot/a.c - file(s) not opened on this client.\n"),
+            );
+
+        let diff = get_diff(&runner, &"//depot/a.c".into(), DiffOptions::default()).unwrap();
+
+        assert_eq!(diff.lines, 0);
+    }
 }
@@ -1,7 +1,9 @@
-use crate::{Error, Result};
-use regex::Regex;
-use std::{path::PathBuf, process::Command, str};
+use std::{convert::Infallible, fmt, path::PathBuf, str::FromStr};
 
+use super::{command::P4Command, path::P4Path, record::Record, runner::P4Runner};
+use crate::{Result, Severity};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum FileAction {
     Add,
     Edit,
@@ -13,9 +15,51 @@ pub enum FileAction {
     Import,
     Purge,
     Archive,
+    /// an action this version doesn't know about, e.g. from a newer server
+    Unknown(String),
+}
+
+impl FromStr for FileAction {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Infallible> {
+        Ok(match s {
+            "add" => Self::Add,
+            "edit" => Self::Edit,
+            "delete" => Self::Delete,
+            "branch" => Self::Branch,
+            "move/add" => Self::MoveAdd,
+            "move/delete" => Self::MoveDelete,
+            "integrate" => Self::Integrate,
+            "import" => Self::Import,
+            "purge" => Self::Purge,
+            "archive" => Self::Archive,
+            _ => Self::Unknown(s.to_string()),
+        })
+    }
 }
 
-pub enum FileType {
+impl fmt::Display for FileAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Add => "add",
+            Self::Edit => "edit",
+            Self::Delete => "delete",
+            Self::Branch => "branch",
+            Self::MoveAdd => "move/add",
+            Self::MoveDelete => "move/delete",
+            Self::Integrate => "integrate",
+            Self::Import => "import",
+            Self::Purge => "purge",
+            Self::Archive => "archive",
+            Self::Unknown(s) => s,
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileBaseType {
     Text,
     Binary,
     Symlink,
@@ -24,50 +68,388 @@ pub enum FileType {
     UTF16,
     Apple,
     Resource,
+    Unknown(String),
+}
+
+impl FileBaseType {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Text => "text",
+            Self::Binary => "binary",
+            Self::Symlink => "symlink",
+            Self::Unicode => "unicode",
+            Self::UTF8 => "utf8",
+            Self::UTF16 => "utf16",
+            Self::Apple => "apple",
+            Self::Resource => "resource",
+            Self::Unknown(s) => s,
+        }
+    }
 }
 
+/// A perforce filetype such as `text+kx`. Storage modifiers (`+C`, `+D`,
+/// `+F`, `+S`) are accepted but not kept.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileType {
+    pub base: FileBaseType,
+    /// `+x`
+    pub executable: bool,
+    /// `+l`
+    pub exclusive: bool,
+    /// `+k` or `+ko`
+    pub keywords: bool,
+    /// `+w`
+    pub writable: bool,
+    /// `+m`
+    pub modtime: bool,
+}
+
+impl FileType {
+    pub const fn new(base: FileBaseType) -> Self {
+        Self {
+            base,
+            executable: false,
+            exclusive: false,
+            keywords: false,
+            writable: false,
+            modtime: false,
+        }
+    }
+
+    fn apply_modifiers(mut self, modifiers: &str) -> Self {
+        for m in modifiers.chars() {
+            match m {
+                'x' => self.executable = true,
+                'l' => self.exclusive = true,
+                'k' => self.keywords = true,
+                'w' => self.writable = true,
+                'm' => self.modtime = true,
+                _ => {}
+            }
+        }
+        self
+    }
+}
+
+impl FromStr for FileType {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Infallible> {
+        let (base, modifiers) = s.split_once('+').unwrap_or((s, ""));
+
+        // pre-2000.1 names that fold modifiers into the base type
+        let (base, implied) = match base {
+            "text" => (FileBaseType::Text, ""),
+            "binary" => (FileBaseType::Binary, ""),
+            "symlink" => (FileBaseType::Symlink, ""),
+            "unicode" => (FileBaseType::Unicode, ""),
+            "utf8" => (FileBaseType::UTF8, ""),
+            "utf16" => (FileBaseType::UTF16, ""),
+            "apple" => (FileBaseType::Apple, ""),
+            "resource" | "uresource" => (FileBaseType::Resource, ""),
+            "ctext" | "ltext" => (FileBaseType::Text, ""),
+            "xtext" | "cxtext" | "xltext" => (FileBaseType::Text, "x"),
+            "ktext" => (FileBaseType::Text, "k"),
+            "kxtext" => (FileBaseType::Text, "kx"),
+            "ubinary" => (FileBaseType::Binary, ""),
+            "tempobj" => (FileBaseType::Binary, "w"),
+            "xbinary" | "uxbinary" => (FileBaseType::Binary, "x"),
+            "xtempobj" => (FileBaseType::Binary, "wx"),
+            "xunicode" => (FileBaseType::Unicode, "x"),
+            "xutf16" => (FileBaseType::UTF16, "x"),
+            _ => (FileBaseType::Unknown(base.to_string()), ""),
+        };
+
+        Ok(Self::new(base)
+            .apply_modifiers(implied)
+            .apply_modifiers(modifiers))
+    }
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.keywords, 'k'),
+            (self.exclusive, 'l'),
+            (self.modtime, 'm'),
+            (self.writable, 'w'),
+            (self.executable, 'x'),
+        ]
+        .iter()
+        .filter_map(|(set, m)| set.then_some(*m))
+        .collect::<String>();
+
+        if modifiers.is_empty() {
+            write!(f, "{}", self.base.as_str())
+        } else {
+            write!(f, "{}+{}", self.base.as_str(), modifiers)
+        }
+    }
+}
+
+/// A file as reported by `p4 fstat`. Head fields are missing for files that
+/// are only opened for add, open fields for files that are not opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct File {
-    exists_in_depot: bool,
-    depot_path: PathBuf,
-    local_path: PathBuf,
-    head_action: FileAction,
-    head_change: u32,
-    head_revision: u32,
-    head_filetype: FileType,
-    have_revision: u32,
-    work_revision: u32,
-    action: FileAction,
-    changelist: u32,
-    filetype: FileType,
+    pub exists_in_depot: bool,
+    pub depot_path: P4Path,
+    pub local_path: Option<PathBuf>,
+    pub head_action: Option<FileAction>,
+    pub head_change: Option<u32>,
+    pub head_revision: Option<u32>,
+    pub head_filetype: Option<FileType>,
+    pub have_revision: Option<u32>,
+    pub work_revision: Option<u32>,
+    pub action: Option<FileAction>,
+    pub changelist: Option<u32>,
+    pub filetype: Option<FileType>,
 }
 
 impl File {
-    // pub fn new() -> Result<Self> {
-    //
-    // }
+    pub fn from_record(record: &Record) -> Result<Self> {
+        let head_revision = record.parse_opt("headRev")?;
+
+        Ok(Self {
+            exists_in_depot: head_revision.is_some(),
+            depot_path: P4Path::new(record.require_bytes("depotFile")?),
+            local_path: record
+                .get_bytes("clientFile")
+                .map(|path| P4Path::new(path).to_path_buf()),
+            head_action: record.parse_opt("headAction")?,
+            head_change: record.parse_opt("headChange")?,
+            head_revision,
+            head_filetype: record.parse_opt("headType")?,
+            have_revision: record.parse_opt("haveRev")?,
+            work_revision: record.parse_opt("workRev")?,
+            action: record.parse_opt("action")?,
+            changelist: match record.get("change") {
+                Some("default") => Some(0),
+                _ => record.parse_opt("change")?,
+            },
+            filetype: record.parse_opt("type")?,
+        })
+    }
+
+    /// A file listed by `p4 describe`, its head fields holding the revision
+    /// in the described changelist.
+    pub(crate) fn from_describe_record(record: &Record, changelist: u32) -> Result<Self> {
+        Ok(Self {
+            exists_in_depot: true,
+            depot_path: P4Path::new(record.require_bytes("depotFile")?),
+            local_path: None,
+            head_action: record.parse_opt("action")?,
+            head_change: Some(changelist),
+            head_revision: record.parse_opt("rev")?,
+            head_filetype: record.parse_opt("type")?,
+            have_revision: None,
+            work_revision: None,
+            action: None,
+            changelist: None,
+            filetype: None,
+        })
+    }
+
+    /// The pending action if the file is opened, the head action otherwise.
+    pub fn current_action(&self) -> Option<&FileAction> {
+        self.action.as_ref().or(self.head_action.as_ref())
+    }
+
+    pub fn current_filetype(&self) -> Option<&FileType> {
+        self.filetype.as_ref().or(self.head_filetype.as_ref())
+    }
 }
 
-pub fn get_files(changelist: u32) -> Result<Vec<String>> {
-    let mut cmd = Command::new("p4");
-    cmd.args(["describe", changelist.to_string().as_str()]);
-
-    let output = cmd.output().map_err(|e| Error::from(e))?;
-
-    let mut result = Vec::new();
-    for line in str::from_utf8(output.stdout.as_slice())
-        .unwrap()
-        // FIXME: replace unwrap with map err
-        .split('\n')
-        .filter(|l| !l.is_empty())
-    {
-        // FIXME: replace unwrap with map err
-        let re = Regex::new(r"\.\.\.s+(\S+)#([0-9]+)\s+(\S+)").unwrap();
-
-        if let Some(caps) = re.captures(line) {
-            let file_line = String::from(&caps[0]);
-            result.push(file_line);
-        }
+/// Which files [`get_files`] should report. Changelist `0` is the default
+/// changelist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileSpec {
+    Opened(u32),
+    Shelved(u32),
+    Submitted(u32),
+    Path(P4Path),
+    Files(Vec<P4Path>),
+}
+
+fn changelist_arg(changelist: u32) -> String {
+    if changelist == 0 {
+        "default".to_string()
+    } else {
+        changelist.to_string()
+    }
+}
+
+pub fn get_files(runner: &dyn P4Runner, spec: &FileSpec) -> Result<Vec<File>> {
+    let cmd = P4Command::new("fstat");
+    let cmd = match spec {
+        FileSpec::Opened(cl) => cmd
+            .args(["-Ro", "-e"])
+            .arg(changelist_arg(*cl))
+            .arg("//..."),
+        FileSpec::Shelved(cl) => cmd.args(["-Rs", "-e"]).arg(cl.to_string()).arg("//..."),
+        FileSpec::Submitted(cl) => cmd
+            .arg("-e")
+            .arg(cl.to_string())
+            .arg(format!("//...@={cl}")),
+        FileSpec::Path(path) => cmd.arg(path),
+        FileSpec::Files(files) => cmd.args(files),
+    };
+
+    match cmd.run(runner) {
+        Ok(records) => records.iter().map(File::from_record).collect(),
+        // warnings such as "file(s) not opened on this client" only mean
+        // that nothing matched
+        Err(e) if e.message().is_some_and(|m| m.severity <= Severity::Warning) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sync::{FixtureRunner, P4Output},
+        Error,
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_filetype() {
+        let t: FileType = "text+kx".parse().unwrap();
+        assert_eq!(t.base, FileBaseType::Text);
+        assert!(t.keywords && t.executable && !t.exclusive);
+
+        let t: FileType = "binary+FSl".parse().unwrap();
+        assert_eq!(t.to_string(), "binary+l");
+
+        let t: FileType = "ktext".parse().unwrap();
+        assert_eq!(t.to_string(), "text+k");
+
+        let t: FileType = "binary+S10w".parse().unwrap();
+        assert_eq!(t.to_string(), "binary+w");
+
+        let t: FileType = "nonsense+x".parse().unwrap();
+        assert_eq!(t.base, FileBaseType::Unknown("nonsense".to_string()));
+        assert_eq!(t.to_string(), "nonsense+x");
+    }
+
+    #[test]
+    fn test_action() {
+        assert_eq!(
+            "move/add".parse::<FileAction>().unwrap(),
+            FileAction::MoveAdd
+        );
+        assert_eq!(FileAction::MoveDelete.to_string(), "move/delete");
+        assert_eq!(
+            "frobnicate".parse::<FileAction>().unwrap(),
+            FileAction::Unknown("frobnicate".to_string())
+        );
+    }
+
+    #[test]
+    fn test_opened_files() {
+        let runner = FixtureRunner::default().with(
+            &["fstat", "-Ro", "-e", "default", "//..."],
+            P4Output::success(
+                "... depotFile //depot/main/a.c\n... clientFile /ws/main/a.c\n... headAction edit\n... headType text\n... headTime 1690000000\n... headRev 4\n... headChange 120\n... haveRev 4\n... action edit\n... change default\n... type text+x\n... actionOwner bob\n... workRev 4\n\n\
+                 ... depotFile //depot/main/new file.txt\n... clientFile /ws/main/new file.txt\n... action add\n... change default\n... type text\n... actionOwner bob\n... workRev 1\n",
+            ),
+        );
+
+        let files = get_files(&runner, &FileSpec::Opened(0)).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].depot_path, "//depot/main/a.c");
+        assert_eq!(files[0].local_path, Some(PathBuf::from("/ws/main/a.c")));
+        assert_eq!(files[0].head_revision, Some(4));
+        assert_eq!(files[0].changelist, Some(0));
+        assert_eq!(files[0].filetype.as_ref().unwrap().to_string(), "text+x");
+        assert!(files[0].exists_in_depot);
+
+        assert_eq!(files[1].depot_path, "//depot/main/new file.txt");
+        assert_eq!(files[1].current_action(), Some(&FileAction::Add));
+        assert_eq!(files[1].head_revision, None);
+        assert!(!files[1].exists_in_depot);
+    }
+
+    #[test]
+    fn test_unknown_action_and_type() {
+        let runner = FixtureRunner::default().with(
+            &["fstat", "-Ro", "-e", "default", "//..."],
+            P4Output::success(
+                "... depotFile //depot/a.c\n... action edit\n... change default\n... type text\n\n\
+                 ... depotFile //depot/b.c\n... action relocate\n... change default\n... type hologram+x\n",
+            ),
+        );
+
+        let files = get_files(&runner, &FileSpec::Opened(0)).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(
+            files[1].current_action(),
+            Some(&FileAction::Unknown("relocate".to_string()))
+        );
+        assert_eq!(
+            files[1].current_filetype().unwrap().to_string(),
+            "hologram+x"
+        );
     }
 
-    Ok(result)
+    #[test]
+    fn test_empty_changelist() {
+        let runner = FixtureRunner::default().with(
+            &["fstat", "-Ro", "-e", "12", "//..."],
+            P4Output::failure("//... - file(s) not opened on this client.\n"),
+        );
+
+        assert_eq!(get_files(&runner, &FileSpec::Opened(12)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_failure_is_reported() {
+        let runner = FixtureRunner::default().with(
+            &["fstat", "//depot/..."],
+            P4Output::failure("You don't have permission for this operation.\n"),
+        );
+
+        let err = get_files(&runner, &FileSpec::Path("//depot/...".into())).unwrap_err();
+
+        assert!(matches!(err, Error::PermissionDenied(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path() {
+        use crate::sync::OutputFormat;
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let output = super::super::marshal::encode(&[vec![
+            ("code", b"stat"),
+            ("depotFile", b"//depot/\xff.bin"),
+            ("clientFile", b"/ws/\xff.bin"),
+        ]]);
+        let mut runner = FixtureRunner::new(OutputFormat::Marshal).with(
+            &["fstat", "-Ro", "-e", "default", "//..."],
+            P4Output::success(output.clone()),
+        );
+        runner.add(
+            vec![OsStr::new("fstat"), OsStr::from_bytes(b"//depot/\xff.bin")],
+            P4Output::success(output),
+        );
+
+        let files = get_files(&runner, &FileSpec::Opened(0)).unwrap();
+        assert_eq!(files[0].depot_path.as_bytes(), b"//depot/\xff.bin");
+        assert_eq!(
+            files[0].local_path.as_ref().unwrap().as_os_str().as_bytes(),
+            b"/ws/\xff.bin"
+        );
+
+        // handed back to p4 byte for byte
+        let files = get_files(&runner, &FileSpec::Path(files[0].depot_path.clone())).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].depot_path.as_bytes(), b"//depot/\xff.bin");
+        assert_eq!(
+            runner.calls()[1].get_args()[1].as_bytes(),
+            b"//depot/\xff.bin"
+        );
+    }
 }
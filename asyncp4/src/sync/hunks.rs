@@ -0,0 +1,275 @@
+use std::fs;
+
+use super::{
+    diff::{get_parsed_diff, DiffLineType, DiffOptions, Hunk, HunkHeader, NO_NEWLINE},
+    path::P4Path,
+    runner::P4Runner,
+};
+use crate::{Error, Result};
+
+/// Drops the changes of `hunk`, as shown with any [`DiffOptions`], from the
+/// opened file `path` by rewriting its local copy, leaving its other changes
+/// in place. Perforce has no partial revert, so this works on the workspace
+/// file only.
+pub fn discard_hunk(runner: &dyn P4Runner, path: &P4Path, hunk: &Hunk) -> Result<()> {
+    // whitespace options and context change what a hunk looks like, so the
+    // file is diffed exactly and hunk by hunk to find the changes shown
+    let options = DiffOptions {
+        context: 0,
+        ..DiffOptions::default()
+    };
+    let file = get_parsed_diff(runner, path, options)?
+        .ok_or_else(|| Error::Generic(format!("no changes to discard in {path}")))?;
+
+    let local_path = file
+        .local_path
+        .ok_or_else(|| Error::Generic(format!("{path} has no local file")))?
+        .to_path_buf();
+
+    if file.diff.binary {
+        return Err(Error::Generic(format!(
+            "cannot discard hunks of binary file {path}"
+        )));
+    }
+
+    let selected = changes(hunk);
+    let exact = file
+        .diff
+        .hunks
+        .iter()
+        .filter(|h| changes(h).iter().all(|c| selected.contains(c)))
+        .collect::<Vec<_>>();
+
+    let found = exact.iter().map(|h| changes(h).len()).sum::<usize>();
+    if selected.is_empty() || found != selected.len() {
+        return Err(Error::Generic(format!(
+            "{path} changed or the hunk hides whitespace changes, hunk not found"
+        )));
+    }
+
+    // from the bottom so the line numbers of the hunks above stay valid
+    let mut content = String::from_utf8(fs::read(&local_path)?)?;
+    for hunk in exact.iter().rev() {
+        content = revert_hunk(&content, hunk)?;
+    }
+    fs::write(&local_path, content)?;
+
+    Ok(())
+}
+
+/// The added and deleted lines of `hunk` with their line numbers and
+/// whether they lack a final newline.
+fn changes(hunk: &Hunk) -> Vec<(DiffLineType, Option<u32>, &str, bool)> {
+    hunk.lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let lineno = match line.line_type {
+                DiffLineType::Add => line.position.new_lineno,
+                DiffLineType::Delete => line.position.old_lineno,
+                _ => return None,
+            };
+            let no_newline = hunk
+                .lines
+                .get(i + 1)
+                .is_some_and(|l| l.content.as_ref() == NO_NEWLINE);
+
+            Some((line.line_type, lineno, line.content.as_ref(), no_newline))
+        })
+        .collect()
+}
+
+/// `content` with the new side of `hunk` replaced by its old side.
+fn revert_hunk(content: &str, hunk: &Hunk) -> Result<String> {
+    let header = hunk
+        .lines
+        .first()
+        .and_then(|l| HunkHeader::parse(&l.content))
+        .ok_or_else(|| Error::Parse("missing hunk header".to_string()))?;
+
+    let lines = content.split_inclusive('\n').collect::<Vec<_>>();
+
+    // a header `+n,0` names the line after which lines were deleted
+    let start = (header.new_start - u32::from(header.new_lines > 0)) as usize;
+    let end = start + header.new_lines as usize;
+    if end > lines.len() {
+        return Err(Error::Generic(
+            "local file changed since the diff was loaded".to_string(),
+        ));
+    }
+
+    let mut old_side = String::new();
+    let mut new_index = start;
+
+    for (i, line) in hunk.lines.iter().enumerate().skip(1) {
+        if line.line_type == DiffLineType::None && line.content.as_ref() == NO_NEWLINE {
+            continue;
+        }
+
+        let no_newline = hunk
+            .lines
+            .get(i + 1)
+            .is_some_and(|l| l.content.as_ref() == NO_NEWLINE);
+
+        if line.position.new_lineno.is_some() {
+            let local = lines
+                .get(new_index)
+                .map(|l| l.strip_suffix('\n').unwrap_or(l));
+            if local != Some(line.content.as_ref()) {
+                return Err(Error::Generic(
+                    "local file changed since the diff was loaded".to_string(),
+                ));
+            }
+            new_index += 1;
+        }
+
+        if line.position.old_lineno.is_some() {
+            old_side.push_str(&line.content);
+            if !no_newline {
+                old_side.push('\n');
+            }
+        }
+    }
+
+    if new_index != end {
+        return Err(Error::Parse(format!(
+            "hunk {header} does not match its lines"
+        )));
+    }
+
+    Ok([
+        &lines[..start].concat(),
+        old_side.as_str(),
+        &lines[end..].concat(),
+    ]
+    .concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{get_diff, DiffWhitespace, FixtureRunner, P4Output};
+    use pretty_assertions::assert_eq;
+    use tempfile::NamedTempFile;
+
+    /// Discards hunk `index` of the diff `shown`, loaded with `options` and
+    /// the matching `flags`, given the exact diff `exact`. Returns the local
+    /// file afterwards.
+    fn discard(
+        before: &str,
+        (options, flags): (DiffOptions, &[&str]),
+        shown: &str,
+        exact: &str,
+        index: usize,
+    ) -> (Result<()>, String) {
+        let file = NamedTempFile::new().unwrap();
+        let local = file.path().to_str().unwrap();
+        fs::write(local, before).unwrap();
+
+        let header = format!("==== //depot/a.c#1 - {local} ====\n");
+        let runner = FixtureRunner::default()
+            .with(
+                &["fstat", "-Ol", "-T", "fileSize", "//depot/a.c#have"],
+                P4Output::success(format!("... fileSize {}\n", before.len())),
+            )
+            .with(
+                &["fstat", "-T", "clientFile", "//depot/a.c"],
+                P4Output::success(format!("... clientFile {local}\n")),
+            )
+            .with(
+                &[&["diff"], flags, &["//depot/a.c"]].concat(),
+                P4Output::success(format!("{header}{shown}")),
+            )
+            .with(
+                &["diff", "-du0", "//depot/a.c"],
+                P4Output::success(format!("{header}{exact}")),
+            );
+
+        let path = "//depot/a.c".into();
+        let diff = get_diff(&runner, &path, options).unwrap();
+        let result = discard_hunk(&runner, &path, &diff.hunks[index]);
+
+        (result, fs::read_to_string(local).unwrap())
+    }
+
+    #[test]
+    fn test_discard_hunk() {
+        let (result, after) = discard(
+            "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nx\n",
+            (DiffOptions::default(), &["-du3"]),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -9,3 +9,4 @@\n i\n j\n k\n+x\n",
+            "@@ -2 +2 @@\n-b\n+B\n@@ -11,0 +12 @@\n+x\n",
+            1,
+        );
+
+        result.unwrap();
+        assert_eq!(after, "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n");
+    }
+
+    #[test]
+    fn test_discard_deletion_and_no_newline() {
+        let (result, after) = discard(
+            "a\nc\nd",
+            (DiffOptions::default(), &["-du3"]),
+            "@@ -1,3 +1,3 @@\n a\n-b\n c\n+d\n\\ No newline at end of file\n",
+            "@@ -2 +1,0 @@\n-b\n@@ -3,0 +3 @@\n+d\n\\ No newline at end of file\n",
+            0,
+        );
+
+        result.unwrap();
+        assert_eq!(after, "a\nb\nc\n");
+    }
+
+    #[test]
+    fn test_wide_context() {
+        let options = DiffOptions {
+            context: 5,
+            ..DiffOptions::default()
+        };
+
+        let (result, after) = discard(
+            "a\nB\nc\nD\ne\n",
+            (options, &["-du5"]),
+            "@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n-d\n+D\n e\n",
+            "@@ -2 +2 @@\n-b\n+B\n@@ -4 +4 @@\n-d\n+D\n",
+            0,
+        );
+
+        result.unwrap();
+        assert_eq!(after, "a\nb\nc\nd\ne\n");
+    }
+
+    #[test]
+    fn test_ignored_whitespace() {
+        let options = DiffOptions {
+            whitespace: DiffWhitespace::IgnoreAll,
+            ..DiffOptions::default()
+        };
+
+        // the shown hunk hides that `b` was indented
+        let (result, after) = discard(
+            "a\n  b\nC\n",
+            (options, &["-du3", "-dw"]),
+            "@@ -1,3 +1,3 @@\n a\n   b\n-c\n+C\n",
+            "@@ -2,2 +2,2 @@\n-b\n-c\n+  b\n+C\n",
+            0,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(after, "a\n  b\nC\n");
+    }
+
+    #[test]
+    fn test_local_file_changed() {
+        let (result, after) = discard(
+            "a\nchanged\nc\n",
+            (DiffOptions::default(), &["-du3"]),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n",
+            "@@ -2 +2 @@\n-b\n+changed\n",
+            0,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(after, "a\nchanged\nc\n");
+    }
+}
@@ -0,0 +1,40 @@
+use super::{command::P4Command, runner::P4Runner};
+use crate::Result;
+
+/// Runs `p4 login`, handing the password over on stdin.
+pub fn login(runner: &dyn P4Runner, password: &str) -> Result<()> {
+    P4Command::new("login")
+        .stdin(format!("{password}\n"))
+        .run(runner)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sync::{FixtureRunner, P4Output},
+        Error,
+    };
+
+    #[test]
+    fn test_login() {
+        let runner = FixtureRunner::default().with(
+            &["login"],
+            P4Output::success("Enter password: \nUser bob logged in.\n"),
+        );
+
+        login(&runner, "secret").unwrap();
+
+        assert_eq!(runner.calls()[0].get_stdin(), Some(b"secret\n".as_slice()));
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let runner =
+            FixtureRunner::default().with(&["login"], P4Output::failure("Password invalid.\n"));
+
+        assert!(matches!(login(&runner, "nope"), Err(Error::P4(_))));
+    }
+}
@@ -0,0 +1,194 @@
+// `p4 -G` writes a stream of python marshal dictionaries, one per record.
+// Only the subset of the format that p4 emits is supported: flat dicts with
+// string or integer keys and values.
+
+use super::record::Record;
+use crate::{Error, Result};
+
+const TYPE_NULL: u8 = b'0';
+const TYPE_NONE: u8 = b'N';
+const TYPE_INT: u8 = b'i';
+const TYPE_DICT: u8 = b'{';
+const TYPE_STRING: u8 = b's';
+const TYPE_UNICODE: u8 = b'u';
+const TYPE_INTERNED: u8 = b't';
+const TYPE_ASCII: u8 = b'a';
+const TYPE_ASCII_INTERNED: u8 = b'A';
+const TYPE_SHORT_ASCII: u8 = b'z';
+const TYPE_SHORT_ASCII_INTERNED: u8 = b'Z';
+const FLAG_REF: u8 = 0x80;
+
+pub fn parse(output: &[u8]) -> Result<Vec<Record>> {
+    let mut reader = Reader {
+        data: output,
+        pos: 0,
+    };
+    let mut records = Vec::new();
+
+    while !reader.at_end() {
+        match reader.read_type()? {
+            TYPE_DICT => records.push(reader.read_dict()?),
+            t => {
+                return Err(Error::Parse(format!(
+                    "unexpected marshal type `{}` at offset {}",
+                    t as char,
+                    reader.pos - 1
+                )))
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| Error::Parse("truncated marshal output".to_string()))?;
+
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_type(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0] & !FLAG_REF)
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_len(&mut self, short: bool) -> Result<usize> {
+        if short {
+            Ok(usize::from(self.take(1)?[0]))
+        } else {
+            usize::try_from(self.read_i32()?)
+                .map_err(|_| Error::Parse("negative marshal string length".to_string()))
+        }
+    }
+
+    fn read_dict(&mut self) -> Result<Record> {
+        let mut record = Record::new();
+
+        loop {
+            let key = match self.read_type()? {
+                TYPE_NULL => return Ok(record),
+                t => String::from_utf8_lossy(&self.read_value(t)?).into_owned(),
+            };
+            let t = self.read_type()?;
+            let value = self.read_value(t)?;
+
+            record.insert_bytes(key, value);
+        }
+    }
+
+    fn read_value(&mut self, t: u8) -> Result<Vec<u8>> {
+        let len = match t {
+            TYPE_NONE => return Ok(Vec::new()),
+            TYPE_INT => return Ok(self.read_i32()?.to_string().into_bytes()),
+            TYPE_STRING | TYPE_UNICODE | TYPE_INTERNED | TYPE_ASCII | TYPE_ASCII_INTERNED => {
+                self.read_len(false)?
+            }
+            TYPE_SHORT_ASCII | TYPE_SHORT_ASCII_INTERNED => self.read_len(true)?,
+            t => {
+                return Err(Error::Parse(format!(
+                    "unsupported marshal type `{}` at offset {}",
+                    t as char,
+                    self.pos - 1
+                )))
+            }
+        };
+
+        Ok(self.take(len)?.to_vec())
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn encode(records: &[Vec<(&str, &[u8])>]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for record in records {
+        out.push(TYPE_DICT);
+        for (key, value) in record {
+            for s in [key.as_bytes(), value] {
+                out.push(TYPE_STRING);
+                out.extend_from_slice(&i32::try_from(s.len()).unwrap().to_le_bytes());
+                out.extend_from_slice(s);
+            }
+        }
+        out.push(TYPE_NULL);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_records() {
+        let output = encode(&[
+            vec![
+                ("code", b"stat"),
+                ("change", b"12"),
+                ("desc", b"multi\n\n... line\n"),
+            ],
+            vec![("code", b"stat"), ("change", b"11")],
+        ]);
+
+        let records = parse(&output).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get("change"), Some("12"));
+        assert_eq!(records[0].get("desc"), Some("multi\n\n... line\n"));
+        assert_eq!(records[1].get("change"), Some("11"));
+    }
+
+    #[test]
+    fn test_int_and_short_values() {
+        let mut output = vec![TYPE_DICT, TYPE_SHORT_ASCII, 8];
+        output.extend_from_slice(b"severity");
+        output.push(TYPE_INT);
+        output.extend_from_slice(&3_i32.to_le_bytes());
+        output.push(TYPE_NULL);
+
+        let records = parse(&output).unwrap();
+
+        assert_eq!(records[0].get("severity"), Some("3"));
+    }
+
+    #[test]
+    fn test_non_utf8() {
+        let output = encode(&[vec![("depotFile", b"//depot/\xff.bin")]]);
+
+        let records = parse(&output).unwrap();
+
+        assert_eq!(records[0].get("depotFile"), Some("//depot/\u{fffd}.bin"));
+        assert_eq!(
+            records[0].get_bytes("depotFile"),
+            Some(b"//depot/\xff.bin".as_slice())
+        );
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut output = encode(&[vec![("change", b"12")]]);
+        output.truncate(output.len() - 3);
+
+        assert!(parse(&output).is_err());
+    }
+}
@@ -1,8 +1,37 @@
+mod binary;
 mod change;
+mod command;
+mod connection;
 mod diff;
 mod file;
+mod hunks;
+mod login;
+mod marshal;
+mod path;
+mod record;
+mod runner;
+mod transcript;
+mod word_diff;
+mod ztag;
 
+pub use binary::{get_changed_bytes, ChangedBytes};
 pub use change::{
-    get_pending_changelists, get_submitted_changelists, Changelist, ChangelistStatus,
+    describe_changelist, describe_changelist_files, get_pending_changelists,
+    get_submitted_changelists, get_submitted_changelists_page, Changelist, ChangelistStatus,
 };
-pub use file::{File, FileAction, FileType};
+pub use command::{OutputFormat, P4Command};
+pub use connection::{ConnectionSettings, Setting, SettingSource};
+pub use diff::{
+    get_changelist_diff, get_diff, DiffLine, DiffLinePosition, DiffLineType, DiffOptions,
+    DiffSegment, DiffWhitespace, FileDiff, Hunk,
+};
+pub use file::{get_files, File, FileAction, FileBaseType, FileSpec, FileType};
+pub use hunks::discard_hunk;
+pub use login::login;
+pub use path::P4Path;
+pub use record::{Record, RecordCode};
+pub use runner::{
+    wait_for_child, CancelToken, CancellableRunner, FixtureRunner, P4Output, P4Runner,
+    ProcessRunner,
+};
+pub use transcript::{RecordingRunner, Transcript, TranscriptData, TranscriptEntry};
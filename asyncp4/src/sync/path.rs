@@ -0,0 +1,108 @@
+use std::{borrow::Cow, ffi::OsString, fmt, path::PathBuf};
+
+/// A depot or local path exactly as `p4` reported it. Paths are not
+/// necessarily UTF-8, so the bytes are handed back to `p4` unchanged and
+/// only converted for display.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct P4Path(Vec<u8>);
+
+impl P4Path {
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        Self(bytes.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+
+    /// The path followed by a revision specifier such as `#have`.
+    pub fn with_suffix(&self, suffix: &str) -> Self {
+        let mut bytes = self.0.clone();
+        bytes.extend_from_slice(suffix.as_bytes());
+        Self(bytes)
+    }
+
+    /// The path as a command line argument, byte for byte where the
+    /// platform allows it.
+    pub fn to_os_string(&self) -> OsString {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStringExt;
+            OsString::from_vec(self.0.clone())
+        }
+        #[cfg(not(unix))]
+        {
+            OsString::from(self.to_string_lossy().into_owned())
+        }
+    }
+
+    pub fn to_path_buf(&self) -> PathBuf {
+        PathBuf::from(self.to_os_string())
+    }
+}
+
+impl From<&str> for P4Path {
+    fn from(path: &str) -> Self {
+        Self(path.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for P4Path {
+    fn from(path: String) -> Self {
+        Self(path.into_bytes())
+    }
+}
+
+impl From<&P4Path> for OsString {
+    fn from(path: &P4Path) -> Self {
+        path.to_os_string()
+    }
+}
+
+impl From<P4Path> for OsString {
+    fn from(path: P4Path) -> Self {
+        path.to_os_string()
+    }
+}
+
+impl PartialEq<str> for P4Path {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for P4Path {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == other.as_bytes()
+    }
+}
+
+impl fmt::Display for P4Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_string_lossy())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_round_trip() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = P4Path::new(b"//depot/\xff.bin".to_vec());
+
+        assert_eq!(path.to_string(), "//depot/\u{fffd}.bin");
+        assert_eq!(
+            path.with_suffix("#have").to_os_string().as_bytes(),
+            b"//depot/\xff.bin#have"
+        );
+    }
+}
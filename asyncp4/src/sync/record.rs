@@ -0,0 +1,194 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use crate::{Error, Result};
+
+/// The `code` field `p4 -G` attaches to every record. Tagged output only
+/// contains `stat` records, so a missing code is treated as such.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordCode {
+    Stat,
+    Info,
+    Error,
+    Text,
+    Binary,
+}
+
+/// A field value as text, along with the original bytes if they are not
+/// valid UTF-8.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Value {
+    text: String,
+    raw: Option<Vec<u8>>,
+}
+
+impl Value {
+    fn from_bytes(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Self { text, raw: None },
+            Err(e) => Self {
+                text: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                raw: Some(e.into_bytes()),
+            },
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        self.raw.as_deref().unwrap_or(self.text.as_bytes())
+    }
+}
+
+/// One block of structured `p4` output, e.g. a single changelist of `p4 changes`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Record {
+    fields: Vec<(String, Value)>,
+}
+
+impl Record {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.insert_value(
+            key.into(),
+            Value {
+                text: value.into(),
+                raw: None,
+            },
+        );
+    }
+
+    /// Inserts a value that may not be valid UTF-8, keeping its bytes.
+    pub fn insert_bytes(&mut self, key: impl Into<String>, value: Vec<u8>) {
+        self.insert_value(key.into(), Value::from_bytes(value));
+    }
+
+    fn insert_value(&mut self, key: String, value: Value) {
+        if let Some(field) = self.fields.iter_mut().find(|(k, _)| *k == key) {
+            field.1 = value;
+        } else {
+            self.fields.push((key, value));
+        }
+    }
+
+    /// The value of `key` as text, with bytes that are not valid UTF-8
+    /// replaced. Use [`Record::get_bytes`] for paths passed back to `p4`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.find(key).map(|v| v.text.as_str())
+    }
+
+    /// The value of `key` exactly as the server sent it.
+    pub fn get_bytes(&self, key: &str) -> Option<&[u8]> {
+        self.find(key).map(Value::as_bytes)
+    }
+
+    fn find(&self, key: &str) -> Option<&Value> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn code(&self) -> RecordCode {
+        match self.get("code") {
+            Some("info") => RecordCode::Info,
+            Some("error") => RecordCode::Error,
+            Some("text") => RecordCode::Text,
+            Some("binary") => RecordCode::Binary,
+            _ => RecordCode::Stat,
+        }
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.text.as_str()))
+    }
+
+    /// Returns the value of `key`, failing if the server did not send it.
+    pub fn require(&self, key: &str) -> Result<&str> {
+        self.get(key)
+            .ok_or_else(|| Error::Parse(format!("missing field `{key}`")))
+    }
+
+    /// Like [`Record::require`], for values that are not necessarily text.
+    pub fn require_bytes(&self, key: &str) -> Result<&[u8]> {
+        self.get_bytes(key)
+            .ok_or_else(|| Error::Parse(format!("missing field `{key}`")))
+    }
+
+    /// Parses the value of `key`, failing if it is missing or malformed.
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<T> {
+        let value = self.require(key)?;
+        value
+            .parse()
+            .map_err(|_| Error::Parse(format!("invalid value `{value}` for field `{key}`")))
+    }
+
+    /// Parses the value of `key` if present.
+    pub fn parse_opt<T: FromStr>(&self, key: &str) -> Result<Option<T>> {
+        if self.contains_key(key) {
+            self.parse(key).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn get_indexed(&self, key: &str, index: usize) -> Option<&str> {
+        self.get(&format!("{key}{index}"))
+    }
+
+    /// Splits indexed fields (`depotFile0`, `rev0`, `depotFile1`, ...) into
+    /// one record per index with the index stripped from the key.
+    pub fn indexed_records(&self) -> Vec<Record> {
+        let mut records: BTreeMap<usize, Record> = BTreeMap::new();
+
+        for (key, value) in &self.fields {
+            if let Some((name, index)) = split_indexed_key(key) {
+                records
+                    .entry(index)
+                    .or_default()
+                    .fields
+                    .push((name.to_string(), value.clone()));
+            }
+        }
+
+        records.into_values().collect()
+    }
+
+    pub(crate) fn append_to(&mut self, key: &str, bytes: &[u8]) {
+        if let Some(field) = self.fields.iter_mut().find(|(k, _)| k == key) {
+            let mut value = field.1.as_bytes().to_vec();
+            value.extend_from_slice(bytes);
+            field.1 = Value::from_bytes(value);
+        }
+    }
+}
+
+fn split_indexed_key(key: &str) -> Option<(&str, usize)> {
+    let name = key.trim_end_matches(|c: char| c.is_ascii_digit());
+    if name.is_empty() || name.len() == key.len() {
+        return None;
+    }
+
+    key[name.len()..].parse().ok().map(|index| (name, index))
+}
+
+impl FromIterator<(String, String)> for Record {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut record = Self::new();
+        for (key, value) in iter {
+            record.insert(key, value);
+        }
+        record
+    }
+}
@@ -0,0 +1,441 @@
+use std::{
+    ffi::OsString,
+    io::{Read, Write},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{
+    command::{OutputFormat, P4Command},
+    connection::ConnectionSettings,
+    transcript::Transcript,
+};
+use crate::{Error, Result};
+
+/// Raw result of a single `p4` invocation.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct P4Output {
+    pub status: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl P4Output {
+    pub fn success(stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 0,
+            stdout: stdout.into(),
+            stderr: Vec::new(),
+        }
+    }
+
+    pub fn failure(stderr: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 1,
+            stdout: Vec::new(),
+            stderr: stderr.into(),
+        }
+    }
+}
+
+/// How often a running `p4` process is checked for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Asks commands that are already running to stop. Clones share the flag.
+#[derive(Debug, Default, Clone)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fails with [`Error::Cancelled`] once cancelled.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        Ok(())
+    }
+}
+
+/// Executes `p4` commands. Every function in [`crate::sync`] goes through a
+/// runner so it can be pointed at a real server or at canned output.
+pub trait P4Runner: Send + Sync {
+    fn run(&self, cmd: &P4Command) -> Result<P4Output>;
+
+    /// Like [`P4Runner::run`], but stops with [`Error::Cancelled`] once
+    /// `cancel` is set. Runners that can't interrupt a command only check
+    /// before starting it.
+    fn run_cancellable(&self, cmd: &P4Command, cancel: &CancelToken) -> Result<P4Output> {
+        cancel.check()?;
+        self.run(cmd)
+    }
+
+    fn output_format(&self) -> OutputFormat {
+        OutputFormat::Tagged
+    }
+}
+
+/// Passes every command to [`P4Runner::run_cancellable`] of `runner`, so
+/// that the functions in [`crate::sync`] can be cancelled as a whole.
+pub struct CancellableRunner<'a> {
+    runner: &'a dyn P4Runner,
+    cancel: &'a CancelToken,
+}
+
+impl<'a> CancellableRunner<'a> {
+    pub fn new(runner: &'a dyn P4Runner, cancel: &'a CancelToken) -> Self {
+        Self { runner, cancel }
+    }
+}
+
+impl P4Runner for CancellableRunner<'_> {
+    fn run(&self, cmd: &P4Command) -> Result<P4Output> {
+        self.runner.run_cancellable(cmd, self.cancel)
+    }
+
+    fn run_cancellable(&self, cmd: &P4Command, cancel: &CancelToken) -> Result<P4Output> {
+        self.cancel.check()?;
+        self.runner.run_cancellable(cmd, cancel)
+    }
+
+    fn output_format(&self) -> OutputFormat {
+        self.runner.output_format()
+    }
+}
+
+/// Runs the `p4` executable found in `PATH`. Processes are killed when
+/// cancelled or once they exceed the timeout.
+#[derive(Debug, Default, Clone)]
+pub struct ProcessRunner {
+    format: OutputFormat,
+    global_args: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+impl ProcessRunner {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            global_args: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// Fails every command that takes longer than `timeout` with
+    /// [`Error::Timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Passes the connection explicitly instead of letting every `p4`
+    /// process resolve it again.
+    pub fn with_connection(mut self, connection: &ConnectionSettings) -> Self {
+        self.global_args = connection.args();
+        self
+    }
+}
+
+impl P4Runner for ProcessRunner {
+    fn run(&self, cmd: &P4Command) -> Result<P4Output> {
+        self.run_cancellable(cmd, &CancelToken::default())
+    }
+
+    fn run_cancellable(&self, cmd: &P4Command, cancel: &CancelToken) -> Result<P4Output> {
+        cancel.check()?;
+
+        let mut command = Command::new("p4");
+        if !cmd.is_raw() {
+            command.arg(self.format.flag());
+        }
+
+        let mut child = command
+            .args(&self.global_args)
+            .args(cmd.get_args())
+            .stdin(if cmd.get_stdin().is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let (Some(input), Some(mut stdin)) = (cmd.get_stdin(), child.stdin.take()) {
+            stdin.write_all(input)?;
+        }
+
+        wait_for_child(child, &cmd.name(), cancel, self.timeout)
+    }
+
+    fn output_format(&self) -> OutputFormat {
+        self.format
+    }
+}
+
+/// Waits for `child` to exit, killing it once `cancel` is set or `timeout`
+/// has passed.
+pub fn wait_for_child(
+    mut child: Child,
+    name: &str,
+    cancel: &CancelToken,
+    timeout: Option<Duration>,
+) -> Result<P4Output> {
+    // drained meanwhile, a full pipe would block the child forever
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        let error = match timeout {
+            _ if cancel.is_cancelled() => Some(Error::Cancelled),
+            Some(timeout) if started.elapsed() >= timeout => {
+                Some(Error::Timeout(name.to_string(), timeout))
+            }
+            _ => None,
+        };
+        if let Some(error) = error {
+            // fails if the child has exited just now, which is fine
+            let _ = child.kill();
+            child.wait()?;
+            return Err(error);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    Ok(P4Output {
+        status: status.code().unwrap_or(-1),
+        stdout: join_reader(stdout)?,
+        stderr: join_reader(stderr)?,
+    })
+}
+
+fn read_in_background<R: Read + Send + 'static>(
+    mut pipe: R,
+) -> JoinHandle<std::io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).map(|_| buf)
+    })
+}
+
+fn join_reader(reader: Option<JoinHandle<std::io::Result<Vec<u8>>>>) -> Result<Vec<u8>> {
+    match reader {
+        Some(reader) => Ok(reader
+            .join()
+            .map_err(|_| Error::Generic("pipe reader panicked".to_string()))??),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Serves canned output for known argument lists, e.g. in tests. Repeated
+/// fixtures for the same arguments are served in order, the last one
+/// sticking once all have been used.
+#[derive(Debug, Default)]
+pub struct FixtureRunner {
+    format: OutputFormat,
+    fixtures: Vec<(Vec<OsString>, P4Output)>,
+    calls: Mutex<Vec<P4Command>>,
+}
+
+impl FixtureRunner {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            ..Self::default()
+        }
+    }
+
+    pub fn with(mut self, args: &[&str], output: P4Output) -> Self {
+        self.add(args.iter().map(ToString::to_string).collect(), output);
+        self
+    }
+
+    /// Like [`FixtureRunner::with`], for args that need not be UTF-8.
+    pub fn add<S: Into<OsString>>(&mut self, args: Vec<S>, output: P4Output) {
+        self.fixtures
+            .push((args.into_iter().map(Into::into).collect(), output));
+    }
+
+    pub fn from_transcript(transcript: Transcript) -> Self {
+        let mut runner = Self::new(transcript.format);
+        for entry in transcript.entries {
+            let output = entry.output();
+            runner.add(entry.args, output);
+        }
+        runner
+    }
+
+    /// Commands run so far, in order.
+    pub fn calls(&self) -> Vec<P4Command> {
+        self.calls.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+}
+
+impl P4Runner for FixtureRunner {
+    fn run(&self, cmd: &P4Command) -> Result<P4Output> {
+        let mut calls = self.calls.lock()?;
+        let previous = calls
+            .iter()
+            .filter(|c| c.get_args() == cmd.get_args())
+            .count();
+        calls.push(cmd.clone());
+
+        let matching = self
+            .fixtures
+            .iter()
+            .filter(|(args, _)| args.as_slice() == cmd.get_args())
+            .map(|(_, output)| output)
+            .collect::<Vec<_>>();
+
+        matching
+            .get(previous)
+            .or_else(|| matching.last())
+            .map(|output| (*output).clone())
+            .ok_or_else(|| Error::Generic(format!("no fixture for `{cmd}`")))
+    }
+
+    fn output_format(&self) -> OutputFormat {
+        self.format
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_fixture_lookup() {
+        let runner =
+            FixtureRunner::default().with(&["info"], P4Output::success("... userName bob\n"));
+
+        let records = P4Command::new("info").run(&runner).unwrap();
+
+        assert_eq!(records[0].get("userName"), Some("bob"));
+        assert_eq!(runner.calls(), vec![P4Command::new("info")]);
+    }
+
+    #[test]
+    fn test_repeated_fixtures() {
+        let runner = FixtureRunner::default()
+            .with(&["info"], P4Output::success("... userName bob\n"))
+            .with(&["info"], P4Output::success("... userName alice\n"));
+
+        let user = || {
+            P4Command::new("info").run(&runner).unwrap()[0]
+                .get("userName")
+                .map(String::from)
+        };
+
+        assert_eq!(user(), Some("bob".to_string()));
+        assert_eq!(user(), Some("alice".to_string()));
+        assert_eq!(user(), Some("alice".to_string()));
+    }
+
+    #[test]
+    fn test_missing_fixture() {
+        let runner = FixtureRunner::default();
+
+        assert!(P4Command::new("info").run(&runner).is_err());
+    }
+
+    #[cfg(unix)]
+    fn sleep_child() -> Child {
+        Command::new("sleep")
+            .arg("5")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_child_timeout() {
+        let started = Instant::now();
+
+        let err = wait_for_child(
+            sleep_child(),
+            "sleep",
+            &CancelToken::default(),
+            Some(Duration::from_millis(50)),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::Timeout(name, _) if name == "sleep"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_child_cancelled() {
+        let cancel = CancelToken::default();
+        let started = Instant::now();
+
+        let canceller = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                cancel.cancel();
+            })
+        };
+        let err = wait_for_child(sleep_child(), "sleep", &cancel, None).unwrap_err();
+        canceller.join().unwrap();
+
+        assert!(matches!(err, Error::Cancelled));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_cancellable_runner() {
+        let runner =
+            FixtureRunner::default().with(&["info"], P4Output::success("... userName bob\n"));
+        let cancel = CancelToken::default();
+        let cancellable = CancellableRunner::new(&runner, &cancel);
+
+        assert!(P4Command::new("info").run(&cancellable).is_ok());
+
+        cancel.cancel();
+        assert!(matches!(
+            P4Command::new("info").run(&cancellable),
+            Err(Error::Cancelled)
+        ));
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[test]
+    fn test_marshal_error_record() {
+        let output = crate::sync::marshal::encode(&[vec![
+            ("code", b"error"),
+            ("data", b"Change 99 unknown.\n"),
+            ("severity", b"3"),
+        ]]);
+        let runner = FixtureRunner::new(OutputFormat::Marshal)
+            .with(&["describe", "99"], P4Output::success(output));
+
+        let err = P4Command::new("describe")
+            .arg("99")
+            .run(&runner)
+            .unwrap_err();
+
+        assert!(matches!(err, Error::NoSuchChangelist(_)));
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    command::{OutputFormat, P4Command},
+    runner::{CancelToken, P4Output, P4Runner},
+};
+use crate::{Error, Result};
+
+// commands whose stdin carries a password
+const REDACTED_COMMANDS: &[&str] = &["login", "passwd"];
+const REDACTED: &str = "<redacted>";
+
+/// Output bytes are kept as text when possible so recordings stay readable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TranscriptData {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<&[u8]> for TranscriptData {
+    fn from(data: &[u8]) -> Self {
+        std::str::from_utf8(data).map_or_else(
+            |_| Self::Bytes(data.to_vec()),
+            |text| Self::Text(text.to_string()),
+        )
+    }
+}
+
+impl From<TranscriptData> for Vec<u8> {
+    fn from(data: TranscriptData) -> Self {
+        match data {
+            TranscriptData::Text(text) => text.into_bytes(),
+            TranscriptData::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub args: Vec<String>,
+    pub stdin: Option<TranscriptData>,
+    pub status: i32,
+    pub stdout: TranscriptData,
+    pub stderr: TranscriptData,
+}
+
+impl TranscriptEntry {
+    fn new(cmd: &P4Command, output: &P4Output) -> Self {
+        let redact = cmd
+            .get_args()
+            .first()
+            .and_then(|c| c.to_str())
+            .is_some_and(|c| REDACTED_COMMANDS.contains(&c));
+
+        Self {
+            // not UTF-8 safe, such arguments won't match on replay
+            args: cmd
+                .get_args()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            stdin: cmd.get_stdin().map(|input| {
+                if redact {
+                    TranscriptData::Text(REDACTED.to_string())
+                } else {
+                    input.into()
+                }
+            }),
+            status: output.status,
+            stdout: output.stdout.as_slice().into(),
+            stderr: output.stderr.as_slice().into(),
+        }
+    }
+
+    pub fn output(&self) -> P4Output {
+        P4Output {
+            status: self.status,
+            stdout: self.stdout.clone().into(),
+            stderr: self.stderr.clone().into(),
+        }
+    }
+}
+
+/// Every `p4` invocation of a session, as written by [`RecordingRunner`]:
+/// the output format on the first line followed by one entry per line.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    pub format: OutputFormat,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let invalid = |e: ron::error::SpannedError| {
+            Error::Generic(format!("invalid transcript {}: {e}", path.display()))
+        };
+
+        let mut lines = data.lines().filter(|line| !line.is_empty()).peekable();
+        let format = from_line(lines.next().unwrap_or_default()).map_err(invalid)?;
+
+        let mut entries = Vec::new();
+        while let Some(line) = lines.next() {
+            match from_line(line) {
+                Ok(entry) => entries.push(entry),
+                // a crash while recording can cut off the last entry
+                Err(e) if lines.peek().is_none() => {
+                    log::warn!("skipping truncated transcript entry: {}", e);
+                }
+                Err(e) => return Err(invalid(e)),
+            }
+        }
+
+        Ok(Self { format, entries })
+    }
+}
+
+fn from_line<T: DeserializeOwned>(line: &str) -> std::result::Result<T, ron::error::SpannedError> {
+    ron::from_str(line)
+}
+
+fn to_line<T: Serialize>(value: &T) -> Result<String> {
+    let mut line = ron::to_string(value)
+        .map_err(|e| Error::Generic(format!("transcript serialization: {e}")))?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// Forwards to another runner and appends every invocation to a transcript
+/// file that can later be replayed with [`super::FixtureRunner::from_transcript`].
+pub struct RecordingRunner {
+    inner: Box<dyn P4Runner>,
+    file: Mutex<fs::File>,
+}
+
+impl RecordingRunner {
+    pub fn new(inner: Box<dyn P4Runner>, path: PathBuf) -> Result<Self> {
+        let mut file = fs::File::create(path)?;
+        file.write_all(to_line(&inner.output_format())?.as_bytes())?;
+
+        Ok(Self {
+            inner,
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl RecordingRunner {
+    fn record(&self, cmd: &P4Command, output: P4Output) -> Result<P4Output> {
+        let line = to_line(&TranscriptEntry::new(cmd, &output))?;
+
+        if let Err(e) = self.file.lock()?.write_all(line.as_bytes()) {
+            log::error!("failed to write transcript: {}", e);
+        }
+
+        Ok(output)
+    }
+}
+
+impl P4Runner for RecordingRunner {
+    fn run(&self, cmd: &P4Command) -> Result<P4Output> {
+        self.record(cmd, self.inner.run(cmd)?)
+    }
+
+    fn run_cancellable(&self, cmd: &P4Command, cancel: &CancelToken) -> Result<P4Output> {
+        self.record(cmd, self.inner.run_cancellable(cmd, cancel)?)
+    }
+
+    fn output_format(&self) -> OutputFormat {
+        self.inner.output_format()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{get_pending_changelists, FixtureRunner};
+    use pretty_assertions::assert_eq;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_record_and_replay() {
+        let file = NamedTempFile::new().unwrap();
+
+        let server = FixtureRunner::default()
+            .with(
+                &["changes", "-l", "-t", "-s", "pending"],
+                P4Output::success("... change 5\n... status pending\n"),
+            )
+            .with(&["login"], P4Output::success("User bob logged in.\n"));
+
+        let recorder = RecordingRunner::new(Box::new(server), file.path().to_path_buf()).unwrap();
+        let recorded = get_pending_changelists(&recorder, None, None).unwrap();
+        P4Command::new("login")
+            .stdin("hunter2\n")
+            .run(&recorder)
+            .unwrap();
+
+        let transcript = Transcript::load(file.path()).unwrap();
+        assert_eq!(transcript.entries.len(), 2);
+        assert_eq!(
+            transcript.entries[1].stdin,
+            Some(TranscriptData::Text(REDACTED.to_string()))
+        );
+
+        let replay = FixtureRunner::from_transcript(transcript);
+        let replayed = get_pending_changelists(&replay, None, None).unwrap();
+
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(replayed[0].number, recorded[0].number);
+    }
+
+    #[test]
+    fn test_truncated_entry() {
+        let file = NamedTempFile::new().unwrap();
+
+        let recorder = RecordingRunner::new(
+            Box::new(FixtureRunner::default().with(&["info"], P4Output::success("ok\n"))),
+            file.path().to_path_buf(),
+        )
+        .unwrap();
+        P4Command::new("info").run(&recorder).unwrap();
+        P4Command::new("info").run(&recorder).unwrap();
+
+        // cut off in the middle of the last entry
+        let data = fs::read_to_string(file.path()).unwrap();
+        fs::write(file.path(), &data[..data.len() - 10]).unwrap();
+
+        let transcript = Transcript::load(file.path()).unwrap();
+        assert_eq!(transcript.entries.len(), 1);
+        assert_eq!(
+            transcript.entries[0].stdout,
+            TranscriptData::Text("ok\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_binary_output() {
+        let entry = TranscriptEntry::new(
+            &P4Command::new("print"),
+            &P4Output::success(vec![0xff, 0x00, b'a']),
+        );
+
+        assert_eq!(entry.stdout, TranscriptData::Bytes(vec![0xff, 0x00, b'a']));
+        assert_eq!(entry.output().stdout, vec![0xff, 0x00, b'a']);
+    }
+}
@@ -0,0 +1,213 @@
+use std::ops::Range;
+
+use super::diff::{DiffLine, DiffLineType, DiffSegment};
+
+// keeps the LCS table small for pathological lines
+const MAX_TOKEN_PRODUCT: usize = 100_000;
+
+/// Fills [`DiffLine::segments`] of every deleted line that is followed by an
+/// added line, pairing the n-th line of a delete run with the n-th line of
+/// the add run after it.
+pub(crate) fn highlight_words(lines: &mut [DiffLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        let deletes = run(lines, i, DiffLineType::Delete);
+        if deletes.is_empty() {
+            i += 1;
+            continue;
+        }
+
+        // a "No newline" marker may trail the last deleted line
+        let mut next = deletes.end;
+        if lines
+            .get(next)
+            .is_some_and(|l| l.line_type == DiffLineType::None)
+        {
+            next += 1;
+        }
+
+        let adds = run(lines, next, DiffLineType::Add);
+        for (old, new) in deletes.zip(adds.clone()) {
+            if let Some((old_segments, new_segments)) =
+                word_diff(&lines[old].content, &lines[new].content)
+            {
+                lines[old].segments = old_segments;
+                lines[new].segments = new_segments;
+            }
+        }
+
+        i = adds.end.max(next);
+    }
+}
+
+fn run(lines: &[DiffLine], start: usize, line_type: DiffLineType) -> Range<usize> {
+    let len = lines
+        .iter()
+        .skip(start)
+        .take_while(|l| l.line_type == line_type)
+        .count();
+
+    start..start + len
+}
+
+/// Words, runs of whitespace and single other characters.
+fn tokenize(s: &str) -> Vec<Range<usize>> {
+    #[derive(PartialEq, Eq, Clone, Copy)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut tokens: Vec<(Range<usize>, Class)> = Vec::new();
+    for (i, c) in s.char_indices() {
+        let cls = class(c);
+        match tokens.last_mut() {
+            Some((range, last)) if *last == cls && cls != Class::Other => {
+                range.end = i + c.len_utf8();
+            }
+            _ => tokens.push((i..i + c.len_utf8(), cls)),
+        }
+    }
+
+    tokens.into_iter().map(|(range, _)| range).collect()
+}
+
+/// Segments of both lines marking the tokens that are not part of their
+/// longest common subsequence. `None` if the lines share no words,
+/// where highlighting every token would only add noise.
+pub(crate) fn word_diff(old: &str, new: &str) -> Option<(Vec<DiffSegment>, Vec<DiffSegment>)> {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    let (n, m) = (old_tokens.len(), new_tokens.len());
+
+    if n == 0 || m == 0 || n * m > MAX_TOKEN_PRODUCT {
+        return None;
+    }
+
+    let token = |s: &str, r: &Range<usize>| s[r.clone()].to_string();
+    let old_words = old_tokens.iter().map(|r| token(old, r)).collect::<Vec<_>>();
+    let new_words = new_tokens.iter().map(|r| token(new, r)).collect::<Vec<_>>();
+
+    // lcs[i][j]: common length of old_words[i..] and new_words[j..]
+    let mut lcs = vec![vec![0_u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_words[i] == new_words[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut old_changed = vec![true; n];
+    let mut new_changed = vec![true; m];
+    let mut common_words = false;
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old_words[i] == new_words[j] {
+            common_words |= !old_words[i].trim().is_empty();
+            old_changed[i] = false;
+            new_changed[j] = false;
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    if !common_words {
+        return None;
+    }
+
+    Some((
+        segments(&old_tokens, &old_changed),
+        segments(&new_tokens, &new_changed),
+    ))
+}
+
+fn segments(tokens: &[Range<usize>], changed: &[bool]) -> Vec<DiffSegment> {
+    let mut segments: Vec<DiffSegment> = Vec::new();
+
+    for (range, &changed) in tokens.iter().zip(changed) {
+        match segments.last_mut() {
+            Some(last) if last.changed == changed => last.range.end = range.end,
+            _ => segments.push(DiffSegment {
+                range: range.clone(),
+                changed,
+            }),
+        }
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn changed<'a>(s: &'a str, segments: &[DiffSegment]) -> Vec<&'a str> {
+        segments
+            .iter()
+            .filter(|s| s.changed)
+            .map(|seg| &s[seg.range.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn test_word_diff() {
+        let old = "let value = compute(a, b);";
+        let new = "let value = compute(a, c);";
+
+        let (old_segments, new_segments) = word_diff(old, new).unwrap();
+
+        assert_eq!(changed(old, &old_segments), vec!["b"]);
+        assert_eq!(changed(new, &new_segments), vec!["c"]);
+        assert_eq!(old_segments.len(), 3);
+        assert_eq!(old_segments.last().unwrap().range.end, old.len());
+    }
+
+    #[test]
+    fn test_nothing_in_common() {
+        assert_eq!(word_diff("foo", "bar"), None);
+        assert_eq!(word_diff("", "bar"), None);
+    }
+
+    #[test]
+    fn test_pairing() {
+        let line = |line_type, content: &str| DiffLine {
+            content: content.into(),
+            line_type,
+            ..DiffLine::default()
+        };
+        let mut lines = vec![
+            line(DiffLineType::None, "same"),
+            line(DiffLineType::Delete, "a = 1"),
+            line(DiffLineType::Delete, "b = 2"),
+            line(DiffLineType::Add, "a = 3"),
+            line(DiffLineType::Add, "extra line"),
+        ];
+
+        highlight_words(&mut lines);
+
+        assert!(lines[0].segments.is_empty());
+        assert_eq!(changed("a = 1", &lines[1].segments), vec!["1"]);
+        assert_eq!(changed("a = 3", &lines[3].segments), vec!["3"]);
+        // paired, but nothing in common
+        assert!(lines[2].segments.is_empty() && lines[4].segments.is_empty());
+    }
+}
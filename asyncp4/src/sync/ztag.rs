@@ -0,0 +1,140 @@
+// `p4 -ztag` writes one `... key value` line per field. Multi-line values
+// (descriptions) continue on lines without the tag, and blank lines may appear
+// inside them, so a new record starts whenever a key repeats instead.
+
+use super::record::Record;
+
+const TAG: &[u8] = b"... ";
+
+/// Values are kept as bytes, paths need not be UTF-8.
+pub fn parse(output: &[u8]) -> Vec<Record> {
+    let mut records = Vec::new();
+    let mut current = Record::new();
+    let mut last_key: Option<String> = None;
+    let mut blank_lines = 0_usize;
+
+    for line in output.split(|b| *b == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        if let Some(field) = line.strip_prefix(TAG) {
+            // nested fields (`... ... otherOpen0 user@client`) are flattened
+            let mut field = field;
+            while let Some(inner) = field.strip_prefix(TAG) {
+                field = inner;
+            }
+
+            let (key, value) = match field.iter().position(|b| *b == b' ') {
+                Some(space) => (&field[..space], &field[space + 1..]),
+                None => (field, [].as_slice()),
+            };
+            let key = String::from_utf8_lossy(key).into_owned();
+
+            if current.contains_key(&key) {
+                records.push(std::mem::take(&mut current));
+            }
+
+            current.insert_bytes(key.clone(), value.to_vec());
+            last_key = Some(key);
+            blank_lines = 0;
+        } else if line.is_empty() {
+            blank_lines += 1;
+        } else if let Some(key) = &last_key {
+            let mut text = vec![b'\n'; blank_lines + 1];
+            text.extend_from_slice(line);
+            current.append_to(key, &text);
+            blank_lines = 0;
+        }
+    }
+
+    if !current.is_empty() {
+        records.push(current);
+    }
+
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_multiple_records() {
+        let output = b"... change 12\n... status pending\n\n... change 11\n... status pending\n\n";
+
+        let records = parse(output);
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].get("change"), Some("12"));
+        assert_eq!(records[1].get("change"), Some("11"));
+        assert_eq!(records[1].get("status"), Some("pending"));
+    }
+
+    #[test]
+    fn test_records_without_separator() {
+        let output = b"... change 12\n... status pending\n... change 11\n... status pending\n";
+
+        let records = parse(output);
+
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    fn test_wrapped_description() {
+        let output = b"... change 3\n... desc first line\n\n  indented\n\n... status submitted\n";
+
+        let records = parse(output);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].get("desc"), Some("first line\n\n  indented"));
+        assert_eq!(records[0].get("status"), Some("submitted"));
+    }
+
+    #[test]
+    fn test_odd_values() {
+        let output =
+            "... depotFile //depot/some dir/fïle #1 @x.txt\r\n... headRev 4\r\n... emptyKey\r\n"
+                .as_bytes();
+
+        let records = parse(output);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].get("depotFile"),
+            Some("//depot/some dir/fïle #1 @x.txt")
+        );
+        assert_eq!(records[0].get("headRev"), Some("4"));
+        assert_eq!(records[0].get("emptyKey"), Some(""));
+    }
+
+    #[test]
+    fn test_indexed_fields() {
+        let output = b"... change 7\n... depotFile0 //depot/a\n... rev0 3\n... depotFile1 //depot/b\n... rev1 1\n... ... otherOpen0 bob@ws\n";
+
+        let records = parse(output);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].get_indexed("depotFile", 1), Some("//depot/b"));
+        assert_eq!(records[0].get("otherOpen0"), Some("bob@ws"));
+
+        let files = records[0].indexed_records();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].get("depotFile"), Some("//depot/a"));
+        assert_eq!(files[0].get("rev"), Some("3"));
+        assert_eq!(files[0].get("otherOpen"), Some("bob@ws"));
+        assert_eq!(files[1].get("rev"), Some("1"));
+    }
+
+    #[test]
+    fn test_non_utf8() {
+        let output = b"... depotFile //depot/\xff.txt\n";
+
+        let records = parse(output);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].get("depotFile"), Some("//depot/\u{fffd}.txt"));
+        assert_eq!(
+            records[0].get_bytes("depotFile"),
+            Some(b"//depot/\xff.txt".as_slice())
+        );
+    }
+}
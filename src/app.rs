@@ -1,17 +1,28 @@
-use std::{borrow::BorrowMut, cell::RefCell, os::raw::c_ushort, path::PathBuf, rc::Rc};
+use std::{cell::RefCell, path::PathBuf, rc::Rc, sync::Arc};
 
 use crate::{
     accessors,
     commandbar::CommandBar,
-    components::{event_pump, Component, DrawableComponent, HelpComponent},
+    components::{
+        command_pump, event_pump, AppOption, CommandInfo, Component, ConfirmComponent,
+        DrawableComponent, HelpComponent, LoginComponent, MsgComponent, OptionsPopupComponent,
+    },
     input::{Input, InputEvent},
     keys::{key_match, KeyConfig, SharedKeyConfig},
+    options::{Options, SharedOptions},
+    queue::{Action, InternalEvent, Queue},
     setup_popups,
     tabbar::TabBar,
     tabs::{PendingTab, SubmittedTab},
     ui::style::{SharedTheme, Theme},
+    AsyncAppNotification, AsyncNotification,
 };
 use anyhow::{bail, Result};
+use asyncp4::{
+    sync::{ConnectionSettings, P4Runner},
+    AsyncP4Notification,
+};
+use crossbeam_channel::Sender;
 use crossterm::event::{Event, KeyEvent};
 use ratatui::{
     backend::Backend,
@@ -25,34 +36,99 @@ pub enum QuitState {
     Close,
 }
 
+/// What to run again once a missing login has been provided.
+enum Retry {
+    Update,
+    Event(Event),
+}
+
 pub struct App {
     cwd: PathBuf,
     do_quit: QuitState,
     key_config: SharedKeyConfig,
     theme: SharedTheme,
     input: Input,
+    queue: Queue,
+    options: SharedOptions,
+    retry: Option<Retry>,
     tabbar: RefCell<TabBar>,
     cmdbar: RefCell<CommandBar>,
     tab: usize,
     pending_tab: PendingTab,
     submitted_tab: SubmittedTab,
     help: HelpComponent,
+    login: LoginComponent,
+    options_popup: OptionsPopupComponent,
+    confirm: ConfirmComponent,
+    msg: MsgComponent,
 }
 
 impl App {
-    pub fn new(cwd: PathBuf, input: Input, key_config: KeyConfig, theme: Theme) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        cwd: PathBuf,
+        connection: ConnectionSettings,
+        runner: Arc<dyn P4Runner>,
+        sender_p4: &Sender<AsyncP4Notification>,
+        sender_app: &Sender<AsyncAppNotification>,
+        input: Input,
+        key_config: KeyConfig,
+        theme: Theme,
+    ) -> Self {
         let key_config = Rc::new(key_config);
         let theme = Rc::new(theme);
+        let queue = Queue::new();
+        let options = Options::new();
         Self {
             do_quit: QuitState::None,
             tab: 0,
-            tabbar: RefCell::new(TabBar::new(key_config.clone(), theme.clone(), cwd.clone())),
+            tabbar: RefCell::new(TabBar::new(
+                key_config.clone(),
+                theme.clone(),
+                cwd.clone(),
+                connection.clone(),
+            )),
             cmdbar: RefCell::new(CommandBar::new(key_config.clone(), theme.clone())),
-            pending_tab: PendingTab::new(key_config.clone(), theme.clone()),
-            submitted_tab: SubmittedTab::new(key_config.clone(), theme.clone()),
+            pending_tab: PendingTab::new(
+                runner.clone(),
+                sender_p4,
+                sender_app,
+                &connection,
+                queue.clone(),
+                options.clone(),
+                key_config.clone(),
+                theme.clone(),
+            ),
+            submitted_tab: SubmittedTab::new(
+                runner.clone(),
+                sender_p4,
+                sender_app,
+                queue.clone(),
+                options.clone(),
+                key_config.clone(),
+                theme.clone(),
+            ),
             help: HelpComponent::new(key_config.clone(), theme.clone()),
+            login: LoginComponent::new(
+                runner,
+                sender_p4,
+                queue.clone(),
+                key_config.clone(),
+                theme.clone(),
+            ),
+            options_popup: OptionsPopupComponent::new(
+                queue.clone(),
+                options.clone(),
+                key_config.clone(),
+                theme.clone(),
+            ),
+            confirm: ConfirmComponent::new(queue.clone(), key_config.clone(), theme.clone()),
+            msg: MsgComponent::new(key_config.clone(), theme.clone()),
             cwd,
             input,
+            queue,
+            options,
+            retry: None,
             key_config,
             theme,
         }
@@ -62,7 +138,7 @@ impl App {
         // let greeting = ratatui::widgets::Paragraph::new("Hello World! (press 'q' to quit)");
         // f.render_widget(greeting, f.size());
 
-        // self.cmdbar.borrow_mut().refresh_width(fsize.width);
+        self.cmdbar.borrow_mut().refresh_width(fsize.width);
 
         let chunks_main = Layout::default()
             .direction(Direction::Vertical)
@@ -92,27 +168,76 @@ impl App {
     }
 
     pub fn update(&mut self) -> Result<()> {
-        self.pending_tab.update()?;
+        // nothing is (re)loaded while waiting for credentials
+        if self.login.is_visible() {
+            return Ok(());
+        }
+
+        // results arrive through `update_async`
+        match self.tab {
+            1 => self.submitted_tab.update(),
+            _ => self.pending_tab.update(),
+        }
+
+        self.update_commands();
+
+        Ok(())
+    }
+
+    pub fn update_async(&mut self, ev: AsyncNotification) -> Result<()> {
+        let ev = match ev {
+            AsyncNotification::P4(ev) => ev,
+            AsyncNotification::App(ev) => {
+                self.pending_tab.update_app_async(ev);
+                self.submitted_tab.update_app_async(ev);
+                return Ok(());
+            }
+        };
 
+        self.login.update_async(ev);
+
+        // each tab only picks up results of its own jobs
+        let results = [
+            self.pending_tab.update_async(ev),
+            self.submitted_tab.update_async(ev),
+        ];
+        for result in results {
+            if let Err(e) = result {
+                self.handle_error(e, Retry::Update)?;
+            }
+        }
+
+        self.process_queue()?;
         self.update_commands();
 
         Ok(())
     }
 
+    /// Runs the diff formatter of the current tab once the width it fills
+    /// is known, i.e. after drawing.
+    pub fn update_formatter(&mut self) {
+        match self.tab {
+            1 => self.submitted_tab.update_formatter(),
+            _ => self.pending_tab.update_formatter(),
+        }
+    }
+
     pub fn event(&mut self, ev: InputEvent) -> Result<()> {
         if let InputEvent::Input(ev) = ev {
-            if self.check_hard_exit(&ev) || self.check_quit(&ev) {
+            if self.check_hard_exit(&ev)
+                || (!self.login.is_visible()
+                    && !self.current_tab().is_input_active()
+                    && self.check_quit(&ev))
+            {
                 return Ok(());
             }
 
-            if event_pump(&ev, self.components_mut().as_mut_slice())?.is_consumed() {
-            } else if let Event::Key(k) = &ev {
-                if key_match(k, self.key_config.keys.tab_pending)
-                    || key_match(k, self.key_config.keys.tab_submitted)
-                {
-                    self.switch_tab(k)?;
-                }
+            if let Err(e) = self.event_impl(&ev) {
+                self.handle_error(e, Retry::Event(ev))?;
             }
+
+            self.process_queue()?;
+            self.update_commands();
         }
 
         Ok(())
@@ -124,9 +249,82 @@ impl App {
 }
 
 impl App {
-    accessors!(self, [help]);
+    accessors!(self, [msg, confirm, login, options_popup, help]);
+
+    setup_popups!(self, [login, options_popup, help, confirm, msg]);
+
+    fn event_impl(&mut self, ev: &Event) -> Result<()> {
+        // typed text must not open popups or switch tabs
+        if !self.any_popup_visible()
+            && self.current_tab().is_input_active()
+            && self.current_tab_mut().event(ev)?.is_consumed()
+        {
+            return Ok(());
+        }
+
+        if event_pump(ev, self.components_mut().as_mut_slice())?.is_consumed()
+            || self.current_tab_mut().event(ev)?.is_consumed()
+        {
+        } else if let Event::Key(k) = ev {
+            if key_match(k, self.key_config.keys.tab_pending)
+                || key_match(k, self.key_config.keys.tab_submitted)
+            {
+                self.switch_tab(k)?;
+            } else if key_match(k, self.key_config.keys.cmd_bar_toggle) {
+                self.cmdbar.borrow_mut().toggle_more();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_error(&mut self, e: anyhow::Error, retry: Retry) -> Result<()> {
+        match e.downcast_ref::<asyncp4::Error>() {
+            Some(asyncp4::Error::NotLoggedIn(message)) => {
+                self.retry = Some(retry);
+                self.login.open(message)?;
+                Ok(())
+            }
+            // failed p4 commands are no reason to quit, the next update retries
+            Some(_) => self.msg.show_error(&e.to_string()),
+            None => Err(e),
+        }
+    }
+
+    fn process_queue(&mut self) -> Result<()> {
+        while let Some(ev) = self.queue.pop() {
+            match ev {
+                InternalEvent::LoggedIn => match self.retry.take() {
+                    Some(Retry::Update) => self.update()?,
+                    Some(Retry::Event(ev)) => self.event(InputEvent::Input(ev))?,
+                    None => {}
+                },
+                InternalEvent::OptionSwitched(o) => match o {
+                    AppOption::Whitespace
+                    | AppOption::ContextLines
+                    | AppOption::InterhunkLines
+                    | AppOption::MaxFileSize => {
+                        self.pending_tab.update_diff();
+                        self.submitted_tab.update_diff();
+                    }
+                },
+                InternalEvent::ConfirmAction(action) => self.confirm.open(action)?,
+                InternalEvent::ConfirmedAction(action) => self.process_confirmed_action(action)?,
+                InternalEvent::ShowErrorMsg(msg) => self.msg.show_error(&msg)?,
+                InternalEvent::LoadHexDump(path) => self.pending_tab.load_hex_dump(path),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_confirmed_action(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::DiscardHunk(path, hunk) => self.pending_tab.discard_hunk(path, hunk),
+        }
 
-    setup_popups!(self, [help]);
+        Ok(())
+    }
 
     fn check_quit(&mut self, ev: &Event) -> bool {
         if let Event::Key(e) = ev {
@@ -152,6 +350,20 @@ impl App {
         vec![&mut self.pending_tab, &mut self.submitted_tab]
     }
 
+    fn current_tab(&self) -> &dyn Component {
+        match self.tab {
+            1 => &self.submitted_tab,
+            _ => &self.pending_tab,
+        }
+    }
+
+    fn current_tab_mut(&mut self) -> &mut dyn Component {
+        match self.tab {
+            1 => &mut self.submitted_tab,
+            _ => &mut self.pending_tab,
+        }
+    }
+
     fn switch_tab(&mut self, k: &KeyEvent) -> Result<()> {
         if key_match(k, self.key_config.keys.tab_pending) {
             self.set_tab(0)?;
@@ -179,8 +391,118 @@ impl App {
     }
 
     fn update_commands(&mut self) {
-        // if self.help.is_visible() {
-        //     self.help.set_cmds(self.commands(true));
-        // }
+        if self.help.is_visible() {
+            self.help.set_cmds(self.commands(true));
+        }
+        self.cmdbar.borrow_mut().set_cmds(self.commands(false));
+    }
+
+    fn commands(&self, force_all: bool) -> Vec<CommandInfo> {
+        let mut res = Vec::new();
+
+        // popups come first, the topmost blocks everything behind it
+        let mut components = self.components();
+        components.push(self.current_tab());
+        command_pump(&mut res, force_all, &components);
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asyncp4::sync::{FixtureRunner, P4Output, Setting, SettingSource};
+    use crossbeam_channel::{unbounded, Receiver};
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use std::time::Duration;
+
+    fn app(runner: Arc<FixtureRunner>) -> (App, Receiver<AsyncP4Notification>) {
+        let connection = ConnectionSettings {
+            port: Setting {
+                value: "perforce:1666".into(),
+                source: SettingSource::Default,
+            },
+            user: None,
+            client: None,
+        };
+        let (tx_p4, rx_p4) = unbounded();
+        let (tx_app, _rx_app) = unbounded();
+        let app = App::new(
+            PathBuf::new(),
+            connection,
+            runner,
+            &tx_p4,
+            &tx_app,
+            Input::new(),
+            KeyConfig::default(),
+            Theme::default(),
+        );
+
+        (app, rx_p4)
+    }
+
+    /// Hands the next finished job to `app`, expecting it to be `expected`.
+    fn next_async(
+        app: &mut App,
+        rx: &Receiver<AsyncP4Notification>,
+        expected: AsyncP4Notification,
+    ) {
+        let ev = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(ev, expected);
+        app.update_async(AsyncNotification::P4(ev)).unwrap();
+    }
+
+    fn key(code: KeyCode) -> InputEvent {
+        InputEvent::Input(Event::Key(KeyEvent::new(code, KeyModifiers::empty())))
+    }
+
+    #[test]
+    fn test_failed_job_keeps_running() {
+        let runner = FixtureRunner::default().with(
+            &["changes", "-l", "-t", "-s", "pending"],
+            P4Output::failure("You don't have permission for this operation.\n"),
+        );
+        let (mut app, rx) = app(Arc::new(runner));
+
+        app.update().unwrap();
+        next_async(&mut app, &rx, AsyncP4Notification::Changelists);
+
+        assert!(matches!(app.do_quit, QuitState::None));
+        assert!(app.msg.is_visible());
+    }
+
+    #[test]
+    fn test_login_retries_update() {
+        let changes = ["changes", "-l", "-t", "-s", "pending"];
+        let runner = Arc::new(
+            FixtureRunner::default()
+                .with(
+                    &changes,
+                    P4Output::failure("Perforce password (P4PASSWD) invalid or unset.\n"),
+                )
+                .with(&changes, P4Output::success(""))
+                .with(&["login"], P4Output::success("User bob logged in.\n")),
+        );
+        let (mut app, rx) = app(runner.clone());
+
+        app.update().unwrap();
+        next_async(&mut app, &rx, AsyncP4Notification::Changelists);
+        assert!(app.login.is_visible());
+
+        app.event(key(KeyCode::Char('s'))).unwrap();
+        app.event(key(KeyCode::Enter)).unwrap();
+        next_async(&mut app, &rx, AsyncP4Notification::Login);
+        assert!(!app.login.is_visible());
+
+        // the failed update runs again without waiting for another key
+        next_async(&mut app, &rx, AsyncP4Notification::Changelists);
+        let calls: Vec<_> = runner
+            .calls()
+            .iter()
+            .map(|c| c.get_args()[0].clone())
+            .collect();
+        assert_eq!(calls, ["changes", "login", "changes"]);
+        assert!(!app.msg.is_visible());
     }
 }
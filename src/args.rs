@@ -1,22 +1,61 @@
 use anyhow::{anyhow, Result};
-use std::{fs, path::PathBuf};
+use asyncp4::sync::OutputFormat;
+use std::{fs, path::PathBuf, time::Duration};
 
-use clap::{crate_name, Arg, Command};
+use clap::{crate_name, Arg, ArgAction, Command};
 
 pub struct CliArgs {
     pub theme: PathBuf,
     pub cwd: PathBuf,
+    pub output_format: OutputFormat,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub timeout: Option<Duration>,
 }
 
 pub fn process_cmdline() -> Result<CliArgs> {
-    let app = Command::new(crate_name!()).arg(
-        Arg::new("theme")
-            .help("Set the color theme (defaults to theme.ron)")
-            .short('t')
-            .long("theme")
-            .value_name("THEME")
-            .num_args(1),
-    );
+    let app = Command::new(crate_name!())
+        .arg(
+            Arg::new("theme")
+                .help("Set the color theme (defaults to theme.ron)")
+                .short('t')
+                .long("theme")
+                .value_name("THEME")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("marshal")
+                .help("Read p4 output as python marshal (p4 -G) instead of tagged text")
+                .long("marshal")
+                .env("P4TUI_MARSHAL")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("record")
+                .help("Record every p4 invocation to a transcript file")
+                .long("record")
+                .env("P4TUI_RECORD")
+                .value_name("FILE")
+                .num_args(1)
+                .conflicts_with("replay"),
+        )
+        .arg(
+            Arg::new("replay")
+                .help("Serve p4 output from a recorded transcript instead of a server")
+                .long("replay")
+                .env("P4TUI_REPLAY")
+                .value_name("FILE")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("timeout")
+                .help("Kill p4 commands running longer than this many seconds")
+                .long("timeout")
+                .env("P4TUI_TIMEOUT")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .num_args(1),
+        );
 
     let arg_matches = app.get_matches();
 
@@ -24,6 +63,18 @@ pub fn process_cmdline() -> Result<CliArgs> {
         .get_one::<String>("theme")
         .map_or_else(|| PathBuf::from("theme.ron"), PathBuf::from);
 
+    let output_format = if arg_matches.get_flag("marshal") {
+        OutputFormat::Marshal
+    } else {
+        OutputFormat::Tagged
+    };
+
+    let record = arg_matches.get_one::<String>("record").map(PathBuf::from);
+    let replay = arg_matches.get_one::<String>("replay").map(PathBuf::from);
+    let timeout = arg_matches
+        .get_one::<u64>("timeout")
+        .map(|secs| Duration::from_secs(*secs));
+
     let cwd = PathBuf::from(".");
 
     let theme = if get_app_config_path()?.join(&arg_theme).is_file() {
@@ -32,7 +83,14 @@ pub fn process_cmdline() -> Result<CliArgs> {
         get_app_config_path()?.join("theme.ron")
     };
 
-    Ok(CliArgs { theme, cwd })
+    Ok(CliArgs {
+        theme,
+        cwd,
+        output_format,
+        record,
+        replay,
+        timeout,
+    })
 }
 
 pub fn get_app_config_path() -> Result<PathBuf> {
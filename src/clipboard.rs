@@ -0,0 +1,61 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+
+fn exec_copy_with_args(command: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut process = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("`{command}`: {e}"))?;
+
+    process
+        .stdin
+        .as_mut()
+        .ok_or_else(|| anyhow!("`{command}`: no stdin"))?
+        .write_all(text.as_bytes())
+        .map_err(|e| anyhow!("`{command}`: {e}"))?;
+
+    let out = process
+        .wait_with_output()
+        .map_err(|e| anyhow!("`{command}`: {e}"))?;
+
+    if out.status.success() {
+        Ok(())
+    } else {
+        let msg = if out.stderr.is_empty() {
+            out.status.to_string()
+        } else {
+            String::from_utf8_lossy(&out.stderr).to_string()
+        };
+        Err(anyhow!("`{command}`: {msg}"))
+    }
+}
+
+#[cfg(all(target_family = "unix", not(target_os = "macos")))]
+pub fn copy_string(text: &str) -> Result<()> {
+    if std::env::var("WAYLAND_DISPLAY").is_ok() {
+        return exec_copy_with_args("wl-copy", &[], text);
+    }
+
+    if exec_copy_with_args("xclip", &["-selection", "clipboard"], text).is_err() {
+        return exec_copy_with_args("xsel", &["--clipboard"], text);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn copy_string(text: &str) -> Result<()> {
+    exec_copy_with_args("pbcopy", &[], text)
+}
+
+#[cfg(windows)]
+pub fn copy_string(text: &str) -> Result<()> {
+    exec_copy_with_args("clip", &[], text)
+}
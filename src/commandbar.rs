@@ -5,10 +5,11 @@ use crate::{components::CommandInfo, keys::SharedKeyConfig, strings, ui::style::
 use ratatui::{
     backend::Backend,
     layout::{Alignment, Rect},
-    text::{Line, Span, Spans},
+    text::{Line, Span},
     widgets::Paragraph,
     Frame,
 };
+use unicode_width::UnicodeWidthStr;
 
 enum DrawListEntry {
     LineBreak,
@@ -51,19 +52,42 @@ impl CommandBar {
 
     pub fn refresh_width(&mut self, width: u16) {
         if width != self.width {
-            self.refresh_width(width);
+            self.refresh_list(width);
             self.width = width;
         }
     }
 
-    // fn is_multiline(&self, width: u16) -> bool {
-    //     let mut line_width = 0_usize;
-    //     for c in &self.cmd_infos {
-    //         let entry_w = Unicode
-    //     }
-    // }
+    /// Lays the commands out in as many lines as `width` requires.
+    fn refresh_list(&mut self, width: u16) {
+        self.draw_list.clear();
 
-    fn refresh_list(&mut self, width: u16) {}
+        let width = usize::from(width.saturating_sub(MORE_WIDTH));
+        let mut line_width = 0_usize;
+        let mut lines = 1_usize;
+
+        for c in &self.cmd_infos {
+            let entry_w = c.text.name.width();
+
+            if line_width > 0 && line_width + entry_w > width {
+                self.draw_list.push(DrawListEntry::LineBreak);
+                line_width = 0;
+                lines += 1;
+            } else if line_width > 0 {
+                self.draw_list.push(DrawListEntry::Splitter);
+            }
+
+            line_width += entry_w + strings::cmd_splitter().width();
+
+            self.draw_list.push(DrawListEntry::Command(Command {
+                txt: c.text.name.clone(),
+                enabled: c.enabled,
+                line: lines - 1,
+            }));
+        }
+
+        self.expandable = lines > 1;
+        self.lines = u16::try_from(lines).unwrap_or(u16::MAX);
+    }
 
     pub fn set_cmds(&mut self, cmds: Vec<CommandInfo>) {
         self.cmd_infos = cmds
@@ -140,3 +164,33 @@ impl CommandBar {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::{CommandInfo, CommandText},
+        keys::KeyConfig,
+        ui::style::Theme,
+    };
+    use std::rc::Rc;
+
+    fn cmd(name: &str) -> CommandInfo {
+        CommandInfo::new(CommandText::new(name.to_string(), "", ""), true, true)
+    }
+
+    #[test]
+    fn test_wraps_commands() {
+        let mut bar = CommandBar::new(Rc::new(KeyConfig::default()), Rc::new(Theme::default()));
+        bar.set_cmds(vec![cmd("aaaa"), cmd("bbbb"), cmd("cccc")]);
+
+        bar.refresh_width(MORE_WIDTH + 10);
+        assert_eq!(bar.height(), 1);
+        bar.toggle_more();
+        assert_eq!(bar.height(), 2);
+
+        bar.refresh_width(MORE_WIDTH + 20);
+        assert!(!bar.expandable);
+        assert_eq!(bar.height(), 1);
+    }
+}
@@ -2,28 +2,38 @@ use std::cell::Cell;
 
 use anyhow::Result;
 use asyncp4::sync;
+use chrono::{DateTime, Local, TimeZone};
 use crossterm::event::{Event, KeyEvent};
 
 use crate::{
     keys::{key_match, SharedKeyConfig},
+    strings,
     ui::style::SharedTheme,
 };
 
 use ratatui::{
     backend::Backend,
     layout::Rect,
-    text::Span,
-    widgets::{Block, Borders},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
     Frame,
 };
 
-use super::{CommandBlocking, CommandInfo, Component, DrawableComponent, EventState, ScrollType};
+use super::{
+    utils::scroll_vertical::VerticalScroll, CommandBlocking, CommandInfo, Component,
+    DrawableComponent, EventState, ScrollType,
+};
+
+const NUMBER_WIDTH: usize = 7;
+const USER_WIDTH: usize = 10;
+const CLIENT_WIDTH: usize = 16;
 
 pub struct ChangelistComponent {
     focused: bool,
     status: sync::ChangelistStatus,
-    changelists: Vec<u32>,
-    selection: u16,
+    changelists: Vec<sync::Changelist>,
+    selection: usize,
+    scroll: VerticalScroll,
     current_height: Cell<u16>,
     key_config: SharedKeyConfig,
     theme: SharedTheme,
@@ -42,84 +52,182 @@ impl ChangelistComponent {
             theme,
             changelists: Vec::new(),
             selection: 0,
+            scroll: VerticalScroll::new(),
             current_height: Cell::new(0),
         }
     }
 
-    pub fn update(&mut self) -> Result<()> {
-        Ok(())
+    pub fn set_changelists(&mut self, changelists: Vec<sync::Changelist>) {
+        // keep the selected changelist selected if it is still there
+        let selected = self.get_selected().map(|c| c.number);
+
+        self.changelists = changelists;
+        let selection = selected
+            .and_then(|number| self.changelists.iter().position(|c| c.number == number))
+            .unwrap_or(self.selection);
+
+        self.set_selection(selection);
     }
 
-    pub fn get_changelists(&self) -> &Vec<u32> {
+    /// Appends a page of older changelists.
+    pub fn extend(&mut self, changelists: Vec<sync::Changelist>) {
+        self.changelists.extend(changelists);
+    }
+
+    /// Whether the selection is less than a screen away from the last
+    /// changelist, so that more should be loaded.
+    pub fn is_near_end(&self) -> bool {
+        self.selection + usize::from(self.current_height.get()) >= self.changelists.len()
+    }
+
+    pub fn get_changelists(&self) -> &Vec<sync::Changelist> {
         &self.changelists
     }
 
-    pub fn get_selected(&self) -> Option<u32> {
-        self.changelists
-            .get(self.selection as usize)
-            .map(|c| c.clone())
+    pub fn get_selected(&self) -> Option<&sync::Changelist> {
+        self.changelists.get(self.selection)
     }
 
-    fn move_event(&mut self, e: &KeyEvent) -> Result<EventState> {
+    fn move_event(&mut self, e: &KeyEvent) -> EventState {
         if key_match(e, self.key_config.keys.move_down) {
-            return self.move_selection(ScrollType::Down).map(Into::into);
+            return self.move_selection(ScrollType::Down).into();
         } else if key_match(e, self.key_config.keys.move_up) {
-            return self.move_selection(ScrollType::Up).map(Into::into);
+            return self.move_selection(ScrollType::Up).into();
         }
 
-        Ok(EventState::NotConsumed)
+        EventState::NotConsumed
     }
 
-    pub fn move_selection(&mut self, scroll: ScrollType) -> Result<bool> {
+    pub fn move_selection(&mut self, scroll: ScrollType) -> bool {
+        let height = usize::from(self.current_height.get());
         let new_selection = match scroll {
-            ScrollType::Up => self.selection.saturating_add(1),
-            ScrollType::Down => self.selection.saturating_sub(1),
-            ScrollType::PageDown => self.selection.saturating_add(self.current_height.get()),
-            ScrollType::PageUp => self.selection.saturating_add(self.current_height.get()),
+            ScrollType::Up => self.selection.saturating_sub(1),
+            ScrollType::Down => self.selection.saturating_add(1),
+            ScrollType::PageDown => self.selection.saturating_add(height),
+            ScrollType::PageUp => self.selection.saturating_sub(height),
             ScrollType::Home => 0,
-            ScrollType::End => self.changelists.len().try_into()?,
+            ScrollType::End => self.changelists.len(),
         };
 
-        self.set_selection(new_selection)?;
+        let old = self.selection;
+        self.set_selection(new_selection);
 
-        Ok(true)
+        old != self.selection
     }
 
-    fn set_selection(&mut self, selection: u16) -> Result<()> {
-        let num_changelists: u16 = self.changelists.len().try_into()?;
-        let num_changelists = num_changelists.saturating_sub(1);
+    fn set_selection(&mut self, selection: usize) {
+        self.selection = selection.min(self.changelists.len().saturating_sub(1));
+    }
 
-        let selection = if selection > num_changelists {
-            num_changelists
+    fn get_entry_to_add<'a>(&self, changelist: &'a sync::Changelist, selected: bool) -> Line<'a> {
+        let number = if changelist.is_default() {
+            "default".to_string()
         } else {
-            selection
+            changelist.number.to_string()
         };
 
-        self.selection = selection;
-        Ok(())
+        // marks pending changelists that also have shelved files
+        let shelved = if changelist.has_shelved { "*" } else { " " };
+
+        let time = if changelist.is_default() {
+            String::new()
+        } else {
+            time_to_string(changelist.time)
+        };
+
+        let user: String = changelist.user.chars().take(USER_WIDTH).collect();
+
+        let mut description = changelist.short_description().to_string();
+        if let Some(count) = changelist.file_count {
+            description = format!("{description} ({count} files)");
+        }
+
+        let mut spans = vec![
+            Span::styled(
+                format!("{number:>NUMBER_WIDTH$}{shelved} "),
+                self.theme.changelist_number(selected),
+            ),
+            Span::styled(format!("{time:<16} "), self.theme.changelist_time(selected)),
+            Span::styled(
+                format!("{user:<USER_WIDTH$} "),
+                self.theme.changelist_user(selected),
+            ),
+        ];
+
+        // pending changelists are all of the current client
+        if self.status == sync::ChangelistStatus::Submitted {
+            let client: String = changelist.client.chars().take(CLIENT_WIDTH).collect();
+            spans.push(Span::styled(
+                format!("{client:<CLIENT_WIDTH$} "),
+                self.theme.changelist_client(selected),
+            ));
+        }
+
+        spans.push(Span::styled(
+            description,
+            self.theme.changelist_description(selected),
+        ));
+
+        Line::from(spans)
     }
 }
 
+pub fn time_to_string(time: i64) -> String {
+    let date: Option<DateTime<Local>> = Local.timestamp_opt(time, 0).single();
+
+    date.map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
 impl DrawableComponent for ChangelistComponent {
     fn draw<B: Backend>(&self, f: &mut Frame<B>, r: Rect) -> Result<()> {
-        let b = Block::default()
-            .title(Span::styled("Changelists", self.theme.title(self.focused)))
-            .borders(Borders::ALL)
-            .border_style(self.theme.block(self.focused));
+        let height = r.height.saturating_sub(2);
+        self.current_height.set(height);
+
+        let top = self
+            .scroll
+            .update(self.selection, self.changelists.len(), height as usize);
+
+        let lines = self
+            .changelists
+            .iter()
+            .enumerate()
+            .skip(top)
+            .take(height as usize)
+            .map(|(i, c)| self.get_entry_to_add(c, self.focused && i == self.selection))
+            .collect::<Vec<_>>();
+
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(Span::styled(
+                        strings::changelists_title(),
+                        self.theme.title(self.focused),
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(self.theme.block(self.focused)),
+            ),
+            r,
+        );
+
+        self.scroll.draw(f, r, &self.theme);
 
-        f.render_widget(b, r);
         Ok(())
     }
 }
 
 impl Component for ChangelistComponent {
-    fn commands(&self, out: &mut Vec<CommandInfo>, force_all: bool) -> CommandBlocking {
+    fn commands(&self, _out: &mut Vec<CommandInfo>, _force_all: bool) -> CommandBlocking {
         CommandBlocking::PassingOn
     }
 
     fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.focused {
+            return Ok(EventState::NotConsumed);
+        }
+
         if let Event::Key(e) = ev {
-            if self.move_event(e)?.is_consumed() {
+            if self.move_event(e).is_consumed() {
                 return Ok(EventState::Consumed);
             }
         }
@@ -134,3 +242,35 @@ impl Component for ChangelistComponent {
         self.focused = focus;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keys::KeyConfig, ui::style::Theme};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_more_than_u16_changelists() {
+        let mut list = ChangelistComponent::new(
+            sync::ChangelistStatus::Submitted,
+            Rc::new(KeyConfig::default()),
+            Rc::new(Theme::default()),
+        );
+        list.set_changelists(
+            (1..=70_000)
+                .map(|number| sync::Changelist {
+                    number,
+                    ..sync::Changelist::new()
+                })
+                .collect(),
+        );
+
+        assert!(list.move_selection(ScrollType::End));
+        assert_eq!(list.get_selected().map(|c| c.number), Some(70_000));
+
+        // the selected changelist is kept when the list is reloaded
+        assert!(list.move_selection(ScrollType::Up));
+        list.set_changelists(list.get_changelists()[1..].to_vec());
+        assert_eq!(list.get_selected().map(|c| c.number), Some(69_999));
+    }
+}
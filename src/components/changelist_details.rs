@@ -0,0 +1,163 @@
+use std::cell::Cell;
+
+use anyhow::Result;
+use asyncp4::sync;
+use crossterm::event::Event;
+use ratatui::{
+    backend::Backend,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use crate::{
+    keys::{key_match, SharedKeyConfig},
+    strings,
+    ui::style::SharedTheme,
+};
+
+use super::{
+    changelist::time_to_string, utils::scroll_vertical::VerticalScroll, CommandBlocking,
+    CommandInfo, Component, DrawableComponent, EventState, ScrollType,
+};
+
+/// Header and full description of a changelist as loaded by `p4 describe`.
+pub struct ChangelistDetailsComponent {
+    focused: bool,
+    changelist: Option<sync::Changelist>,
+    scroll: VerticalScroll,
+    current_height: Cell<usize>,
+    key_config: SharedKeyConfig,
+    theme: SharedTheme,
+}
+
+impl ChangelistDetailsComponent {
+    pub fn new(key_config: SharedKeyConfig, theme: SharedTheme) -> Self {
+        Self {
+            focused: false,
+            changelist: None,
+            scroll: VerticalScroll::new(),
+            current_height: Cell::new(0),
+            key_config,
+            theme,
+        }
+    }
+
+    pub fn set_changelist(&mut self, changelist: Option<sync::Changelist>) {
+        self.changelist = changelist;
+        self.scroll.reset();
+    }
+
+    fn get_text(&self) -> Vec<Line<'_>> {
+        let Some(changelist) = &self.changelist else {
+            return Vec::new();
+        };
+
+        let mut txt = vec![
+            Line::from(vec![
+                Span::styled(
+                    strings::changelist_details_number(changelist.number),
+                    self.theme.changelist_number(false),
+                ),
+                Span::styled(
+                    strings::changelist_details_author(&changelist.user, &changelist.client),
+                    self.theme.changelist_user(false),
+                ),
+                Span::styled(
+                    strings::changelist_details_time(&time_to_string(changelist.time)),
+                    self.theme.changelist_time(false),
+                ),
+            ]),
+            Line::default(),
+        ];
+
+        txt.extend(
+            changelist
+                .description
+                .lines()
+                .map(|line| Line::from(line.trim_end())),
+        );
+
+        if let Some(count) = changelist.file_count {
+            txt.push(Line::default());
+            txt.push(Line::from(Span::styled(
+                strings::changelist_details_files(count),
+                self.theme.text(false, false),
+            )));
+        }
+
+        txt
+    }
+}
+
+impl DrawableComponent for ChangelistDetailsComponent {
+    fn draw<B: Backend>(&self, f: &mut Frame<B>, r: Rect) -> Result<()> {
+        let height = r.height.saturating_sub(2) as usize;
+        self.current_height.set(height);
+
+        let txt = self.get_text();
+        self.scroll.update_no_selection(txt.len(), height);
+
+        let lines = txt
+            .into_iter()
+            .skip(self.scroll.get_top())
+            .take(height)
+            .collect::<Vec<_>>();
+
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(Span::styled(
+                        strings::changelist_details_title(),
+                        self.theme.title(self.focused),
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(self.theme.block(self.focused)),
+            ),
+            r,
+        );
+
+        if self.focused {
+            self.scroll.draw(f, r, &self.theme);
+        }
+
+        Ok(())
+    }
+}
+
+impl Component for ChangelistDetailsComponent {
+    fn commands(&self, out: &mut Vec<CommandInfo>, _force_all: bool) -> CommandBlocking {
+        out.push(CommandInfo::new(
+            strings::commands::scroll(&self.key_config),
+            self.get_text().len() > self.current_height.get(),
+            self.focused,
+        ));
+
+        CommandBlocking::PassingOn
+    }
+
+    fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.focused {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if let Event::Key(e) = ev {
+            if key_match(e, self.key_config.keys.move_down) {
+                return Ok(self.scroll.move_top(ScrollType::Down).into());
+            } else if key_match(e, self.key_config.keys.move_up) {
+                return Ok(self.scroll.move_top(ScrollType::Up).into());
+            }
+        }
+
+        Ok(EventState::NotConsumed)
+    }
+
+    fn focused(&self) -> bool {
+        self.focused
+    }
+
+    fn focus(&mut self, focus: bool) {
+        self.focused = focus;
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    keys::{key_match, SharedKeyConfig},
+    queue::{Action, InternalEvent, Queue},
+    strings,
+    ui::{self, style::SharedTheme},
+};
+
+use super::{
+    visibility_blocking, CommandBlocking, CommandInfo, Component, DrawableComponent, EventState,
+};
+
+use anyhow::Result;
+use crossterm::event::Event;
+use ratatui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    text::Span,
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Asks before running a destructive [`Action`].
+pub struct ConfirmComponent {
+    target: Option<Action>,
+    visible: bool,
+    queue: Queue,
+    key_config: SharedKeyConfig,
+    theme: SharedTheme,
+}
+
+impl DrawableComponent for ConfirmComponent {
+    fn draw<B: Backend>(&self, f: &mut Frame<B>, _rect: Rect) -> Result<()> {
+        if self.visible {
+            const SIZE: (u16, u16) = (50, 6);
+
+            let (title, msg) = self.get_text();
+
+            let area = ui::centered_rect_absolute(SIZE.0, SIZE.1, f.size());
+
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(msg)
+                    .block(
+                        Block::default()
+                            .title(Span::styled(title, self.theme.title(true)))
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Thick),
+                    )
+                    .alignment(Alignment::Left)
+                    .wrap(Wrap { trim: true }),
+                area,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Component for ConfirmComponent {
+    fn commands(&self, out: &mut Vec<CommandInfo>, _force_all: bool) -> CommandBlocking {
+        out.push(CommandInfo::new(
+            strings::commands::confirm_action(&self.key_config),
+            true,
+            self.visible,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::close_popup(&self.key_config),
+            true,
+            self.visible,
+        ));
+
+        visibility_blocking(self)
+    }
+
+    fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.visible {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if let Event::Key(e) = ev {
+            if key_match(e, self.key_config.keys.exit_popup) {
+                self.hide();
+            } else if key_match(e, self.key_config.keys.enter) {
+                self.confirm();
+            }
+        }
+
+        Ok(EventState::Consumed)
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn hide(&mut self) {
+        self.visible = false;
+        self.target = None;
+    }
+
+    fn show(&mut self) -> Result<()> {
+        self.visible = true;
+
+        Ok(())
+    }
+}
+
+impl ConfirmComponent {
+    pub const fn new(queue: Queue, key_config: SharedKeyConfig, theme: SharedTheme) -> Self {
+        Self {
+            target: None,
+            visible: false,
+            queue,
+            key_config,
+            theme,
+        }
+    }
+
+    pub fn open(&mut self, action: Action) -> Result<()> {
+        self.target = Some(action);
+        self.show()
+    }
+
+    fn confirm(&mut self) {
+        if let Some(action) = self.target.take() {
+            self.queue.push(InternalEvent::ConfirmedAction(action));
+        }
+
+        self.hide();
+    }
+
+    fn get_text(&self) -> (String, String) {
+        match &self.target {
+            Some(Action::DiscardHunk(path, _)) => (
+                strings::confirm_title_discard_hunk(),
+                strings::confirm_msg_discard_hunk(&path.to_string_lossy()),
+            ),
+            None => (String::new(), String::new()),
+        }
+    }
+}
@@ -1,74 +1,1647 @@
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+    rc::Rc,
+};
+
 use anyhow::Result;
-use crossterm::event::Event;
+use asyncp4::{
+    asyncjob::AsyncSingleJob,
+    sync::{ChangedBytes, DiffLine, DiffLineType, DiffSegment, FileDiff, FileType, Hunk, P4Path},
+};
+use crossbeam_channel::Sender;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 
-use crate::{keys::SharedKeyConfig, ui::style::SharedTheme};
+use crate::{
+    clipboard,
+    formatter::{AsyncFormatJob, FormatParams},
+    keys::{key_match, SharedKeyConfig},
+    options::SharedOptions,
+    queue::{Action, InternalEvent, Queue},
+    strings::{self, symbol},
+    ui::{
+        ansi,
+        style::SharedTheme,
+        syntax_text::{
+            AsyncHighlightJob, DiffHighlights, HighlightParams, LineHighlights, SyntaxCache,
+        },
+    },
+    AsyncAppNotification,
+};
 
 use super::{
-    utils::scroll_vertical::VerticalScroll, visibility_blocking, CommandBlocking, CommandInfo,
-    Component, DrawableComponent, EventState,
+    utils::{scroll_horizontal::HorizontalScroll, scroll_vertical::VerticalScroll, search::Search},
+    CommandBlocking, CommandInfo, Component, DrawableComponent, EventState, HorizontalScrollType,
+    ScrollType,
 };
 
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
 use ratatui::{
     backend::Backend,
-    layout::Rect,
-    text::Span,
-    widgets::{Block, Borders},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
     Frame,
 };
 
+/// Bytes per row of the hex dump.
+const HEX_DUMP_ROW: usize = 16;
+
+/// A line of the diff as `(hunk, line)` indices.
+type LineIndex = (usize, usize);
+
+/// One row of the side-by-side view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SplitRow {
+    Header(LineIndex),
+    /// old and new side, `None` being a filler
+    Lines(Option<LineIndex>, Option<LineIndex>),
+}
+
+/// Output of the configured diff formatter for the current diff.
+struct FormattedDiff {
+    lines: Vec<Line<'static>>,
+    max_width: usize,
+}
+
 pub struct DiffComponent {
     focused: bool,
+    path: Option<P4Path>,
+    diff: Option<FileDiff>,
+    file_type: Option<FileType>,
+    /// changed bytes of a binary or too large file, on request
+    hex_dump: Option<ChangedBytes>,
+    pending: bool,
+    split: bool,
+    wrap: bool,
+    show_whitespace: bool,
+    use_formatter: bool,
+    formatted: Option<FormattedDiff>,
+    format_job: AsyncSingleJob<AsyncFormatJob>,
+    /// formatter run last requested for the current diff, the raw diff is
+    /// shown until it finishes
+    format_params: Option<FormatParams>,
+    /// width of the view at the last draw, which the formatter fills
+    format_width: Cell<usize>,
+    split_rows: Vec<SplitRow>,
+    /// content width and rows taken by each line, or split row, when
+    /// wrapping
+    row_heights: RefCell<Option<(usize, Vec<usize>)>>,
+    selected_hunk: Option<usize>,
+    search: Search,
+    /// search matches, ordered by line and position
+    matches: Vec<(LineIndex, Range<usize>)>,
+    /// index into `matches` of the match jumped to
+    current_match: Option<usize>,
+    highlights: Option<Rc<DiffHighlights>>,
+    /// cache key of the highlights of the current diff, which stays plain
+    /// until they are done
+    highlight_key: Option<u64>,
+    highlight_job: AsyncSingleJob<AsyncHighlightJob>,
+    syntax_cache: SyntaxCache,
+    number_width: usize,
+    max_line_width: usize,
+    current_height: Cell<usize>,
     vertical_scroll: VerticalScroll,
-    // horizontal_scroll
+    horizontal_scroll: HorizontalScroll,
+    /// diffs of files that are not opened can't have hunks discarded
+    is_immutable: bool,
+    queue: Queue,
+    options: SharedOptions,
     key_config: SharedKeyConfig,
     theme: SharedTheme,
 }
 
 impl DiffComponent {
-    pub fn new(key_config: SharedKeyConfig, theme: SharedTheme) -> Self {
+    pub fn new(
+        sender: &Sender<AsyncAppNotification>,
+        queue: Queue,
+        options: SharedOptions,
+        key_config: SharedKeyConfig,
+        theme: SharedTheme,
+        is_immutable: bool,
+    ) -> Self {
         Self {
             focused: false,
+            path: None,
+            diff: None,
+            file_type: None,
+            hex_dump: None,
+            pending: false,
+            split: false,
+            wrap: false,
+            show_whitespace: false,
+            use_formatter: true,
+            formatted: None,
+            format_job: AsyncSingleJob::new(sender.clone()),
+            format_params: None,
+            format_width: Cell::new(0),
+            split_rows: Vec::new(),
+            row_heights: RefCell::new(None),
+            selected_hunk: None,
+            search: Search::default(),
+            matches: Vec::new(),
+            current_match: None,
+            highlights: None,
+            highlight_key: None,
+            highlight_job: AsyncSingleJob::new(sender.clone()),
+            syntax_cache: SyntaxCache::default(),
+            number_width: 0,
+            max_line_width: 0,
+            current_height: Cell::new(0),
             vertical_scroll: VerticalScroll::new(),
+            horizontal_scroll: HorizontalScroll::new(),
+            is_immutable,
+            queue,
+            options,
             key_config,
             theme,
         }
     }
 
     fn can_scroll(&self) -> bool {
-        // TODO: fix!
-        false
+        self.line_count() > self.current_height.get()
+    }
+
+    /// Columns left for line content of a diff drawn `width` wide.
+    fn content_width(&self, width: u16) -> usize {
+        let inner = usize::from(width.saturating_sub(2));
+
+        // line numbers plus the +/- column
+        if self.split {
+            (inner / 2).saturating_sub(self.number_width + 3)
+        } else {
+            inner.saturating_sub(2 * self.number_width + 3)
+        }
+    }
+
+    fn line_count(&self) -> usize {
+        if let Some(diff) = self.diff.as_ref().filter(|d| shows_summary(d)) {
+            self.get_summary_text(diff).len()
+        } else if let Some(formatted) = &self.formatted {
+            formatted.lines.len()
+        } else if let Some((_, heights)) = &*self.row_heights.borrow() {
+            heights.iter().sum()
+        } else if self.split {
+            self.split_rows.len()
+        } else {
+            self.diff.as_ref().map_or(0, |d| d.lines)
+        }
+    }
+
+    /// Row of the view that line, or split row, `item` starts at.
+    fn visual_row(&self, item: usize) -> usize {
+        self.row_heights
+            .borrow()
+            .as_ref()
+            .map_or(item, |(_, heights)| heights.iter().take(item).sum())
+    }
+
+    /// Wraps lines at `width` columns if wrapping is on, keeping the
+    /// result until the width or the content changes.
+    fn update_row_heights(&self, width: usize) {
+        let Some(diff) = self
+            .diff
+            .as_ref()
+            .filter(|d| self.wrap && !shows_summary(d) && !self.uses_formatter())
+        else {
+            self.row_heights.replace(None);
+            return;
+        };
+
+        if self
+            .row_heights
+            .borrow()
+            .as_ref()
+            .is_some_and(|(w, _)| *w == width)
+        {
+            return;
+        }
+
+        let line_height = |index: Option<LineIndex>| {
+            index
+                .and_then(|i| self.get_line(i))
+                .map_or(1, |line| self.line_height(line, width))
+        };
+
+        let heights = if self.split {
+            self.split_rows
+                .iter()
+                .map(|row| match *row {
+                    SplitRow::Header(_) => 1,
+                    SplitRow::Lines(old, new) => line_height(old).max(line_height(new)),
+                })
+                .collect()
+        } else {
+            diff.hunks
+                .iter()
+                .flat_map(|h| h.lines.iter())
+                .map(|line| self.line_height(line, width))
+                .collect()
+        };
+
+        self.row_heights.replace(Some((width, heights)));
+    }
+
+    /// Starts the diff formatter, if configured, for the current diff at
+    /// the width it was last drawn with, unless it already ran for both.
+    pub fn update_formatter(&mut self) {
+        let width = self.format_width.get();
+        let command = self
+            .use_formatter
+            .then(|| self.options.borrow().diff_formatter())
+            .flatten()
+            .filter(|_| width > 0);
+        let request = match (command, &self.path, &self.diff) {
+            (Some(command), Some(path), Some(diff)) if !shows_summary(diff) => {
+                Some((command, path, diff))
+            }
+            _ => None,
+        };
+        let Some((command, path, diff)) = request else {
+            self.reset_formatted();
+            return;
+        };
+
+        if self
+            .format_params
+            .as_ref()
+            .is_some_and(|p| p.command == command && p.width == width)
+        {
+            return;
+        }
+
+        let params = FormatParams {
+            command,
+            width,
+            diff: diff_to_string(path, diff),
+        };
+        self.reset_formatted();
+        self.format_job.spawn(AsyncFormatJob::new(params.clone()));
+        self.format_params = Some(params);
+    }
+
+    pub fn update_async(&mut self, ev: AsyncAppNotification) {
+        match ev {
+            AsyncAppNotification::Formatted => self.update_formatted(),
+            AsyncAppNotification::Highlighted => self.update_highlights(),
+        }
+    }
+
+    /// Picks up the formatter output if it is still for the current diff.
+    fn update_formatted(&mut self) {
+        let Some((params, result)) = self.format_job.take_last().and_then(|job| job.result())
+        else {
+            return;
+        };
+        if self.format_params.as_ref() != Some(&params) {
+            return;
+        }
+
+        match result {
+            Ok(output) => {
+                let lines = ansi::to_lines(&output);
+                let max_width = lines.iter().map(Line::width).max().unwrap_or_default();
+                self.formatted = Some(FormattedDiff { lines, max_width });
+                self.row_heights.replace(None);
+            }
+            // the raw diff stays, the formatter is not run again for it
+            Err(e) => self.queue.push(InternalEvent::ShowErrorMsg(e.to_string())),
+        }
+    }
+
+    /// Picks up the highlights if they are still for the current diff.
+    fn update_highlights(&mut self) {
+        let Some((key, highlights)) = self.highlight_job.take_last().and_then(|job| job.result())
+        else {
+            return;
+        };
+
+        // cached either way, the diff may be shown again later
+        let highlights = self.syntax_cache.insert(key, highlights);
+        if self.highlight_key == Some(key) {
+            self.highlights = highlights;
+        }
+    }
+
+    /// Highlights `diff` from the cache, or starts highlighting it.
+    fn request_highlights(&mut self, path: &P4Path, file_type: Option<&FileType>, diff: &FileDiff) {
+        let path = path.to_string_lossy();
+        let syntax_theme = self.theme.get_syntax();
+        let key = SyntaxCache::key(&path, file_type, diff, syntax_theme);
+
+        if let Some(highlights) = self.syntax_cache.get(key) {
+            self.reset_highlights();
+            self.highlight_key = Some(key);
+            self.highlights = highlights;
+            return;
+        }
+
+        if self.highlight_key == Some(key) && self.highlight_job.is_pending() {
+            return;
+        }
+
+        let params = HighlightParams {
+            key,
+            path: path.to_string(),
+            file_type: file_type.cloned(),
+            diff: diff.clone(),
+            syntax_theme: syntax_theme.to_string(),
+        };
+        self.reset_highlights();
+        self.highlight_job.spawn(AsyncHighlightJob::new(params));
+        self.highlight_key = Some(key);
+    }
+
+    /// Drops the highlights and cancels a running highlighter.
+    fn reset_highlights(&mut self) {
+        self.highlight_job.cancel();
+        self.highlight_key = None;
+        self.highlights = None;
+    }
+
+    /// Drops the formatter output and cancels a running formatter.
+    fn reset_formatted(&mut self) {
+        self.format_job.cancel();
+        self.format_params = None;
+        self.formatted = None;
+    }
+
+    fn uses_formatter(&self) -> bool {
+        self.formatted.is_some()
+    }
+
+    fn line_height(&self, line: &DiffLine, width: usize) -> usize {
+        if line.line_type == DiffLineType::Header {
+            return 1;
+        }
+
+        let style = Style::default();
+        let pieces = display_pieces(
+            &line.content,
+            0..line.content.len(),
+            style,
+            self.show_whitespace.then_some(style),
+        );
+
+        wrap_columns(pieces, width).len()
+    }
+
+    fn update_max_line_width(&mut self) {
+        let show_whitespace = self.show_whitespace;
+
+        self.max_line_width = self.diff.as_ref().map_or(0, |diff| {
+            diff.hunks
+                .iter()
+                .flat_map(|h| h.lines.iter())
+                .filter(|l| l.line_type != DiffLineType::Header)
+                .map(|l| {
+                    let style = Style::default();
+                    display_pieces(
+                        &l.content,
+                        0..l.content.len(),
+                        style,
+                        show_whitespace.then_some(style),
+                    )
+                    .iter()
+                    .map(|(text, _)| text.width())
+                    .sum()
+                })
+                .max()
+                .unwrap_or_default()
+        });
     }
 
     pub fn clear(&mut self, pending: bool) {
+        self.path = None;
+        self.diff = None;
+        self.hex_dump = None;
+        self.split_rows.clear();
+        self.row_heights.replace(None);
+        self.reset_formatted();
+        self.selected_hunk = None;
+        self.matches.clear();
+        self.current_match = None;
+        self.reset_highlights();
+        self.pending = pending;
+        self.vertical_scroll.reset();
+        self.horizontal_scroll.reset();
+    }
+
+    /// Marks `path` as loading, keeping the old diff on screen meanwhile.
+    pub fn set_pending(&mut self, path: &P4Path) {
+        self.pending = true;
+
+        if self.path.as_ref() != Some(path) {
+            self.path = Some(path.clone());
+            self.diff = None;
+            self.hex_dump = None;
+            self.split_rows.clear();
+            self.row_heights.replace(None);
+            self.reset_formatted();
+            self.selected_hunk = None;
+            self.matches.clear();
+            self.current_match = None;
+            self.reset_highlights();
+            self.vertical_scroll.reset();
+            self.horizontal_scroll.reset();
+        }
+    }
+
+    pub fn update(&mut self, path: P4Path, file_type: Option<FileType>, diff: FileDiff) {
+        self.pending = false;
+
+        if self.path.as_ref() != Some(&path) {
+            self.vertical_scroll.reset();
+            self.horizontal_scroll.reset();
+        }
+
+        // the changed bytes may differ even where the sizes don't, so a hex
+        // dump shown for this file is loaded again
+        let reload_hex_dump = self.hex_dump.take().is_some() && self.path.as_ref() == Some(&path);
+
+        // both line number columns are as wide as the largest number
+        self.number_width = diff
+            .hunks
+            .iter()
+            .flat_map(|h| h.lines.iter())
+            .flat_map(|l| [l.position.old_lineno, l.position.new_lineno])
+            .flatten()
+            .max()
+            .unwrap_or_default()
+            .to_string()
+            .len();
+
+        self.split_rows = split_rows(&diff);
+        self.row_heights.replace(None);
+        self.reset_formatted();
+        self.update_selected_hunk(&diff);
+        self.request_highlights(&path, file_type.as_ref(), &diff);
+        self.file_type = file_type;
+        self.path = Some(path);
+        self.diff = Some(diff);
+        self.update_max_line_width();
+        self.update_matches();
+
+        if reload_hex_dump {
+            self.toggle_hex_dump();
+        }
+    }
+
+    /// Diffs of files that are not opened, e.g. shelved ones, can't have
+    /// hunks discarded.
+    pub fn set_immutable(&mut self, is_immutable: bool) {
+        self.is_immutable = is_immutable;
+    }
+
+    /// Shows `bytes` if the diff of `path` is still displayed.
+    pub fn set_hex_dump(&mut self, path: &P4Path, bytes: ChangedBytes) {
+        if self.path.as_ref() == Some(path) {
+            self.hex_dump = Some(bytes);
+        }
+    }
+
+    fn can_hex_dump(&self) -> bool {
+        !self.is_immutable && !self.pending && self.diff.as_ref().is_some_and(shows_summary)
+    }
+
+    fn toggle_hex_dump(&mut self) {
+        if self.hex_dump.take().is_some() || !self.can_hex_dump() {
+            return;
+        }
+
+        if let Some(path) = &self.path {
+            self.queue.push(InternalEvent::LoadHexDump(path.clone()));
+        }
+    }
+
+    /// Keeps the selected hunk selected across refreshes as long as its
+    /// header is unchanged.
+    fn update_selected_hunk(&mut self, diff: &FileDiff) {
+        let hash = self.get_selected_hunk().map(|h| h.header_hash);
+
+        self.selected_hunk = hash
+            .and_then(|hash| diff.hunks.iter().position(|h| h.header_hash == hash))
+            .or_else(|| {
+                self.selected_hunk
+                    .map(|i| i.min(diff.hunks.len().saturating_sub(1)))
+            })
+            .filter(|_| !diff.hunks.is_empty());
+    }
+
+    fn get_selected_hunk(&self) -> Option<&Hunk> {
+        self.diff.as_ref()?.hunks.get(self.selected_hunk?)
+    }
+
+    fn move_hunk(&mut self, next: bool) -> bool {
+        let count = self.diff.as_ref().map_or(0, |d| d.hunks.len());
+        if count == 0 || self.uses_formatter() {
+            return false;
+        }
+
+        let selected = match (self.selected_hunk, next) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(i), true) => (i + 1).min(count - 1),
+            (Some(i), false) => i.saturating_sub(1),
+        };
+        self.selected_hunk = Some(selected);
+
+        let (start, end) = self.hunk_rows(selected);
+        self.vertical_scroll
+            .move_area_to_visible(self.current_height.get(), start, end);
+
+        true
+    }
+
+    /// First and past-the-end row of hunk `index` in the current view.
+    fn hunk_rows(&self, index: usize) -> (usize, usize) {
+        let (start, end) = if self.split {
+            let header = |hunk| {
+                self.split_rows
+                    .iter()
+                    .position(|r| *r == SplitRow::Header((hunk, 0)))
+            };
+
+            (
+                header(index).unwrap_or_default(),
+                header(index + 1).unwrap_or(self.split_rows.len()),
+            )
+        } else {
+            let hunks = self.diff.as_ref().map_or(&[][..], |d| d.hunks.as_slice());
+            let start = hunks.iter().take(index).map(|h| h.lines.len()).sum();
+
+            (start, start + hunks.get(index).map_or(0, |h| h.lines.len()))
+        };
+
+        (self.visual_row(start), self.visual_row(end))
+    }
+
+    fn copy_hunk(&self) {
+        let Some(hunk) = self.get_selected_hunk().filter(|_| self.can_select_hunk()) else {
+            return;
+        };
+
+        if let Err(e) = clipboard::copy_string(&hunk_to_string(hunk)) {
+            self.queue.push(InternalEvent::ShowErrorMsg(e.to_string()));
+        }
+    }
+
+    /// The selected hunk is not visible in formatter output.
+    fn can_select_hunk(&self) -> bool {
+        !self.uses_formatter() && self.get_selected_hunk().is_some()
+    }
+
+    fn can_discard_hunk(&self) -> bool {
+        !self.is_immutable && !self.pending && self.can_select_hunk()
+    }
+
+    fn discard_hunk(&self) {
+        if !self.can_discard_hunk() {
+            return;
+        }
+
+        if let (Some(path), Some(hunk)) = (&self.path, self.get_selected_hunk()) {
+            self.queue
+                .push(InternalEvent::ConfirmAction(Action::DiscardHunk(
+                    path.clone(),
+                    hunk.clone(),
+                )));
+        }
+    }
+
+    /// Search works on the built-in view of a diff shown line by line.
+    fn can_search(&self) -> bool {
+        !self.uses_formatter() && self.diff.as_ref().is_some_and(|d| !shows_summary(d))
+    }
+
+    /// Finds the matches of the search, keeping the current one within
+    /// bounds.
+    fn update_matches(&mut self) {
+        let search = &self.search;
+
+        self.matches = self.diff.as_ref().map_or_else(Vec::new, |diff| {
+            diff.hunks
+                .iter()
+                .enumerate()
+                .flat_map(|(h, hunk)| {
+                    hunk.lines
+                        .iter()
+                        .enumerate()
+                        .map(move |(l, line)| ((h, l), line))
+                })
+                .filter(|(_, line)| line.line_type != DiffLineType::Header)
+                .flat_map(|(index, line)| {
+                    search
+                        .find(&line.content)
+                        .into_iter()
+                        .map(move |range| (index, range))
+                })
+                .collect()
+        });
+
+        self.current_match = self
+            .current_match
+            .map(|i| i.min(self.matches.len().saturating_sub(1)))
+            .filter(|_| !self.matches.is_empty());
+    }
+
+    /// Jumps to the first match from the top of the view on for the
+    /// changed query or mode.
+    fn search_changed(&mut self) {
+        self.update_matches();
+
+        let top = self.vertical_scroll.get_top();
+        self.current_match = self
+            .matches
+            .iter()
+            .position(|(index, _)| self.visual_row(self.item_of(*index)) >= top)
+            .or_else(|| (!self.matches.is_empty()).then_some(0));
+
+        self.scroll_to_match();
+    }
+
+    fn clear_search(&mut self) {
+        self.search.clear();
+        self.matches.clear();
+        self.current_match = None;
+    }
+
+    fn move_match(&mut self, next: bool) -> bool {
+        let count = self.matches.len();
+        if count == 0 || !self.can_search() {
+            return false;
+        }
+
+        self.current_match = Some(match (self.current_match, next) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+        });
+        self.scroll_to_match();
+
+        true
+    }
+
+    fn scroll_to_match(&self) {
+        if let Some((index, _)) = self.current_match.and_then(|i| self.matches.get(i)) {
+            let item = self.item_of(*index);
+            self.vertical_scroll.move_area_to_visible(
+                self.current_height.get(),
+                self.visual_row(item),
+                self.visual_row(item + 1),
+            );
+        }
+    }
+
+    /// Line, or split row, of the view showing line `index`.
+    fn item_of(&self, index: LineIndex) -> usize {
+        if self.split {
+            self.split_rows
+                .iter()
+                .position(|row| match *row {
+                    SplitRow::Header(header) => header == index,
+                    SplitRow::Lines(old, new) => old == Some(index) || new == Some(index),
+                })
+                .unwrap_or_default()
+        } else {
+            let hunks = self.diff.as_ref().map_or(&[][..], |d| d.hunks.as_slice());
+            hunks
+                .iter()
+                .take(index.0)
+                .map(|h| h.lines.len())
+                .sum::<usize>()
+                + index.1
+        }
+    }
+
+    /// Search matches in line `index`, styled as such.
+    fn get_match_styles(&self, index: LineIndex) -> Vec<(Range<usize>, Style)> {
+        let start = self.matches.partition_point(|(i, _)| *i < index);
+
+        self.matches[start..]
+            .iter()
+            .take_while(|(i, _)| *i == index)
+            .enumerate()
+            .map(|(i, (_, range))| {
+                let current = self.current_match == Some(start + i);
+                (range.clone(), self.theme.diff_search_match(current))
+            })
+            .collect()
+    }
+
+    fn search_event(&mut self, e: &KeyEvent) -> EventState {
+        // the search key is part of the query while typing, e.g. in `//depot`
+        if key_match(e, self.key_config.keys.diff_search)
+            && !self.search.is_editing()
+            && self.can_search()
+        {
+            self.search.start();
+            self.search_changed();
+        } else if key_match(e, self.key_config.keys.diff_search_next) {
+            return self.move_match(true).into();
+        } else if key_match(e, self.key_config.keys.diff_search_prev) {
+            return self.move_match(false).into();
+        } else if self.search.is_active()
+            && key_match(e, self.key_config.keys.diff_search_ignore_case)
+        {
+            self.search.toggle_ignore_case();
+            self.search_changed();
+        } else if self.search.is_active() && key_match(e, self.key_config.keys.diff_search_regex) {
+            self.search.toggle_regex();
+            self.search_changed();
+        } else if self.search.is_active() && key_match(e, self.key_config.keys.exit_popup) {
+            self.clear_search();
+        } else if self.search.is_editing() {
+            // typed text is never passed on
+            if key_match(e, self.key_config.keys.enter) {
+                self.search.stop();
+            } else if e.code == KeyCode::Backspace {
+                self.search.pop();
+                self.search_changed();
+            } else if let KeyCode::Char(c) = e.code {
+                if e.modifiers == KeyModifiers::empty() || e.modifiers == KeyModifiers::SHIFT {
+                    self.search.push(c);
+                    self.search_changed();
+                }
+            }
+        } else {
+            return EventState::NotConsumed;
+        }
+
+        EventState::Consumed
+    }
+
+    /// Query, modes and match count shown below the diff.
+    fn get_search_bar(&self) -> Line<'_> {
+        let mut spans = vec![Span::styled(
+            strings::diff_search_query(self.search.query()),
+            self.theme.text(true, false),
+        )];
+
+        if self.search.is_editing() {
+            spans.push(Span::styled(
+                " ",
+                Style::default().add_modifier(Modifier::REVERSED),
+            ));
+        }
+
+        spans.push(Span::styled(
+            strings::diff_search_status(
+                self.search.ignore_case(),
+                self.search.regex(),
+                (!self.search.query().is_empty())
+                    .then_some((self.current_match, self.matches.len())),
+                self.search.is_invalid(),
+            ),
+            self.theme.text(false, false),
+        ));
+
+        Line::from(spans)
+    }
+
+    fn toggle_split(&mut self) {
+        self.split = !self.split;
+        self.row_heights.replace(None);
+        self.vertical_scroll.reset();
+    }
+
+    fn toggle_wrap(&mut self) {
+        self.wrap = !self.wrap;
+        self.row_heights.replace(None);
         self.vertical_scroll.reset();
-        // self.horizontal_scroll.reset();
+        self.horizontal_scroll.reset();
+    }
+
+    fn toggle_formatter(&mut self) {
+        self.use_formatter = !self.use_formatter;
+        self.reset_formatted();
+        self.row_heights.replace(None);
+        self.vertical_scroll.reset();
+        self.horizontal_scroll.reset();
+    }
+
+    fn toggle_whitespace(&mut self) {
+        self.show_whitespace = !self.show_whitespace;
+        self.row_heights.replace(None);
+        self.update_max_line_width();
+    }
+
+    /// Content width lines are wrapped at, `None` when not wrapping.
+    fn wrap_width(&self) -> Option<usize> {
+        self.row_heights.borrow().as_ref().map(|(width, _)| *width)
+    }
+
+    fn get_line(&self, (hunk, line): LineIndex) -> Option<&DiffLine> {
+        self.diff.as_ref()?.hunks.get(hunk)?.lines.get(line)
+    }
+
+    fn get_text(&self, height: usize) -> Vec<Line<'_>> {
+        let Some(diff) = &self.diff else {
+            return Vec::new();
+        };
+
+        if shows_summary(diff) {
+            return self
+                .get_summary_text(diff)
+                .into_iter()
+                .skip(self.vertical_scroll.get_top())
+                .take(height)
+                .collect();
+        }
+
+        if let Some(formatted) = &self.formatted {
+            return formatted
+                .lines
+                .iter()
+                .skip(self.vertical_scroll.get_top())
+                .take(height)
+                .map(|line| {
+                    let pieces = line
+                        .spans
+                        .iter()
+                        .map(|span| (span.content.to_string(), span.style));
+                    Line::from(skip_columns(pieces, self.horizontal_scroll.get_right()))
+                })
+                .collect();
+        }
+
+        let lines = diff.hunks.iter().enumerate().flat_map(|(h, hunk)| {
+            hunk.lines
+                .iter()
+                .enumerate()
+                .map(move |(l, line)| ((h, l), line))
+        });
+
+        if let Some((_, heights)) = &*self.row_heights.borrow() {
+            let lines = lines.collect::<Vec<_>>();
+            return visible_rows(heights, self.vertical_scroll.get_top(), height, |i| {
+                let (index, line) = lines[i];
+                self.get_line_to_add(index, line)
+            });
+        }
+
+        lines
+            .skip(self.vertical_scroll.get_top())
+            .take(height)
+            .flat_map(|(index, line)| self.get_line_to_add(index, line))
+            .collect()
+    }
+
+    /// Filetype and sizes of a file not shown line by line, followed by
+    /// its hex dump if loaded.
+    fn get_summary_text(&self, diff: &FileDiff) -> Vec<Line<'static>> {
+        let title = if diff.binary {
+            strings::diff_binary()
+        } else {
+            strings::diff_too_large()
+        };
+
+        let mut txt = vec![
+            Line::from(Span::styled(title, self.theme.text(true, false))),
+            Line::from(strings::diff_file_type(self.file_type.as_ref())),
+            Line::from(strings::diff_sizes(diff.sizes, diff.size_delta)),
+            Line::default(),
+        ];
+
+        match &self.hex_dump {
+            Some(bytes) => {
+                txt.push(Line::from(Span::styled(
+                    format!("@@ 0x{:08x} @@", bytes.offset),
+                    self.theme.diff_line(DiffLineType::Header, false),
+                )));
+
+                for (symbol, line_type, side) in [
+                    ("-", DiffLineType::Delete, &bytes.old),
+                    ("+", DiffLineType::Add, &bytes.new),
+                ] {
+                    let style = self.theme.diff_line(line_type, false);
+                    txt.extend(side.chunks(HEX_DUMP_ROW).enumerate().map(|(i, row)| {
+                        Line::from(Span::styled(
+                            format!(
+                                "{symbol}{}",
+                                hex_dump_row(bytes.offset + i * HEX_DUMP_ROW, row)
+                            ),
+                            style,
+                        ))
+                    }));
+                }
+            }
+            None if !self.is_immutable => {
+                txt.push(Line::from(strings::diff_hex_dump_hint(&self.key_config)));
+            }
+            None => {}
+        }
+
+        txt
+    }
+
+    /// Old and new column of the side-by-side view.
+    fn get_split_text(&self, height: usize) -> (Vec<Line<'_>>, Vec<Line<'_>>) {
+        let top = self.vertical_scroll.get_top();
+
+        let rows = if let Some((_, heights)) = &*self.row_heights.borrow() {
+            visible_rows(heights, top, height, |i| {
+                self.get_split_row(self.split_rows[i])
+            })
+        } else {
+            self.split_rows
+                .iter()
+                .skip(top)
+                .take(height)
+                .flat_map(|row| self.get_split_row(*row))
+                .collect()
+        };
+
+        rows.into_iter().unzip()
+    }
+
+    /// Rows of both columns for `row`, the shorter side filled up.
+    fn get_split_row(&self, row: SplitRow) -> Vec<(Line<'_>, Line<'_>)> {
+        match row {
+            SplitRow::Header(index) => self
+                .get_line(index)
+                .map(|line| {
+                    self.get_line_to_add(index, line)
+                        .into_iter()
+                        .map(|l| (l.clone(), l))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            SplitRow::Lines(left, right) => {
+                let side = |index: Option<LineIndex>, old_side: bool| {
+                    index
+                        .and_then(|i| self.get_line(i).map(|line| (i, line)))
+                        .map_or_else(Vec::new, |(i, line)| {
+                            self.get_side_line_to_add(i, line, old_side)
+                        })
+                };
+
+                let mut old = side(left, true);
+                let mut new = side(right, false);
+                let rows = old.len().max(new.len()).max(1);
+                old.resize(rows, Line::default());
+                new.resize(rows, Line::default());
+
+                old.into_iter().zip(new).collect()
+            }
+        }
+    }
+
+    fn get_line_to_add<'a>(&self, index: LineIndex, line: &'a DiffLine) -> Vec<Line<'a>> {
+        if line.line_type == DiffLineType::Header {
+            let selected = self.focused && self.selected_hunk == Some(index.0);
+
+            return vec![Line::from(Span::styled(
+                line.content.as_ref(),
+                self.theme.diff_line(line.line_type, selected),
+            ))];
+        }
+
+        let width = self.number_width;
+        let number = |n: Option<u32>| n.map(|n| n.to_string()).unwrap_or_default();
+
+        let gutter = format!(
+            "{:>width$} {:>width$} ",
+            number(line.position.old_lineno),
+            number(line.position.new_lineno),
+        );
+
+        self.with_gutter(gutter, self.get_content_rows(index, line))
+    }
+
+    fn get_side_line_to_add<'a>(
+        &self,
+        index: LineIndex,
+        line: &'a DiffLine,
+        old_side: bool,
+    ) -> Vec<Line<'a>> {
+        let width = self.number_width;
+        let number = if old_side {
+            line.position.old_lineno
+        } else {
+            line.position.new_lineno
+        }
+        .map(|n| n.to_string())
+        .unwrap_or_default();
+
+        self.with_gutter(
+            format!("{number:>width$} "),
+            self.get_content_rows(index, line),
+        )
+    }
+
+    /// Puts the line numbers in front of the first row and blank space as
+    /// wide in front of wrapped rows.
+    fn with_gutter<'a>(&self, gutter: String, rows: Vec<Vec<Span<'a>>>) -> Vec<Line<'a>> {
+        let blank = " ".repeat(gutter.width());
+        let mut gutter = Some(gutter);
+
+        rows.into_iter()
+            .map(|spans| {
+                let gutter = gutter.take().unwrap_or_else(|| blank.clone());
+                let mut line = vec![Span::styled(gutter, self.theme.diff_line_number())];
+                line.extend(spans);
+                Line::from(line)
+            })
+            .collect()
+    }
+
+    fn get_highlights(&self, (hunk, line): LineIndex) -> Option<&LineHighlights> {
+        let highlights = self.highlights.as_ref()?.get(hunk)?.get(line)?;
+        (!highlights.is_empty()).then_some(highlights)
+    }
+
+    /// The +/- column and content of a line, as several rows when wrapping.
+    fn get_content_rows(&self, index: LineIndex, line: &DiffLine) -> Vec<Vec<Span<'static>>> {
+        let symbol = match line.line_type {
+            DiffLineType::Add => "+",
+            DiffLineType::Delete => "-",
+            _ => " ",
+        };
+        let style = self.theme.diff_line(line.line_type, false);
+        let highlights = self.get_highlights(index);
+
+        let (symbol_style, styles) = highlights.map_or_else(
+            || (style, vec![(0..line.content.len(), style)]),
+            |highlights| {
+                let styles = highlights
+                    .iter()
+                    .map(|(range, syntax)| {
+                        (
+                            range.clone(),
+                            self.theme.diff_syntax(line.line_type, *syntax),
+                        )
+                    })
+                    .collect();
+                (self.theme.diff_syntax(line.line_type, style), styles)
+            },
+        );
+
+        let changed = self.theme.diff_word(line.line_type, true);
+
+        let styles = mark_changed(styles, &line.segments, changed);
+        let pieces = mark_ranges(styles, &self.get_match_styles(index))
+            .into_iter()
+            .flat_map(|(range, style)| {
+                let whitespace = self
+                    .show_whitespace
+                    .then(|| self.theme.diff_whitespace(style));
+                display_pieces(&line.content, range, style, whitespace)
+            });
+
+        let rows = match self.wrap_width() {
+            Some(width) => wrap_columns(pieces, width),
+            None => vec![skip_columns(pieces, self.horizontal_scroll.get_right())],
+        };
+
+        rows.into_iter()
+            .enumerate()
+            .map(|(i, mut spans)| {
+                let symbol = if i == 0 {
+                    Span::styled(symbol, symbol_style)
+                } else {
+                    Span::raw(" ")
+                };
+                spans.insert(0, symbol);
+                spans
+            })
+            .collect()
+    }
+
+    fn move_event(&mut self, ev: &Event) -> EventState {
+        if let Event::Key(e) = ev {
+            if key_match(e, self.key_config.keys.move_down) {
+                return self.vertical_scroll.move_top(ScrollType::Down).into();
+            } else if key_match(e, self.key_config.keys.move_up) {
+                return self.vertical_scroll.move_top(ScrollType::Up).into();
+            } else if key_match(e, self.key_config.keys.move_right) {
+                return self
+                    .horizontal_scroll
+                    .move_right(HorizontalScrollType::Right)
+                    .into();
+            } else if key_match(e, self.key_config.keys.move_left) {
+                // not consumed at the left edge, so focus can move back
+                return self
+                    .horizontal_scroll
+                    .move_right(HorizontalScrollType::Left)
+                    .into();
+            } else if key_match(e, self.key_config.keys.diff_split_view) {
+                self.toggle_split();
+                return EventState::Consumed;
+            } else if key_match(e, self.key_config.keys.diff_wrap) {
+                self.toggle_wrap();
+                return EventState::Consumed;
+            } else if key_match(e, self.key_config.keys.diff_show_whitespace) {
+                self.toggle_whitespace();
+                return EventState::Consumed;
+            } else if key_match(e, self.key_config.keys.diff_formatter) {
+                self.toggle_formatter();
+                return EventState::Consumed;
+            }
+        }
+
+        EventState::NotConsumed
+    }
+}
+
+/// Splits `styles` at the segment boundaries, giving changed segments the
+/// `changed` style.
+fn mark_changed(
+    styles: Vec<(Range<usize>, Style)>,
+    segments: &[DiffSegment],
+    changed: Style,
+) -> Vec<(Range<usize>, Style)> {
+    let mut result = Vec::new();
+
+    for (range, style) in styles {
+        let mut start = range.start;
+
+        for segment in segments {
+            let end = segment.range.end.min(range.end);
+            if end <= start {
+                continue;
+            }
+
+            result.push((start..end, if segment.changed { changed } else { style }));
+            start = end;
+        }
+
+        if start < range.end {
+            result.push((start..range.end, style));
+        }
+    }
+
+    result
+}
+
+/// Gives the parts of `styles` covered by one of the ordered, disjoint
+/// `marks` the style of that mark.
+fn mark_ranges(
+    styles: Vec<(Range<usize>, Style)>,
+    marks: &[(Range<usize>, Style)],
+) -> Vec<(Range<usize>, Style)> {
+    if marks.is_empty() {
+        return styles;
+    }
+
+    let mut result = Vec::new();
+
+    for (range, style) in styles {
+        let mut start = range.start;
+
+        for (mark, mark_style) in marks {
+            let mark_start = mark.start.max(start);
+            let mark_end = mark.end.min(range.end);
+            if mark_end <= mark_start {
+                continue;
+            }
+
+            if start < mark_start {
+                result.push((start..mark_start, style));
+            }
+            result.push((mark_start..mark_end, *mark_style));
+            start = mark_end;
+        }
+
+        if start < range.end {
+            result.push((start..range.end, style));
+        }
+    }
+
+    result
+}
+
+/// Binary and too large files are summarized instead of shown line by line.
+const fn shows_summary(diff: &FileDiff) -> bool {
+    diff.binary || diff.too_large
+}
+
+/// `offset  hex bytes  |ascii|` like `hexdump -C`.
+fn hex_dump_row(offset: usize, bytes: &[u8]) -> String {
+    let hex = (0..HEX_DUMP_ROW)
+        .map(|i| {
+            bytes
+                .get(i)
+                .map_or_else(|| "  ".to_string(), |b| format!("{b:02x}"))
+        })
+        .collect::<Vec<_>>();
+    let ascii = bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                char::from(b)
+            } else {
+                '.'
+            }
+        })
+        .collect::<String>();
+
+    format!(
+        "{offset:08x}  {}  {}  |{ascii}|",
+        hex[..HEX_DUMP_ROW / 2].join(" "),
+        hex[HEX_DUMP_ROW / 2..].join(" ")
+    )
+}
+
+/// Drops the first `columns` terminal columns of the styled text.
+fn skip_columns(
+    pieces: impl IntoIterator<Item = (String, Style)>,
+    columns: usize,
+) -> Vec<Span<'static>> {
+    let mut skip = columns;
+
+    pieces
+        .into_iter()
+        .filter_map(|(text, style)| {
+            let mut start = text.len();
+            for (i, c) in text.char_indices() {
+                if skip == 0 {
+                    start = i;
+                    break;
+                }
+                skip = skip.saturating_sub(c.width().unwrap_or_default());
+            }
+
+            (start < text.len()).then(|| Span::styled(text[start..].to_string(), style))
+        })
+        .collect()
+}
+
+/// Splits the styled text into rows at most `width` columns wide. A wide
+/// character that does not fit starts the next row.
+fn wrap_columns(
+    pieces: impl IntoIterator<Item = (String, Style)>,
+    width: usize,
+) -> Vec<Vec<Span<'static>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut column = 0;
+
+    for (text, style) in pieces {
+        let mut start = 0;
+
+        for (i, c) in text.char_indices() {
+            let char_width = c.width().unwrap_or_default();
+            if column > 0 && column + char_width > width {
+                if start < i {
+                    row.push(Span::styled(text[start..i].to_string(), style));
+                }
+                rows.push(std::mem::take(&mut row));
+                start = i;
+                column = 0;
+            }
+            column += char_width;
+        }
+
+        if start < text.len() {
+            row.push(Span::styled(text[start..].to_string(), style));
+        }
+    }
+
+    rows.push(row);
+    rows
+}
+
+/// Rows `top..top + height` of items taking `heights` rows each, `render`
+/// giving the rows of an item.
+fn visible_rows<T>(
+    heights: &[usize],
+    top: usize,
+    height: usize,
+    mut render: impl FnMut(usize) -> Vec<T>,
+) -> Vec<T> {
+    let mut rows = Vec::new();
+    let mut start = 0;
+
+    for (item, item_height) in heights.iter().enumerate() {
+        if rows.len() >= height {
+            break;
+        }
+
+        if start + item_height > top {
+            let skip = top.saturating_sub(start);
+            let take = height - rows.len();
+            rows.extend(render(item).into_iter().skip(skip).take(take));
+        }
+
+        start += item_height;
+    }
+
+    rows
+}
+
+/// `content[range]` as displayed: tabs expanded and carriage returns
+/// dropped, or, given a `whitespace` style, tabs, trailing spaces and
+/// carriage returns shown as [`symbol::WHITESPACE`].
+fn display_pieces(
+    content: &str,
+    range: Range<usize>,
+    style: Style,
+    whitespace: Option<Style>,
+) -> Vec<(String, Style)> {
+    let trailing = content.trim_end_matches([' ', '\t', '\r']).len();
+    let mut pieces: Vec<(String, Style)> = Vec::new();
+
+    let mut push = |text: &str, style: Style| match pieces.last_mut() {
+        Some((last, last_style)) if *last_style == style => last.push_str(text),
+        _ => pieces.push((text.to_string(), style)),
+    };
+
+    for (i, c) in content[range.clone()].char_indices() {
+        match (c, whitespace) {
+            ('\t', Some(whitespace)) => {
+                push(symbol::WHITESPACE, whitespace);
+                push("   ", style);
+            }
+            ('\t', None) => push("    ", style),
+            ('\r', Some(whitespace)) => push(symbol::WHITESPACE, whitespace),
+            ('\r', None) => {}
+            (' ', Some(whitespace)) if range.start + i >= trailing => {
+                push(symbol::WHITESPACE, whitespace);
+            }
+            _ => push(c.encode_utf8(&mut [0; 4]), style),
+        }
     }
 
-    pub fn update(&mut self) {}
+    pieces
+}
+
+/// The diff as unified diff text, as diff formatters expect it.
+fn diff_to_string(path: &P4Path, diff: &FileDiff) -> String {
+    let mut text = format!("--- {path}\n+++ {path}\n");
+    text.extend(diff.hunks.iter().map(hunk_to_string));
+    text
+}
+
+/// The hunk as unified diff text.
+fn hunk_to_string(hunk: &Hunk) -> String {
+    hunk.lines
+        .iter()
+        .map(|line| {
+            let prefix = match line.line_type {
+                DiffLineType::Add => "+",
+                DiffLineType::Delete => "-",
+                DiffLineType::None if !is_no_newline_marker(line) => " ",
+                _ => "",
+            };
+            format!("{prefix}{}\n", line.content)
+        })
+        .collect()
+}
+
+fn is_no_newline_marker(line: &DiffLine) -> bool {
+    line.line_type == DiffLineType::None
+        && line.position.old_lineno.is_none()
+        && line.position.new_lineno.is_none()
+}
+
+/// Pairs each run of deleted lines with the added lines following it, row
+/// by row, filling up the shorter side.
+fn split_rows(diff: &FileDiff) -> Vec<SplitRow> {
+    fn flush(rows: &mut Vec<SplitRow>, old: &mut Vec<LineIndex>, new: &mut Vec<LineIndex>) {
+        for i in 0..old.len().max(new.len()) {
+            rows.push(SplitRow::Lines(old.get(i).copied(), new.get(i).copied()));
+        }
+        old.clear();
+        new.clear();
+    }
+
+    let mut rows = Vec::new();
+
+    for (h, hunk) in diff.hunks.iter().enumerate() {
+        let mut old = Vec::new();
+        let mut new = Vec::new();
+        let mut last_type = DiffLineType::None;
+
+        for (l, line) in hunk.lines.iter().enumerate() {
+            let index = (h, l);
+
+            if is_no_newline_marker(line) {
+                // belongs to the side of the line it follows
+                match last_type {
+                    DiffLineType::Delete => old.push(index),
+                    DiffLineType::Add => new.push(index),
+                    _ => rows.push(SplitRow::Lines(Some(index), Some(index))),
+                }
+                continue;
+            }
+
+            match line.line_type {
+                DiffLineType::Header => {
+                    flush(&mut rows, &mut old, &mut new);
+                    rows.push(SplitRow::Header(index));
+                }
+                DiffLineType::Delete => {
+                    // a delete after adds starts a new change block
+                    if !new.is_empty() {
+                        flush(&mut rows, &mut old, &mut new);
+                    }
+                    old.push(index);
+                }
+                DiffLineType::Add => new.push(index),
+                DiffLineType::None => {
+                    flush(&mut rows, &mut old, &mut new);
+                    rows.push(SplitRow::Lines(Some(index), Some(index)));
+                }
+            }
+
+            last_type = line.line_type;
+        }
+
+        flush(&mut rows, &mut old, &mut new);
+    }
+
+    rows
 }
 
 impl DrawableComponent for DiffComponent {
     fn draw<B: Backend>(&self, f: &mut Frame<B>, r: Rect) -> Result<()> {
-        // self.vertical_scroll.update()
-        // self.horizontal_scroll.update()
+        let inner_width = usize::from(r.width.saturating_sub(2));
+        self.format_width.set(inner_width);
+
+        let show_search = self.search.is_active() && self.can_search();
+        let height =
+            usize::from(r.height.saturating_sub(2)).saturating_sub(usize::from(show_search));
+        self.current_height.set(height);
+        let formatted_width = self.formatted.as_ref().map(|f| f.max_width);
+
+        let width = self.content_width(r.width);
+        self.update_row_heights(width);
+
+        self.vertical_scroll
+            .update_no_selection(self.line_count(), height);
+        match formatted_width {
+            Some(max_width) => self
+                .horizontal_scroll
+                .update_no_selection(max_width, inner_width),
+            None => self
+                .horizontal_scroll
+                .update_no_selection(if self.wrap { 0 } else { self.max_line_width }, width),
+        };
 
-        let diff = Block::default()
-            .title(Span::styled("Diff", self.theme.title(self.focused)))
+        let title = match (&self.path, self.pending) {
+            (Some(path), true) => strings::diff_title_loading(&path.to_string_lossy()),
+            (Some(path), false) => strings::diff_title(&path.to_string_lossy()),
+            (None, _) => strings::diff_title(""),
+        };
+
+        let block = Block::default()
+            .title(Span::styled(title, self.theme.title(self.focused)))
             .borders(Borders::ALL)
             .border_style(self.theme.block(self.focused));
 
-        f.render_widget(diff, r);
+        let summary = self.diff.as_ref().is_some_and(shows_summary);
+
+        let mut inner = block.inner(r);
+        f.render_widget(block, r);
+
+        if show_search {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+                .split(inner);
+            inner = rows[0];
+            f.render_widget(Paragraph::new(self.get_search_bar()), rows[1]);
+        }
+
+        if self.split && !summary && formatted_width.is_none() {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+                .split(inner);
+
+            let (old, new) = self.get_split_text(height);
+            f.render_widget(Paragraph::new(old), columns[0]);
+            f.render_widget(
+                Paragraph::new(new).block(
+                    Block::default()
+                        .borders(Borders::LEFT)
+                        .border_style(self.theme.block(false)),
+                ),
+                columns[1],
+            );
+        } else {
+            f.render_widget(Paragraph::new(self.get_text(height)), inner);
+        }
+
+        if self.focused && self.can_scroll() {
+            self.vertical_scroll.draw(f, r, &self.theme);
+        }
+        if self.focused {
+            self.horizontal_scroll.draw(f, r, &self.theme);
+        }
+
         Ok(())
     }
 }
 
 impl Component for DiffComponent {
-    fn commands(&self, out: &mut Vec<CommandInfo>, force_all: bool) -> CommandBlocking {
+    fn commands(&self, out: &mut Vec<CommandInfo>, _force_all: bool) -> CommandBlocking {
+        out.push(CommandInfo::new(
+            strings::commands::scroll(&self.key_config),
+            self.can_scroll(),
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::scroll_horizontal(&self.key_config),
+            self.horizontal_scroll.can_scroll(),
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_split_view(&self.key_config),
+            true,
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_wrap(&self.key_config),
+            true,
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_show_whitespace(&self.key_config),
+            true,
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_formatter(&self.key_config),
+            true,
+            self.focused && self.options.borrow().diff_formatter().is_some(),
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_search(&self.key_config),
+            self.can_search(),
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_search_next(&self.key_config),
+            self.can_search() && !self.matches.is_empty(),
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_search_modes(&self.key_config),
+            true,
+            self.focused && self.search.is_active(),
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_search_close(&self.key_config),
+            true,
+            self.focused && self.search.is_active(),
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_hunk_next(&self.key_config),
+            !self.uses_formatter() && self.diff.as_ref().is_some_and(|d| !d.hunks.is_empty()),
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_hunk_copy(&self.key_config),
+            self.can_select_hunk(),
+            self.focused,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_hunk_discard(&self.key_config),
+            self.can_discard_hunk(),
+            self.focused && !self.is_immutable,
+        ));
+        out.push(CommandInfo::new(
+            strings::commands::diff_hex_dump(&self.key_config),
+            self.can_hex_dump() || self.hex_dump.is_some(),
+            self.focused && !self.is_immutable,
+        ));
+
         CommandBlocking::PassingOn
     }
 
     fn event(&mut self, ev: &Event) -> Result<EventState> {
-        Ok(EventState::NotConsumed)
+        if !self.focused {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if let Event::Key(e) = ev {
+            if self.search_event(e).is_consumed() {
+                return Ok(EventState::Consumed);
+            }
+
+            if key_match(e, self.key_config.keys.diff_hunk_next) {
+                return Ok(self.move_hunk(true).into());
+            } else if key_match(e, self.key_config.keys.diff_hunk_prev) {
+                return Ok(self.move_hunk(false).into());
+            } else if key_match(e, self.key_config.keys.diff_hunk_copy) {
+                self.copy_hunk();
+                return Ok(EventState::Consumed);
+            } else if key_match(e, self.key_config.keys.diff_hunk_discard) {
+                self.discard_hunk();
+                return Ok(EventState::Consumed);
+            } else if key_match(e, self.key_config.keys.diff_hex_dump) {
+                self.toggle_hex_dump();
+                return Ok(EventState::Consumed);
+            }
+        }
+
+        Ok(self.move_event(ev))
     }
 
     fn focused(&self) -> bool {
@@ -77,4 +1650,242 @@ impl Component for DiffComponent {
     fn focus(&mut self, focus: bool) {
         self.focused = focus;
     }
+
+    fn is_input_active(&self) -> bool {
+        self.focused && self.search.is_editing()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{keys::KeyConfig, options::Options, ui::style::Theme};
+    use asyncp4::sync::{DiffLinePosition, Hunk};
+    use crossbeam_channel::unbounded;
+    use pretty_assertions::assert_eq;
+    use std::{rc::Rc, time::Duration};
+
+    fn line(line_type: DiffLineType, old: Option<u32>, new: Option<u32>) -> DiffLine {
+        DiffLine {
+            content: "".into(),
+            line_type,
+            position: DiffLinePosition {
+                old_lineno: old,
+                new_lineno: new,
+            },
+            ..DiffLine::default()
+        }
+    }
+
+    #[test]
+    fn test_split_rows() {
+        let diff = FileDiff {
+            hunks: vec![Hunk {
+                header_hash: 0,
+                lines: vec![
+                    line(DiffLineType::Header, None, None),
+                    line(DiffLineType::None, Some(1), Some(1)),
+                    line(DiffLineType::Delete, Some(2), None),
+                    line(DiffLineType::Delete, Some(3), None),
+                    line(DiffLineType::Add, None, Some(2)),
+                    line(DiffLineType::None, Some(4), Some(3)),
+                    line(DiffLineType::Add, None, Some(4)),
+                    line(DiffLineType::None, None, None),
+                ],
+            }],
+            ..FileDiff::default()
+        };
+
+        assert_eq!(
+            split_rows(&diff),
+            vec![
+                SplitRow::Header((0, 0)),
+                SplitRow::Lines(Some((0, 1)), Some((0, 1))),
+                SplitRow::Lines(Some((0, 2)), Some((0, 4))),
+                SplitRow::Lines(Some((0, 3)), None),
+                SplitRow::Lines(Some((0, 5)), Some((0, 5))),
+                SplitRow::Lines(None, Some((0, 6))),
+                SplitRow::Lines(None, Some((0, 7))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrap_columns() {
+        let texts = |rows: Vec<Vec<Span>>| {
+            rows.iter()
+                .map(|row| row.iter().map(|s| s.content.as_ref()).collect::<String>())
+                .collect::<Vec<_>>()
+        };
+        let style = Style::default();
+
+        assert_eq!(
+            texts(wrap_columns(
+                [("abcd".to_string(), style), ("ef".to_string(), style)],
+                3
+            )),
+            vec!["abc", "def"]
+        );
+        // the wide character does not fit behind `a`
+        assert_eq!(
+            texts(wrap_columns([("a\u{4e2d}b".to_string(), style)], 2)),
+            vec!["a", "\u{4e2d}", "b"]
+        );
+        assert_eq!(texts(wrap_columns([], 3)), vec![""]);
+    }
+
+    #[test]
+    fn test_display_pieces() {
+        let style = Style::default();
+        let whitespace = Style::default().fg(ratatui::style::Color::DarkGray);
+        let text =
+            |pieces: Vec<(String, Style)>| pieces.into_iter().map(|(t, _)| t).collect::<String>();
+        let content = "\ta b \r";
+
+        assert_eq!(
+            text(display_pieces(content, 0..content.len(), style, None)),
+            "    a b "
+        );
+        assert_eq!(
+            text(display_pieces(
+                content,
+                0..content.len(),
+                style,
+                Some(whitespace)
+            )),
+            "\u{b7}   a b\u{b7}\u{b7}"
+        );
+        assert_eq!(
+            display_pieces(content, 3..6, style, Some(whitespace)),
+            vec![
+                ("b".to_string(), style),
+                ("\u{b7}\u{b7}".to_string(), whitespace)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mark_ranges() {
+        let style = Style::default();
+        let syntax = Style::default().fg(ratatui::style::Color::Blue);
+        let mark = Style::default().bg(ratatui::style::Color::Yellow);
+
+        assert_eq!(
+            mark_ranges(
+                vec![(0..4, style), (4..10, syntax)],
+                &[(2..6, mark), (8..9, mark)]
+            ),
+            vec![
+                (0..2, style),
+                (2..4, mark),
+                (4..6, mark),
+                (6..8, syntax),
+                (8..9, mark),
+                (9..10, syntax),
+            ]
+        );
+    }
+
+    fn component(queue: Queue) -> DiffComponent {
+        let (sender, _receiver) = unbounded();
+        DiffComponent::new(
+            &sender,
+            queue,
+            Options::new(),
+            Rc::new(KeyConfig::default()),
+            Rc::new(Theme::default()),
+            false,
+        )
+    }
+
+    #[test]
+    fn test_search_query_with_search_key() {
+        let mut diff = component(Queue::new());
+        diff.update(
+            "//depot/a.c".into(),
+            None,
+            FileDiff {
+                hunks: vec![Hunk {
+                    lines: vec![line(DiffLineType::Add, None, Some(1))],
+                    ..Hunk::default()
+                }],
+                ..FileDiff::default()
+            },
+        );
+
+        for c in "///d".chars() {
+            let ev = KeyEvent::new(KeyCode::Char(c), KeyModifiers::empty());
+            assert!(diff.search_event(&ev).is_consumed());
+        }
+
+        assert!(diff.search.is_editing());
+        assert_eq!(diff.search.query(), "//d");
+    }
+
+    #[test]
+    fn test_hex_dump_reloaded() {
+        let queue = Queue::new();
+        let mut diff = component(queue.clone());
+        let path = P4Path::from("//depot/a.png");
+        let binary = FileDiff {
+            binary: true,
+            sizes: (4, 4),
+            ..FileDiff::default()
+        };
+
+        diff.update(path.clone(), None, binary.clone());
+        diff.set_hex_dump(&path, ChangedBytes::default());
+
+        // same sizes, but the bytes may have changed
+        diff.update(path.clone(), None, binary);
+
+        assert!(diff.hex_dump.is_none());
+        assert!(matches!(queue.pop(), Some(InternalEvent::LoadHexDump(p)) if p == path));
+    }
+
+    #[test]
+    fn test_highlighted_async() {
+        let (sender, receiver) = unbounded();
+        let mut diff = DiffComponent::new(
+            &sender,
+            Queue::new(),
+            Options::new(),
+            Rc::new(KeyConfig::default()),
+            Rc::new(Theme::default()),
+            false,
+        );
+        let path = P4Path::from("//depot/main.rs");
+        let rust = FileDiff {
+            hunks: vec![Hunk {
+                lines: vec![DiffLine {
+                    content: "fn main() {}".into(),
+                    ..line(DiffLineType::Add, None, Some(1))
+                }],
+                ..Hunk::default()
+            }],
+            ..FileDiff::default()
+        };
+
+        // plain until the job is done
+        diff.update(path.clone(), None, rust.clone());
+        assert!(diff.highlights.is_none());
+
+        let ev = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(ev, AsyncAppNotification::Highlighted);
+        diff.update_async(ev);
+        assert!(diff.highlights.is_some());
+
+        // cached when shown again
+        diff.clear(false);
+        diff.update(path, None, rust);
+        assert!(diff.highlights.is_some());
+    }
+
+    #[test]
+    fn test_hex_dump_row() {
+        assert_eq!(
+            hex_dump_row(0x20, b"GIF89a\x01\x00\x01\x00"),
+            "00000020  47 49 46 38 39 61 01 00  01 00                    |GIF89a....|"
+        );
+    }
 }
@@ -1,24 +1,30 @@
 use anyhow::Result;
+use asyncp4::sync;
 use crossterm::event::Event;
 use ratatui::{
     backend::Backend,
     layout::Rect,
-    text::Span,
-    widgets::{Block, Borders},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
     Frame,
 };
 
-use crate::{keys::SharedKeyConfig, ui::style::SharedTheme};
-
-use std::path::PathBuf;
+use crate::{
+    keys::{key_match, SharedKeyConfig},
+    ui::style::SharedTheme,
+};
 
-use super::{CommandBlocking, CommandInfo, Component, DrawableComponent, EventState};
+use super::{
+    utils::scroll_vertical::VerticalScroll, CommandBlocking, CommandInfo, Component,
+    DrawableComponent, EventState, ScrollType,
+};
 
 pub struct FileListComponent {
     focused: bool,
     title: String,
-    changelist: Option<u32>,
-    files: Vec<PathBuf>,
+    files: Vec<sync::File>,
+    selection: usize,
+    scroll: VerticalScroll,
     key_config: SharedKeyConfig,
     theme: SharedTheme,
 }
@@ -28,35 +34,116 @@ impl FileListComponent {
         Self {
             focused: false,
             title,
-            changelist: Option::None,
             files: Vec::new(),
+            selection: 0,
+            scroll: VerticalScroll::new(),
             key_config,
             theme,
         }
     }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    pub fn set_files(&mut self, files: Vec<sync::File>) {
+        // keep the selected file selected if it is still there
+        let selected = self.selected_file().map(|f| f.depot_path.clone());
+
+        self.files = files;
+        self.selection = selected
+            .and_then(|path| self.files.iter().position(|f| f.depot_path == path))
+            .unwrap_or_else(|| self.selection.min(self.files.len().saturating_sub(1)));
+    }
+
+    pub fn selected_file(&self) -> Option<&sync::File> {
+        self.files.get(self.selection)
+    }
+
+    pub fn move_selection(&mut self, scroll: ScrollType) -> bool {
+        let max = self.files.len().saturating_sub(1);
+        let new_selection = match scroll {
+            ScrollType::Up => self.selection.saturating_sub(1),
+            ScrollType::Down => self.selection.saturating_add(1).min(max),
+            ScrollType::Home | ScrollType::PageUp => 0,
+            ScrollType::End | ScrollType::PageDown => max,
+        };
+
+        let changed = new_selection != self.selection;
+        self.selection = new_selection;
+        changed
+    }
+
+    fn get_entry_to_add<'a>(&self, file: &'a sync::File, selected: bool) -> Line<'a> {
+        let action = file
+            .current_action()
+            .map(|a| a.to_string())
+            .unwrap_or_default();
+
+        Line::from(vec![
+            Span::styled(
+                format!("{action:<11} "),
+                self.theme.file_action(file.current_action(), selected),
+            ),
+            Span::styled(
+                file.depot_path.to_string_lossy(),
+                self.theme.text(true, selected),
+            ),
+        ])
+    }
 }
 
 impl DrawableComponent for FileListComponent {
     fn draw<B: Backend>(&self, f: &mut Frame<B>, r: Rect) -> Result<()> {
-        let b = Block::default()
-            .title(Span::styled(
-                self.title.as_str(),
-                self.theme.title(self.focused),
-            ))
-            .borders(Borders::ALL)
-            .border_style(self.theme.block(self.focused));
-
-        f.render_widget(b, r);
+        let height = r.height.saturating_sub(2) as usize;
+        let top = self.scroll.update(self.selection, self.files.len(), height);
+
+        let lines = self
+            .files
+            .iter()
+            .enumerate()
+            .skip(top)
+            .take(height)
+            .map(|(i, file)| self.get_entry_to_add(file, self.focused && i == self.selection))
+            .collect::<Vec<_>>();
+
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(Span::styled(
+                        self.title.as_str(),
+                        self.theme.title(self.focused),
+                    ))
+                    .borders(Borders::ALL)
+                    .border_style(self.theme.block(self.focused)),
+            ),
+            r,
+        );
+
+        self.scroll.draw(f, r, &self.theme);
+
         Ok(())
     }
 }
 
 impl Component for FileListComponent {
-    fn commands(&self, out: &mut Vec<CommandInfo>, force_all: bool) -> CommandBlocking {
+    fn commands(&self, _out: &mut Vec<CommandInfo>, _force_all: bool) -> CommandBlocking {
         CommandBlocking::PassingOn
     }
 
     fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.focused {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if let Event::Key(e) = ev {
+            if key_match(e, self.key_config.keys.move_down) {
+                return Ok(self.move_selection(ScrollType::Down).into());
+            } else if key_match(e, self.key_config.keys.move_up) {
+                return Ok(self.move_selection(ScrollType::Up).into());
+            }
+        }
+
         Ok(EventState::NotConsumed)
     }
 
@@ -65,6 +152,6 @@ impl Component for FileListComponent {
     }
 
     fn focus(&mut self, focus: bool) {
-        self.focused = focus
+        self.focused = focus;
     }
 }
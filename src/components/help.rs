@@ -163,7 +163,7 @@ impl HelpComponent {
 
         self.cmds.sort_by_key(|e| e.text.clone());
         self.cmds.dedup_by_key(|e| e.text.clone());
-        // self.cmds.sort_by_key(|e| e.text.group);
+        self.cmds.sort_by_key(|e| e.text.group);
     }
 
     fn move_selection(&mut self, inc: bool) {
@@ -185,34 +185,35 @@ impl HelpComponent {
 
         let mut processed = 0u16;
 
-        // for (key, group) in &self.cmds.iter().group_by(|e| e.text.group) {
-        //     txt.push(Line::from(Span::styled(
-        //         Cow::from(key.to_string()),
-        //         Style::default().add_modifier(Modifier::REVERSED),
-        //     )));
-        //
-        //     for command_info in group {
-        //         let is_selected = self.selection == processed;
-        //
-        //         processed += 1;
-        //
-        //         txt.push(Line::from(Span::styled(
-        //             Cow::from(if is_selected {
-        //                 format!(">{}", command_info.text.name)
-        //             } else {
-        //                 format!(" {}", command_info.text.name)
-        //             }),
-        //             self.theme.text(true, is_selected),
-        //         )));
-        //
-        //         if is_selected {
-        //             txt.push(Line::from(Span::styled(
-        //                 Cow::from(format!(" {}\n", command_info.text.desc)),
-        //                 self.theme.text(true, is_selected),
-        //             )));
-        //         }
-        //     }
-        // }
+        // commands are sorted by group, so each group is one run
+        for group in self.cmds.chunk_by(|a, b| a.text.group == b.text.group) {
+            txt.push(Line::from(Span::styled(
+                Cow::from(group[0].text.group),
+                Style::default().add_modifier(Modifier::REVERSED),
+            )));
+
+            for command_info in group {
+                let is_selected = self.selection == processed;
+
+                processed += 1;
+
+                txt.push(Line::from(Span::styled(
+                    Cow::from(if is_selected {
+                        format!(">{}", command_info.text.name)
+                    } else {
+                        format!(" {}", command_info.text.name)
+                    }),
+                    self.theme.text(true, is_selected),
+                )));
+
+                if is_selected {
+                    txt.push(Line::from(Span::styled(
+                        Cow::from(format!(" {}\n", command_info.text.desc)),
+                        self.theme.text(true, is_selected),
+                    )));
+                }
+            }
+        }
 
         txt
     }
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use crate::{
+    keys::{key_match, SharedKeyConfig},
+    queue::{InternalEvent, Queue},
+    strings,
+    ui::{self, style::SharedTheme},
+};
+
+use super::{
+    visibility_blocking, CommandBlocking, CommandInfo, Component, DrawableComponent, EventState,
+};
+
+use anyhow::Result;
+use asyncp4::{
+    asyncjob::AsyncSingleJob, sync::P4Runner, AsyncLoginJob, AsyncP4Notification, P4Message,
+};
+use crossbeam_channel::Sender;
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+pub struct LoginComponent {
+    visible: bool,
+    password: String,
+    message: String,
+    runner: Arc<dyn P4Runner>,
+    job: AsyncSingleJob<AsyncLoginJob>,
+    queue: Queue,
+    key_config: SharedKeyConfig,
+    theme: SharedTheme,
+}
+
+impl DrawableComponent for LoginComponent {
+    fn draw<B: Backend>(&self, f: &mut Frame<B>, _rect: Rect) -> Result<()> {
+        if self.visible {
+            const SIZE: (u16, u16) = (60, 7);
+
+            let area = ui::centered_rect_absolute(SIZE.0, SIZE.1, f.size());
+
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Block::default()
+                    .title(Span::styled(
+                        strings::login_title(&self.key_config),
+                        self.theme.title(true),
+                    ))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Thick),
+                area,
+            );
+
+            let chunks = Layout::default()
+                .vertical_margin(1)
+                .horizontal_margin(1)
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(1)].as_ref())
+                .split(area);
+
+            f.render_widget(
+                Paragraph::new(self.message.as_str())
+                    .style(self.theme.text(false, false))
+                    .wrap(Wrap { trim: true }),
+                chunks[0],
+            );
+
+            f.render_widget(
+                Paragraph::new(Line::from(vec![
+                    Span::raw(strings::login_password()),
+                    Span::styled(
+                        "*".repeat(self.password.chars().count()),
+                        self.theme.text(true, false),
+                    ),
+                ])),
+                chunks[1],
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Component for LoginComponent {
+    fn commands(&self, out: &mut Vec<CommandInfo>, force_all: bool) -> CommandBlocking {
+        if self.visible || force_all {
+            out.push(CommandInfo::new(
+                strings::commands::login_confirm(&self.key_config),
+                !self.password.is_empty() && !self.job.is_pending(),
+                true,
+            ));
+
+            out.push(CommandInfo::new(
+                strings::commands::close_popup(&self.key_config),
+                true,
+                true,
+            ));
+        }
+
+        visibility_blocking(self)
+    }
+
+    fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.visible {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if let Event::Key(e) = ev {
+            if key_match(e, self.key_config.keys.exit_popup) {
+                self.hide();
+            } else if key_match(e, self.key_config.keys.enter) {
+                self.submit();
+            } else if e.code == KeyCode::Backspace {
+                self.password.pop();
+            } else if let KeyCode::Char(c) = e.code {
+                if e.modifiers.difference(KeyModifiers::SHIFT).is_empty() {
+                    self.password.push(c);
+                }
+            }
+        }
+
+        Ok(EventState::Consumed)
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn hide(&mut self) {
+        self.job.cancel();
+        self.visible = false;
+        self.password.clear();
+    }
+
+    fn show(&mut self) -> Result<()> {
+        self.visible = true;
+
+        Ok(())
+    }
+}
+
+impl LoginComponent {
+    pub fn new(
+        runner: Arc<dyn P4Runner>,
+        sender: &Sender<AsyncP4Notification>,
+        queue: Queue,
+        key_config: SharedKeyConfig,
+        theme: SharedTheme,
+    ) -> Self {
+        Self {
+            visible: false,
+            password: String::new(),
+            message: String::new(),
+            runner,
+            job: AsyncSingleJob::new(sender.clone()),
+            queue,
+            key_config,
+            theme,
+        }
+    }
+
+    pub fn open(&mut self, message: &P4Message) -> Result<()> {
+        self.message = message.text.clone();
+        self.password.clear();
+        self.show()
+    }
+
+    /// Picks up the result of `p4 login`.
+    pub fn update_async(&mut self, ev: AsyncP4Notification) {
+        if ev != AsyncP4Notification::Login {
+            return;
+        }
+
+        match self.job.take_last().and_then(|job| job.result()) {
+            Some(Ok(())) if self.visible => {
+                self.hide();
+                self.queue.push(InternalEvent::LoggedIn);
+            }
+            Some(Err(e)) if self.visible => {
+                self.message = e.to_string();
+                self.password.clear();
+            }
+            _ => {}
+        }
+    }
+
+    fn submit(&mut self) {
+        if self.password.is_empty() || self.job.is_pending() {
+            return;
+        }
+
+        self.message = strings::login_pending();
+        self.job.spawn(AsyncLoginJob::new(
+            self.runner.clone(),
+            self.password.clone(),
+        ));
+    }
+}
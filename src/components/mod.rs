@@ -1,13 +1,25 @@
 mod changelist;
+mod changelist_details;
 mod command;
+mod confirm;
 mod diff;
 mod filelist;
 mod help;
+mod login;
+mod msg;
+mod options_popup;
 mod utils;
 
+pub use changelist::ChangelistComponent;
+pub use changelist_details::ChangelistDetailsComponent;
 pub use command::{CommandInfo, CommandText};
+pub use confirm::ConfirmComponent;
 pub use diff::DiffComponent;
+pub use filelist::FileListComponent;
 pub use help::HelpComponent;
+pub use login::LoginComponent;
+pub use msg::MsgComponent;
+pub use options_popup::{AppOption, OptionsPopupComponent};
 
 use anyhow::Result;
 use crossterm::event::Event;
@@ -75,6 +87,16 @@ pub fn event_pump(ev: &Event, components: &mut [&mut dyn Component]) -> Result<E
     Ok(EventState::NotConsumed)
 }
 
+/// Collects the commands of `components` in order, stopping after the first
+/// one that blocks those behind it, e.g. a visible popup.
+pub fn command_pump(out: &mut Vec<CommandInfo>, force_all: bool, components: &[&dyn Component]) {
+    for c in components {
+        if c.commands(out, force_all) != CommandBlocking::PassingOn && !force_all {
+            break;
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum ScrollType {
     Up,
@@ -142,6 +164,11 @@ pub trait Component {
 
     fn focus(&mut self, _focus: bool) {}
 
+    /// Text is being typed, so keys must reach the component first.
+    fn is_input_active(&self) -> bool {
+        false
+    }
+
     fn is_visible(&self) -> bool {
         true
     }
@@ -151,13 +178,4 @@ pub trait Component {
     fn show(&mut self) -> Result<()> {
         Ok(())
     }
-
-    fn toggle_visible(&mut self) -> Result<()> {
-        if self.is_visible() {
-            self.hide();
-            Ok(())
-        } else {
-            self.show()
-        }
-    }
 }
@@ -0,0 +1,113 @@
+use crate::{
+    keys::{key_match, SharedKeyConfig},
+    strings,
+    ui::{self, style::SharedTheme},
+};
+
+use super::{
+    visibility_blocking, CommandBlocking, CommandInfo, Component, DrawableComponent, EventState,
+};
+
+use anyhow::Result;
+use crossterm::event::Event;
+use ratatui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    text::Span,
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+pub struct MsgComponent {
+    title: String,
+    msg: String,
+    visible: bool,
+    key_config: SharedKeyConfig,
+    theme: SharedTheme,
+}
+
+impl DrawableComponent for MsgComponent {
+    fn draw<B: Backend>(&self, f: &mut Frame<B>, _rect: Rect) -> Result<()> {
+        if self.visible {
+            const SIZE: (u16, u16) = (60, 8);
+
+            let area = ui::centered_rect_absolute(SIZE.0, SIZE.1, f.size());
+
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(self.msg.as_str())
+                    .block(
+                        Block::default()
+                            .title(Span::styled(self.title.as_str(), self.theme.title(true)))
+                            .borders(Borders::ALL)
+                            .border_type(BorderType::Thick),
+                    )
+                    .alignment(Alignment::Left)
+                    .wrap(Wrap { trim: true }),
+                area,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Component for MsgComponent {
+    fn commands(&self, out: &mut Vec<CommandInfo>, _force_all: bool) -> CommandBlocking {
+        out.push(CommandInfo::new(
+            strings::commands::close_msg(&self.key_config),
+            true,
+            self.visible,
+        ));
+
+        visibility_blocking(self)
+    }
+
+    fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.visible {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if let Event::Key(e) = ev {
+            if key_match(e, self.key_config.keys.enter)
+                || key_match(e, self.key_config.keys.exit_popup)
+            {
+                self.hide();
+            }
+        }
+
+        Ok(EventState::Consumed)
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    fn show(&mut self) -> Result<()> {
+        self.visible = true;
+
+        Ok(())
+    }
+}
+
+impl MsgComponent {
+    pub const fn new(key_config: SharedKeyConfig, theme: SharedTheme) -> Self {
+        Self {
+            title: String::new(),
+            msg: String::new(),
+            visible: false,
+            key_config,
+            theme,
+        }
+    }
+
+    pub fn show_error(&mut self, msg: &str) -> Result<()> {
+        self.title = strings::msg_title_error(&self.key_config);
+        self.msg = msg.to_string();
+        self.show()
+    }
+}
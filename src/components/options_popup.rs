@@ -0,0 +1,238 @@
+use crate::{
+    keys::{key_match, SharedKeyConfig},
+    options::SharedOptions,
+    queue::{InternalEvent, Queue},
+    strings,
+    ui::{self, style::SharedTheme},
+};
+
+use super::{
+    visibility_blocking, CommandBlocking, CommandInfo, Component, DrawableComponent, EventState,
+};
+
+use anyhow::Result;
+use crossterm::event::Event;
+use ratatui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+    Frame,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AppOption {
+    Whitespace,
+    ContextLines,
+    InterhunkLines,
+    MaxFileSize,
+}
+
+const APP_OPTIONS: [AppOption; 4] = [
+    AppOption::Whitespace,
+    AppOption::ContextLines,
+    AppOption::InterhunkLines,
+    AppOption::MaxFileSize,
+];
+
+pub struct OptionsPopupComponent {
+    selection: usize,
+    visible: bool,
+    queue: Queue,
+    options: SharedOptions,
+    key_config: SharedKeyConfig,
+    theme: SharedTheme,
+}
+
+impl OptionsPopupComponent {
+    pub const fn new(
+        queue: Queue,
+        options: SharedOptions,
+        key_config: SharedKeyConfig,
+        theme: SharedTheme,
+    ) -> Self {
+        Self {
+            selection: 0,
+            visible: false,
+            queue,
+            options,
+            key_config,
+            theme,
+        }
+    }
+
+    fn get_text(&self, width: u16) -> Vec<Line<'_>> {
+        let diff = self.options.borrow().diff_options();
+
+        let mut txt = vec![Line::from(Span::styled(
+            strings::options::diff_header(),
+            self.theme.title(true),
+        ))];
+
+        for (i, option) in APP_OPTIONS.iter().enumerate() {
+            let (name, value) = match option {
+                AppOption::Whitespace => (
+                    strings::options::diff_whitespace(),
+                    strings::options::diff_whitespace_value(diff.whitespace).to_string(),
+                ),
+                AppOption::ContextLines => {
+                    (strings::options::diff_context(), diff.context.to_string())
+                }
+                AppOption::InterhunkLines => (
+                    strings::options::diff_interhunk_lines(),
+                    diff.interhunk_lines.to_string(),
+                ),
+                AppOption::MaxFileSize => (
+                    strings::options::diff_max_size(),
+                    strings::file_size(diff.max_size),
+                ),
+            };
+
+            self.add_entry(&mut txt, width, name, &value, i == self.selection);
+        }
+
+        txt
+    }
+
+    fn add_entry(
+        &self,
+        txt: &mut Vec<Line<'_>>,
+        width: u16,
+        entry: &str,
+        value: &str,
+        selected: bool,
+    ) {
+        let half = usize::from(width / 2);
+        txt.push(Line::from(vec![
+            Span::styled(format!("{entry:>half$}"), self.theme.text(true, false)),
+            Span::styled(format!(" {value:<half$}"), self.theme.text(true, selected)),
+        ]));
+    }
+
+    fn move_selection(&mut self, up: bool) {
+        self.selection = if up {
+            self.selection.saturating_sub(1)
+        } else {
+            (self.selection + 1).min(APP_OPTIONS.len() - 1)
+        };
+    }
+
+    fn switch_option(&mut self, right: bool) {
+        let option = APP_OPTIONS[self.selection];
+
+        {
+            let mut options = self.options.borrow_mut();
+            match option {
+                AppOption::Whitespace => options.diff_whitespace_change(right),
+                AppOption::ContextLines => options.diff_context_change(right),
+                AppOption::InterhunkLines => options.diff_interhunk_lines_change(right),
+                AppOption::MaxFileSize => options.diff_max_size_change(right),
+            }
+        }
+
+        self.queue.push(InternalEvent::OptionSwitched(option));
+    }
+}
+
+impl DrawableComponent for OptionsPopupComponent {
+    fn draw<B: Backend>(&self, f: &mut Frame<B>, _rect: Rect) -> Result<()> {
+        if self.visible {
+            const SIZE: (u16, u16) = (50, 8);
+
+            let area = ui::centered_rect_absolute(SIZE.0, SIZE.1, f.size());
+
+            let block = Block::default()
+                .title(Span::styled(
+                    strings::options_title(&self.key_config),
+                    self.theme.title(true),
+                ))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Thick);
+            let width = block.inner(area).width;
+
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(self.get_text(width))
+                    .block(block)
+                    .alignment(Alignment::Left),
+                area,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl Component for OptionsPopupComponent {
+    fn commands(&self, out: &mut Vec<CommandInfo>, force_all: bool) -> CommandBlocking {
+        if self.visible || force_all {
+            out.push(CommandInfo::new(
+                strings::commands::scroll(&self.key_config),
+                true,
+                true,
+            ));
+            out.push(CommandInfo::new(
+                strings::commands::options_change_value(&self.key_config),
+                true,
+                true,
+            ));
+            out.push(CommandInfo::new(
+                strings::commands::close_popup(&self.key_config),
+                true,
+                true,
+            ));
+        }
+
+        if !self.visible || force_all {
+            out.push(CommandInfo::new(
+                strings::commands::options_popup(&self.key_config),
+                true,
+                true,
+            ));
+        }
+
+        visibility_blocking(self)
+    }
+
+    fn event(&mut self, ev: &Event) -> Result<EventState> {
+        let Event::Key(e) = ev else {
+            return Ok(self.visible.into());
+        };
+
+        if !self.visible {
+            if key_match(e, self.key_config.keys.open_options) {
+                self.show()?;
+                return Ok(EventState::Consumed);
+            }
+            return Ok(EventState::NotConsumed);
+        }
+
+        if key_match(e, self.key_config.keys.exit_popup) {
+            self.hide();
+        } else if key_match(e, self.key_config.keys.move_up) {
+            self.move_selection(true);
+        } else if key_match(e, self.key_config.keys.move_down) {
+            self.move_selection(false);
+        } else if key_match(e, self.key_config.keys.move_right) {
+            self.switch_option(true);
+        } else if key_match(e, self.key_config.keys.move_left) {
+            self.switch_option(false);
+        }
+
+        Ok(EventState::Consumed)
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    fn show(&mut self) -> Result<()> {
+        self.visible = true;
+
+        Ok(())
+    }
+}
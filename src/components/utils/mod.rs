@@ -1,2 +1,3 @@
 pub mod scroll_horizontal;
 pub mod scroll_vertical;
+pub mod search;
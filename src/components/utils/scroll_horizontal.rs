@@ -0,0 +1,103 @@
+use std::cell::Cell;
+
+use crate::{
+    components::HorizontalScrollType,
+    ui::{draw_scrollbar, style::SharedTheme, Orientation},
+};
+
+use ratatui::{backend::Backend, layout::Rect, Frame};
+
+pub struct HorizontalScroll {
+    right: Cell<usize>,
+    max_right: Cell<usize>,
+}
+
+impl HorizontalScroll {
+    pub const fn new() -> Self {
+        Self {
+            right: Cell::new(0),
+            max_right: Cell::new(0),
+        }
+    }
+
+    pub fn get_right(&self) -> usize {
+        self.right.get()
+    }
+
+    pub fn reset(&self) {
+        self.right.set(0);
+    }
+
+    pub fn can_scroll(&self) -> bool {
+        self.max_right.get() > 0
+    }
+
+    pub fn move_right(&self, move_type: HorizontalScrollType) -> bool {
+        let old = self.right.get();
+        let max = self.max_right.get();
+
+        let new_scroll_right = match move_type {
+            HorizontalScrollType::Left => old.saturating_sub(1),
+            HorizontalScrollType::Right => old.saturating_add(1),
+        };
+
+        let new_scroll_right = new_scroll_right.clamp(0, max);
+
+        if new_scroll_right == old {
+            return false;
+        }
+
+        self.right.set(new_scroll_right);
+        true
+    }
+
+    /// Keeps the scroll position within `column_count` columns of which
+    /// `visual_width` fit on screen.
+    pub fn update_no_selection(&self, column_count: usize, visual_width: usize) -> usize {
+        let max_right = if visual_width == 0 {
+            0
+        } else {
+            column_count.saturating_sub(visual_width)
+        };
+
+        self.max_right.set(max_right);
+
+        let new_right = self.right.get().min(max_right);
+        self.right.set(new_right);
+
+        new_right
+    }
+
+    pub fn draw<B: Backend>(&self, f: &mut Frame<B>, r: Rect, theme: &SharedTheme) {
+        draw_scrollbar(
+            f,
+            r,
+            theme,
+            self.max_right.get(),
+            self.right.get(),
+            Orientation::Horizontal,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scroll_bounds() {
+        let scroll = HorizontalScroll::new();
+        scroll.update_no_selection(12, 10);
+
+        assert!(!scroll.move_right(HorizontalScrollType::Left));
+        assert!(scroll.move_right(HorizontalScrollType::Right));
+        assert!(scroll.move_right(HorizontalScrollType::Right));
+        assert!(!scroll.move_right(HorizontalScrollType::Right));
+        assert_eq!(scroll.get_right(), 2);
+
+        // content got narrower, e.g. after a resize
+        assert_eq!(scroll.update_no_selection(11, 10), 1);
+        assert_eq!(scroll.update_no_selection(5, 10), 0);
+        assert!(!scroll.can_scroll());
+    }
+}
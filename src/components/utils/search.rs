@@ -0,0 +1,144 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+/// Query typed after `/`, matched literally or as a regular expression.
+#[derive(Default)]
+pub struct Search {
+    query: String,
+    editing: bool,
+    ignore_case: bool,
+    regex: bool,
+    /// `None` for an empty query or an invalid regex
+    matcher: Option<Regex>,
+}
+
+impl Search {
+    /// Starts typing a new query, keeping the modes.
+    pub fn start(&mut self) {
+        self.query.clear();
+        self.matcher = None;
+        self.editing = true;
+    }
+
+    /// Stops typing, keeping the query and its matches.
+    pub fn stop(&mut self) {
+        self.editing = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.query.clear();
+        self.matcher = None;
+        self.editing = false;
+    }
+
+    pub const fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    /// Whether the search bar is shown.
+    pub fn is_active(&self) -> bool {
+        self.editing || !self.query.is_empty()
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub const fn ignore_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    pub const fn regex(&self) -> bool {
+        self.regex
+    }
+
+    /// The query does not compile as a regex.
+    pub fn is_invalid(&self) -> bool {
+        !self.query.is_empty() && self.matcher.is_none()
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.update_matcher();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.update_matcher();
+    }
+
+    pub fn toggle_ignore_case(&mut self) {
+        self.ignore_case = !self.ignore_case;
+        self.update_matcher();
+    }
+
+    pub fn toggle_regex(&mut self) {
+        self.regex = !self.regex;
+        self.update_matcher();
+    }
+
+    /// Byte ranges of all non-empty matches in `text`.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        self.matcher.as_ref().map_or_else(Vec::new, |matcher| {
+            matcher
+                .find_iter(text)
+                .map(|m| m.range())
+                .filter(|range| !range.is_empty())
+                .collect()
+        })
+    }
+
+    fn update_matcher(&mut self) {
+        if self.query.is_empty() {
+            self.matcher = None;
+            return;
+        }
+
+        let pattern = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+
+        self.matcher = RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn typed(query: &str) -> Search {
+        let mut search = Search::default();
+        search.start();
+        query.chars().for_each(|c| search.push(c));
+        search
+    }
+
+    #[test]
+    fn test_find() {
+        let mut search = typed("a.c");
+        assert_eq!(search.find("abc a.c A.C"), vec![4..7]);
+
+        search.toggle_ignore_case();
+        assert_eq!(search.find("abc a.c A.C"), vec![4..7, 8..11]);
+
+        search.toggle_regex();
+        assert_eq!(search.find("abc a.c A.C"), vec![0..3, 4..7, 8..11]);
+
+        search.pop();
+        search.push('(');
+        assert!(search.is_invalid());
+        assert_eq!(search.find("a.("), Vec::<Range<usize>>::new());
+
+        // empty matches would highlight nothing
+        let mut search = typed("x*");
+        search.toggle_regex();
+        assert_eq!(search.find("axxb"), vec![1..3]);
+    }
+}
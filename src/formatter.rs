@@ -0,0 +1,180 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use asyncp4::{
+    asyncjob::{AsyncJob, RunParams},
+    sync::{self, CancelToken},
+    Error,
+};
+
+use crate::AsyncAppNotification;
+
+/// Formatters still running after this long are killed.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs the configured diff formatter, e.g. `delta`, through the shell with
+/// the unified `diff` on stdin and returns its output. `COLUMNS` holds the
+/// width available for it.
+pub fn format_diff(
+    command: &str,
+    diff: String,
+    width: usize,
+    cancel: &CancelToken,
+) -> Result<Vec<u8>> {
+    let mut process = shell(command)
+        .env("COLUMNS", width.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("`{command}`: {e}"))?;
+
+    let mut stdin = process
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("`{command}`: no stdin"))?;
+
+    // written on its own thread, as the formatter may fill its output pipe
+    // before it read all input
+    let writer = thread::spawn(move || stdin.write_all(diff.as_bytes()));
+
+    let out =
+        sync::wait_for_child(process, command, cancel, Some(TIMEOUT)).map_err(|e| match e {
+            Error::Timeout(_, timeout) => anyhow!("`{command}` timed out after {timeout:?}"),
+            e => anyhow!("`{command}`: {e}"),
+        })?;
+
+    // a formatter is free to stop reading early
+    writer.join().ok();
+
+    if out.status == 0 {
+        Ok(out.stdout)
+    } else {
+        let msg = if out.stderr.is_empty() {
+            format!("exit status {}", out.status)
+        } else {
+            String::from_utf8_lossy(&out.stderr).to_string()
+        };
+        Err(anyhow!("`{command}`: {msg}"))
+    }
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatParams {
+    pub command: String,
+    pub width: usize,
+    /// the unified diff to format
+    pub diff: String,
+}
+
+enum JobState {
+    Request(FormatParams),
+    Response(FormatParams, Result<Vec<u8>>),
+}
+
+/// Runs the diff formatter off the UI thread.
+#[derive(Clone)]
+pub struct AsyncFormatJob {
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncFormatJob {
+    pub fn new(params: FormatParams) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(Some(JobState::Request(params)))),
+        }
+    }
+
+    /// The formatter output along with the params it was requested with.
+    pub fn result(&self) -> Option<(FormatParams, Result<Vec<u8>>)> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(params, result)) = state.take() {
+                return Some((params, result));
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncFormatJob {
+    type Notification = AsyncAppNotification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        params: RunParams<Self::Notification, Self::Progress>,
+    ) -> asyncp4::Result<Self::Notification> {
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(request) => {
+                let result = format_diff(
+                    &request.command,
+                    request.diff.clone(),
+                    request.width,
+                    params.cancel_token(),
+                );
+                JobState::Response(request, result)
+            }
+            response => response,
+        });
+
+        Ok(AsyncAppNotification::Formatted)
+    }
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_format_diff() {
+        let output = format_diff(
+            "tr a-z A-Z; echo $COLUMNS",
+            "+a\n".to_string(),
+            42,
+            &CancelToken::default(),
+        )
+        .unwrap();
+
+        assert_eq!(output, b"+A\n42\n");
+    }
+
+    #[test]
+    fn test_cancel_kills_formatter() {
+        let cancel = CancelToken::default();
+        let canceller = cancel.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            canceller.cancel();
+        });
+
+        let started = Instant::now();
+        let result = format_diff("sleep 5", String::new(), 80, &cancel);
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
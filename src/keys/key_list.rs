@@ -49,6 +49,25 @@ pub struct KeysList {
     pub move_down: P4KeyEvent,
     pub exit_popup: P4KeyEvent,
     pub open_help: P4KeyEvent,
+    pub open_options: P4KeyEvent,
+    pub cmd_bar_toggle: P4KeyEvent,
+    pub enter: P4KeyEvent,
+    pub focus_next: P4KeyEvent,
+    pub focus_prev: P4KeyEvent,
+    pub diff_split_view: P4KeyEvent,
+    pub diff_wrap: P4KeyEvent,
+    pub diff_show_whitespace: P4KeyEvent,
+    pub diff_formatter: P4KeyEvent,
+    pub diff_hunk_next: P4KeyEvent,
+    pub diff_hunk_prev: P4KeyEvent,
+    pub diff_hunk_copy: P4KeyEvent,
+    pub diff_hunk_discard: P4KeyEvent,
+    pub diff_hex_dump: P4KeyEvent,
+    pub diff_search: P4KeyEvent,
+    pub diff_search_next: P4KeyEvent,
+    pub diff_search_prev: P4KeyEvent,
+    pub diff_search_ignore_case: P4KeyEvent,
+    pub diff_search_regex: P4KeyEvent,
 }
 
 impl Default for KeysList {
@@ -64,6 +83,25 @@ impl Default for KeysList {
             move_down: P4KeyEvent::new(KeyCode::Down, KeyModifiers::empty()),
             exit_popup: P4KeyEvent::new(KeyCode::Esc, KeyModifiers::empty()),
             open_help: P4KeyEvent::new(KeyCode::Char('h'), KeyModifiers::empty()),
+            open_options: P4KeyEvent::new(KeyCode::Char('o'), KeyModifiers::empty()),
+            cmd_bar_toggle: P4KeyEvent::new(KeyCode::Char('.'), KeyModifiers::empty()),
+            enter: P4KeyEvent::new(KeyCode::Enter, KeyModifiers::empty()),
+            focus_next: P4KeyEvent::new(KeyCode::Tab, KeyModifiers::empty()),
+            focus_prev: P4KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT),
+            diff_split_view: P4KeyEvent::new(KeyCode::Char('v'), KeyModifiers::empty()),
+            diff_wrap: P4KeyEvent::new(KeyCode::Char('w'), KeyModifiers::empty()),
+            diff_show_whitespace: P4KeyEvent::new(KeyCode::Char('W'), KeyModifiers::SHIFT),
+            diff_formatter: P4KeyEvent::new(KeyCode::Char('f'), KeyModifiers::empty()),
+            diff_hunk_next: P4KeyEvent::new(KeyCode::Char('n'), KeyModifiers::empty()),
+            diff_hunk_prev: P4KeyEvent::new(KeyCode::Char('p'), KeyModifiers::empty()),
+            diff_hunk_copy: P4KeyEvent::new(KeyCode::Char('y'), KeyModifiers::empty()),
+            diff_hunk_discard: P4KeyEvent::new(KeyCode::Char('D'), KeyModifiers::SHIFT),
+            diff_hex_dump: P4KeyEvent::new(KeyCode::Char('x'), KeyModifiers::empty()),
+            diff_search: P4KeyEvent::new(KeyCode::Char('/'), KeyModifiers::empty()),
+            diff_search_next: P4KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL),
+            diff_search_prev: P4KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL),
+            diff_search_ignore_case: P4KeyEvent::new(KeyCode::Char('c'), KeyModifiers::ALT),
+            diff_search_regex: P4KeyEvent::new(KeyCode::Char('r'), KeyModifiers::ALT),
         }
     }
 }
@@ -1,10 +1,14 @@
 mod app;
 mod args;
+mod clipboard;
 mod commandbar;
 mod components;
+mod formatter;
 mod input;
 mod keys;
 mod notify_mutex;
+mod options;
+mod queue;
 mod strings;
 mod tabbar;
 mod tabs;
@@ -14,12 +18,19 @@ mod version;
 use std::{
     io::{self, Stdout},
     path::PathBuf,
+    sync::Arc,
 };
 
 use anyhow::{bail, Context, Result};
 use app::App;
-use args::process_cmdline;
-use crossbeam_channel::{Receiver, Select};
+use args::{process_cmdline, CliArgs};
+use asyncp4::{
+    sync::{
+        ConnectionSettings, FixtureRunner, P4Runner, ProcessRunner, RecordingRunner, Transcript,
+    },
+    AsyncP4Notification,
+};
+use crossbeam_channel::{unbounded, Receiver, Select};
 use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
@@ -29,9 +40,25 @@ use keys::KeyConfig;
 use ratatui::{backend::CrosstermBackend, prelude::Backend, Terminal};
 use ui::style::Theme;
 
+/// Sent by async jobs of the app itself rather than of `asyncp4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsyncAppNotification {
+    /// the diff formatter finished
+    Formatted,
+    /// a diff was syntax highlighted
+    Highlighted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsyncNotification {
+    App(AsyncAppNotification),
+    P4(AsyncP4Notification),
+}
+
 #[derive(Clone)]
 pub enum QueueEvent {
     Notify,
+    AsyncEvent(AsyncNotification),
     InputEvent(InputEvent),
 }
 
@@ -43,12 +70,43 @@ fn main() -> Result<()> {
         .unwrap_or_default();
     let theme = Theme::init(&cliargs.theme);
 
+    let connection = ConnectionSettings::resolve(&cliargs.cwd);
+    let runner = create_runner(&cliargs, &connection)?;
+
     let mut terminal = setup_terminal().context("setup terminal failed")?;
-    run(cliargs.cwd, &mut terminal, key_config, theme).context("app loop failed")?;
+    run(
+        cliargs.cwd,
+        connection,
+        runner,
+        &mut terminal,
+        key_config,
+        theme,
+    )
+    .context("app loop failed")?;
     shutdown_terminal(&mut terminal).context("restore terminal failed")?;
     Ok(())
 }
 
+fn create_runner(cliargs: &CliArgs, connection: &ConnectionSettings) -> Result<Arc<dyn P4Runner>> {
+    if let Some(path) = &cliargs.replay {
+        let transcript = Transcript::load(path).context("loading transcript failed")?;
+        return Ok(Arc::new(FixtureRunner::from_transcript(transcript)));
+    }
+
+    let mut runner = ProcessRunner::new(cliargs.output_format).with_connection(connection);
+    if let Some(timeout) = cliargs.timeout {
+        runner = runner.with_timeout(timeout);
+    }
+
+    Ok(match &cliargs.record {
+        Some(path) => Arc::new(
+            RecordingRunner::new(Box::new(runner), path.clone())
+                .context("creating transcript failed")?,
+        ),
+        None => Arc::new(runner),
+    })
+}
+
 fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
     let mut stdout = io::stdout();
     enable_raw_mode().context("failed to enable raw mode")?;
@@ -65,28 +123,38 @@ fn shutdown_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Resul
 
 fn run(
     cwd: PathBuf,
+    connection: ConnectionSettings,
+    runner: Arc<dyn P4Runner>,
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     key_config: KeyConfig,
     theme: Theme,
 ) -> Result<()> {
+    let (tx_p4, rx_p4) = unbounded();
+    let (tx_app, rx_app) = unbounded();
+
     let input = Input::new();
 
     let rx_input = input.receiver();
 
     let mut first_update = true;
-    let mut app = App::new(cwd, input, key_config, theme);
+    let mut app = App::new(
+        cwd, connection, runner, &tx_p4, &tx_app, input, key_config, theme,
+    );
     loop {
         let event = if first_update {
             first_update = false;
             QueueEvent::Notify
         } else {
-            select_event(&rx_input)?
+            select_event(&rx_input, &rx_p4, &rx_app)?
         };
 
         match event {
             QueueEvent::Notify => {
                 app.update()?;
             }
+            QueueEvent::AsyncEvent(ev) => {
+                app.update_async(ev)?;
+            }
             QueueEvent::InputEvent(ev) => {
                 if matches!(ev, InputEvent::State(InputState::Polling)) {
                     terminal.hide_cursor()?;
@@ -96,6 +164,8 @@ fn run(
         }
 
         draw(terminal, &app)?;
+        // the formatter depends on the width the diff was just drawn with
+        app.update_formatter();
 
         if app.is_quit() {
             break;
@@ -104,16 +174,28 @@ fn run(
     Ok(())
 }
 
-fn select_event(rx_input: &Receiver<InputEvent>) -> Result<QueueEvent> {
+fn select_event(
+    rx_input: &Receiver<InputEvent>,
+    rx_p4: &Receiver<AsyncP4Notification>,
+    rx_app: &Receiver<AsyncAppNotification>,
+) -> Result<QueueEvent> {
     let mut sel = Select::new();
 
     sel.recv(rx_input);
+    sel.recv(rx_p4);
+    sel.recv(rx_app);
 
     let oper = sel.select();
     let index = oper.index();
 
     let ev = match index {
         0 => oper.recv(rx_input).map(QueueEvent::InputEvent),
+        1 => oper
+            .recv(rx_p4)
+            .map(|ev| QueueEvent::AsyncEvent(AsyncNotification::P4(ev))),
+        2 => oper
+            .recv(rx_app)
+            .map(|ev| QueueEvent::AsyncEvent(AsyncNotification::App(ev))),
         _ => bail!("unknown select source"),
     }?;
 
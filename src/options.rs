@@ -0,0 +1,141 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{Read, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use anyhow::Result;
+use asyncp4::sync::{DiffOptions, DiffWhitespace};
+use ron::{
+    de::from_bytes,
+    ser::{to_string_pretty, PrettyConfig},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::args::get_app_config_path;
+
+const DIFF_CONTEXT_MAX: u32 = 100;
+const DIFF_INTERHUNK_LINES_MAX: u32 = 100;
+const DIFF_MAX_SIZE_MIN: u64 = 64 * 1024;
+const DIFF_MAX_SIZE_MAX: u64 = 1024 * 1024 * 1024;
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct OptionsData {
+    diff: DiffOptions,
+    /// shell command that formats diffs, e.g. `delta --width=$COLUMNS`
+    diff_formatter: Option<String>,
+}
+
+/// User settings changed from within the app, kept in `options.ron` next to
+/// `key_bindings.ron`.
+pub struct Options {
+    data: OptionsData,
+}
+
+pub type SharedOptions = Rc<RefCell<Options>>;
+
+impl Options {
+    pub fn new() -> SharedOptions {
+        let data = Self::read().unwrap_or_else(|e| {
+            log::info!("no options loaded: {e}");
+            OptionsData::default()
+        });
+
+        Rc::new(RefCell::new(Self { data }))
+    }
+
+    pub const fn diff_options(&self) -> DiffOptions {
+        self.data.diff
+    }
+
+    pub fn diff_formatter(&self) -> Option<String> {
+        self.data
+            .diff_formatter
+            .clone()
+            .filter(|command| !command.trim().is_empty())
+    }
+
+    pub fn diff_whitespace_change(&mut self, forward: bool) {
+        let modes = [
+            DiffWhitespace::Show,
+            DiffWhitespace::IgnoreChanges,
+            DiffWhitespace::IgnoreAll,
+        ];
+        let current = modes
+            .iter()
+            .position(|m| *m == self.data.diff.whitespace)
+            .unwrap_or_default();
+        let next = if forward {
+            (current + 1) % modes.len()
+        } else {
+            (current + modes.len() - 1) % modes.len()
+        };
+
+        self.data.diff.whitespace = modes[next];
+        self.save();
+    }
+
+    pub fn diff_context_change(&mut self, increase: bool) {
+        self.data.diff.context = change(self.data.diff.context, increase, DIFF_CONTEXT_MAX);
+        self.save();
+    }
+
+    pub fn diff_interhunk_lines_change(&mut self, increase: bool) {
+        self.data.diff.interhunk_lines = change(
+            self.data.diff.interhunk_lines,
+            increase,
+            DIFF_INTERHUNK_LINES_MAX,
+        );
+        self.save();
+    }
+
+    /// Doubles or halves the size above which files are not diffed.
+    pub fn diff_max_size_change(&mut self, increase: bool) {
+        let size = self.data.diff.max_size;
+        let size = if increase {
+            size.saturating_mul(2)
+        } else {
+            size / 2
+        };
+
+        self.data.diff.max_size = size.clamp(DIFF_MAX_SIZE_MIN, DIFF_MAX_SIZE_MAX);
+        self.save();
+    }
+
+    fn options_file() -> Result<PathBuf> {
+        Ok(get_app_config_path()?.join("options.ron"))
+    }
+
+    fn read() -> Result<OptionsData> {
+        let mut file = File::open(Self::options_file()?)?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        Ok(from_bytes(&buffer)?)
+    }
+
+    fn save(&self) {
+        if let Err(e) = self.save_failable() {
+            log::error!("options save error: {e}");
+        }
+    }
+
+    fn save_failable(&self) -> Result<()> {
+        let mut file = File::create(Self::options_file()?)?;
+        let data = to_string_pretty(&self.data, PrettyConfig::default())?;
+        file.write_all(data.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+fn change(value: u32, increase: bool, max: u32) -> u32 {
+    if increase {
+        value.saturating_add(1).min(max)
+    } else {
+        value.saturating_sub(1)
+    }
+}
@@ -0,0 +1,40 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use asyncp4::sync::{Hunk, P4Path};
+
+use crate::components::AppOption;
+
+/// Destructive actions that have to be confirmed first.
+pub enum Action {
+    /// depot path and the hunk as shown
+    DiscardHunk(P4Path, Hunk),
+}
+
+pub enum InternalEvent {
+    LoggedIn,
+    OptionSwitched(AppOption),
+    ConfirmAction(Action),
+    ConfirmedAction(Action),
+    ShowErrorMsg(String),
+    /// depot path of the opened file to hex dump
+    LoadHexDump(P4Path),
+}
+
+#[derive(Clone, Default)]
+pub struct Queue {
+    data: Rc<RefCell<VecDeque<InternalEvent>>>,
+}
+
+impl Queue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&self, ev: InternalEvent) {
+        self.data.borrow_mut().push_back(ev);
+    }
+
+    pub fn pop(&self) -> Option<InternalEvent> {
+        self.data.borrow_mut().pop_front()
+    }
+}
@@ -1,5 +1,7 @@
 use std::borrow::Cow;
 
+use asyncp4::sync::FileType;
+use bytesize::ByteSize;
 use unicode_truncate::UnicodeTruncateStr;
 use unicode_width::UnicodeWidthStr;
 
@@ -38,6 +40,157 @@ pub fn help_title(_kc: &SharedKeyConfig) -> String {
     "Help: all commands".to_string()
 }
 
+pub fn login_title(_kc: &SharedKeyConfig) -> String {
+    "Login".to_string()
+}
+
+pub fn options_title(_kc: &SharedKeyConfig) -> String {
+    "Options".to_string()
+}
+
+pub fn msg_title_error(_kc: &SharedKeyConfig) -> String {
+    "Error".to_string()
+}
+
+pub fn confirm_title_discard_hunk() -> String {
+    "Discard hunk".to_string()
+}
+
+pub fn confirm_msg_discard_hunk(path: &str) -> String {
+    format!("Drop the changes of this hunk from the local copy of {path}?")
+}
+
+pub fn login_password() -> String {
+    "Password: ".to_string()
+}
+
+pub fn login_pending() -> String {
+    "Logging in...".to_string()
+}
+
+pub fn changelists_title() -> String {
+    "Changelists".to_string()
+}
+
+pub fn changelist_details_title() -> String {
+    "Details".to_string()
+}
+
+pub fn changelist_details_number(number: u32) -> String {
+    format!("change {number} ")
+}
+
+pub fn changelist_details_author(user: &str, client: &str) -> String {
+    format!("by {user}@{client} ")
+}
+
+pub fn changelist_details_time(time: &str) -> String {
+    format!("on {time}")
+}
+
+pub fn changelist_details_files(count: usize) -> String {
+    if count == 1 {
+        "1 file".to_string()
+    } else {
+        format!("{count} files")
+    }
+}
+
+pub fn submitted_files_title() -> String {
+    "Files".to_string()
+}
+
+pub fn opened_files_title(changelist: Option<u32>) -> String {
+    files_title("Unshelved Files", changelist)
+}
+
+pub fn shelved_files_title(changelist: Option<u32>) -> String {
+    files_title("Shelved Files", changelist)
+}
+
+fn files_title(title: &str, changelist: Option<u32>) -> String {
+    match changelist {
+        Some(0) => format!("{title}: default"),
+        Some(number) => format!("{title}: {number}"),
+        None => title.to_string(),
+    }
+}
+
+pub fn diff_title(path: &str) -> String {
+    format!("Diff: {path}")
+}
+
+pub fn diff_title_loading(path: &str) -> String {
+    format!("Diff: {path} (loading)")
+}
+
+pub fn diff_binary() -> String {
+    "binary file".to_string()
+}
+
+pub fn diff_too_large() -> String {
+    "file too large to show line by line".to_string()
+}
+
+pub fn diff_file_type(file_type: Option<&FileType>) -> String {
+    file_type.map_or_else(
+        || "filetype: unknown".to_string(),
+        |t| format!("filetype: {t}"),
+    )
+}
+
+pub fn diff_sizes((old, new): (u64, u64), delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!(
+        "size: {} -> {} ({sign}{})",
+        file_size(old),
+        file_size(new),
+        file_size(delta.unsigned_abs())
+    )
+}
+
+pub fn diff_hex_dump_hint(key_config: &SharedKeyConfig) -> String {
+    format!(
+        "[{}] hex dump of the changed region",
+        key_config.get_hint(key_config.keys.diff_hex_dump)
+    )
+}
+
+pub fn diff_search_query(query: &str) -> String {
+    format!("/{query}")
+}
+
+/// Modes of the search, then `current/total` matches of a non-empty query.
+pub fn diff_search_status(
+    ignore_case: bool,
+    regex: bool,
+    matches: Option<(Option<usize>, usize)>,
+    invalid: bool,
+) -> String {
+    let mut status = String::from("  ");
+    if ignore_case {
+        status.push_str("[ignore case] ");
+    }
+    if regex {
+        status.push_str("[regex] ");
+    }
+
+    match (matches, invalid) {
+        (_, true) => status.push_str("invalid regex"),
+        (Some((Some(current), total)), false) => {
+            status.push_str(&format!("{}/{total}", current + 1));
+        }
+        (Some((None, _)), false) => status.push_str("no matches"),
+        (None, false) => {}
+    }
+
+    status
+}
+
+pub fn file_size(bytes: u64) -> String {
+    ByteSize::b(bytes).to_string_as(true)
+}
+
 pub fn cmd_splitter() -> String {
     " ".to_string()
 }
@@ -60,6 +213,7 @@ pub mod commands {
     use crate::keys::SharedKeyConfig;
 
     static CMD_GROUP_GENERAL: &str = "-- General --";
+    static CMD_GROUP_DIFF: &str = "-- Diff --";
 
     pub fn scroll(key_config: &SharedKeyConfig) -> CommandText {
         CommandText::new(
@@ -72,6 +226,86 @@ pub mod commands {
             CMD_GROUP_GENERAL,
         )
     }
+    pub fn scroll_horizontal(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Scroll [{}{}]",
+                key_config.get_hint(key_config.keys.move_left),
+                key_config.get_hint(key_config.keys.move_right)
+            ),
+            "scroll left or right in focused view",
+            CMD_GROUP_GENERAL,
+        )
+    }
+    pub fn diff_focus_right(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!("Diff [{}]", key_config.get_hint(key_config.keys.move_right)),
+            "inspect file diff",
+            CMD_GROUP_GENERAL,
+        )
+    }
+
+    pub fn diff_focus_left(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!("Back [{}]", key_config.get_hint(key_config.keys.move_left)),
+            "view and select changed files",
+            CMD_GROUP_GENERAL,
+        )
+    }
+
+    pub fn focus_next(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Focus [{}{}]",
+                key_config.get_hint(key_config.keys.focus_next),
+                key_config.get_hint(key_config.keys.focus_prev)
+            ),
+            "move focus to the next or previous pane",
+            CMD_GROUP_GENERAL,
+        )
+    }
+
+    pub fn diff_split_view(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Split view [{}]",
+                key_config.get_hint(key_config.keys.diff_split_view)
+            ),
+            "toggle between unified and side-by-side diff",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_wrap(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!("Wrap [{}]", key_config.get_hint(key_config.keys.diff_wrap)),
+            "toggle wrapping of long lines",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_formatter(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Formatter [{}]",
+                key_config.get_hint(key_config.keys.diff_formatter)
+            ),
+            "toggle between the configured diff formatter and the built-in view",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_show_whitespace(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Whitespace [{}]",
+                key_config.get_hint(key_config.keys.diff_show_whitespace)
+            ),
+            "toggle showing tabs, trailing spaces and carriage returns",
+            CMD_GROUP_DIFF,
+        )
+    }
+
     pub fn close_popup(key_config: &SharedKeyConfig) -> CommandText {
         CommandText::new(
             format!(
@@ -83,6 +317,145 @@ pub mod commands {
         )
     }
 
+    pub fn login_confirm(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!("Login [{}]", key_config.get_hint(key_config.keys.enter)),
+            "run p4 login with the entered password",
+            CMD_GROUP_GENERAL,
+        )
+    }
+
+    pub fn options_popup(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Options [{}]",
+                key_config.get_hint(key_config.keys.open_options)
+            ),
+            "open options popup",
+            CMD_GROUP_GENERAL,
+        )
+    }
+
+    pub fn options_change_value(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Change [{}{}]",
+                key_config.get_hint(key_config.keys.move_left),
+                key_config.get_hint(key_config.keys.move_right)
+            ),
+            "change selected option",
+            CMD_GROUP_GENERAL,
+        )
+    }
+
+    pub fn close_msg(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!("Close [{}]", key_config.get_hint(key_config.keys.enter)),
+            "close msg popup (e.g display error)",
+            CMD_GROUP_GENERAL,
+        )
+        .hide_help()
+    }
+
+    pub fn confirm_action(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!("Confirm [{}]", key_config.get_hint(key_config.keys.enter)),
+            "confirm action",
+            CMD_GROUP_GENERAL,
+        )
+    }
+
+    pub fn diff_hunk_next(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Hunk [{}{}]",
+                key_config.get_hint(key_config.keys.diff_hunk_next),
+                key_config.get_hint(key_config.keys.diff_hunk_prev)
+            ),
+            "jump to next or previous hunk",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_hunk_copy(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Copy hunk [{}]",
+                key_config.get_hint(key_config.keys.diff_hunk_copy)
+            ),
+            "copy selected hunk to clipboard",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_hunk_discard(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Discard hunk [{}]",
+                key_config.get_hint(key_config.keys.diff_hunk_discard)
+            ),
+            "drop the changes of the selected hunk from the local file",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_hex_dump(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Hex dump [{}]",
+                key_config.get_hint(key_config.keys.diff_hex_dump)
+            ),
+            "show or hide the bytes around the first change of a binary or large file",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_search(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Search [{}]",
+                key_config.get_hint(key_config.keys.diff_search)
+            ),
+            "search the diff, highlighting all matches",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_search_next(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Match [{}{}]",
+                key_config.get_hint(key_config.keys.diff_search_next),
+                key_config.get_hint(key_config.keys.diff_search_prev)
+            ),
+            "jump to next or previous search match",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_search_modes(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Ignore case [{}] Regex [{}]",
+                key_config.get_hint(key_config.keys.diff_search_ignore_case),
+                key_config.get_hint(key_config.keys.diff_search_regex)
+            ),
+            "toggle case-insensitive and regex search",
+            CMD_GROUP_DIFF,
+        )
+    }
+
+    pub fn diff_search_close(key_config: &SharedKeyConfig) -> CommandText {
+        CommandText::new(
+            format!(
+                "Close search [{}]",
+                key_config.get_hint(key_config.keys.exit_popup)
+            ),
+            "clear the search and its highlights",
+            CMD_GROUP_DIFF,
+        )
+    }
+
     pub fn help_open(key_config: &SharedKeyConfig) -> CommandText {
         CommandText::new(
             format!("Help [{}]", key_config.get_hint(key_config.keys.open_help)),
@@ -91,3 +464,35 @@ pub mod commands {
         )
     }
 }
+
+pub mod options {
+    use asyncp4::sync::DiffWhitespace;
+
+    pub const fn diff_header() -> &'static str {
+        "Diff"
+    }
+
+    pub const fn diff_whitespace() -> &'static str {
+        "Whitespace"
+    }
+
+    pub const fn diff_whitespace_value(whitespace: DiffWhitespace) -> &'static str {
+        match whitespace {
+            DiffWhitespace::Show => "show",
+            DiffWhitespace::IgnoreChanges => "ignore changes",
+            DiffWhitespace::IgnoreAll => "ignore all",
+        }
+    }
+
+    pub const fn diff_context() -> &'static str {
+        "Context lines"
+    }
+
+    pub const fn diff_interhunk_lines() -> &'static str {
+        "Interhunk lines"
+    }
+
+    pub const fn diff_max_size() -> &'static str {
+        "Max file size"
+    }
+}
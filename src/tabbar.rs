@@ -1,5 +1,7 @@
 use std::{borrow::Cow, path::PathBuf};
 
+use asyncp4::sync::ConnectionSettings;
+
 use crate::{keys::SharedKeyConfig, strings, ui::style::SharedTheme};
 
 use unicode_width::UnicodeWidthStr;
@@ -14,15 +16,22 @@ use ratatui::{
 
 pub struct TabBar {
     cwd: PathBuf,
+    connection: ConnectionSettings,
     selected_tab: usize,
     theme: SharedTheme,
     key_config: SharedKeyConfig,
 }
 
 impl TabBar {
-    pub const fn new(key_config: SharedKeyConfig, theme: SharedTheme, cwd: PathBuf) -> Self {
+    pub const fn new(
+        key_config: SharedKeyConfig,
+        theme: SharedTheme,
+        cwd: PathBuf,
+        connection: ConnectionSettings,
+    ) -> Self {
         Self {
             cwd,
+            connection,
             key_config,
             theme,
             selected_tab: 0,
@@ -83,7 +92,10 @@ impl TabBar {
 
         f.render_widget(
             Paragraph::new(Line::from(vec![Span::styled(
-                strings::ellipsis_trim_start("this/is/a/tmp/path", text_area.width as usize),
+                strings::ellipsis_trim_start(
+                    &self.connection.to_string(),
+                    text_area.width as usize,
+                ),
                 self.theme.title(true),
             )]))
             .alignment(Alignment::Right),
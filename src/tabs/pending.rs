@@ -1,98 +1,478 @@
+use std::sync::Arc;
+
 use crate::{
     components::{
-        visibility_blocking, CommandBlocking, CommandInfo, Component, DiffComponent,
-        DrawableComponent, EventState,
+        visibility_blocking, ChangelistComponent, CommandBlocking, CommandInfo, Component,
+        DiffComponent, DrawableComponent, EventState, FileListComponent,
     },
-    keys::SharedKeyConfig,
+    keys::{key_match, SharedKeyConfig},
+    options::SharedOptions,
+    queue::Queue,
+    strings,
     ui::style::SharedTheme,
+    AsyncAppNotification,
 };
 
 use anyhow::Result;
+use asyncp4::{
+    asyncjob::AsyncSingleJob,
+    sync::{Changelist, ChangelistStatus, ConnectionSettings, FileSpec, Hunk, P4Path, P4Runner},
+    AsyncChangedBytesJob, AsyncChangelistsJob, AsyncDiffJob, AsyncDiscardHunkJob, AsyncFilesJob,
+    AsyncP4Notification, ChangelistsParams, DiffCache, DiffParams, DiffSource,
+};
+use crossbeam_channel::Sender;
 use crossterm::event::Event;
 use ratatui::{
     backend::Backend,
-    layout::{Direction, Layout, Rect},
-    prelude::Constraint,
-    text::Span,
-    widgets::{Block, Borders},
+    layout::{Constraint, Direction, Layout, Rect},
     Frame,
 };
 
+/// Bytes loaded per side for the hex dump of a binary or too large file.
+const HEX_DUMP_MAX_BYTES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Changelists,
+    Files,
+    Shelved,
+    Diff,
+}
+
+impl Focus {
+    /// Order the panes are cycled through in.
+    const ALL: [Self; 4] = [Self::Changelists, Self::Files, Self::Shelved, Self::Diff];
+
+    fn cycle(self, next: bool) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|f| *f == self)
+            .unwrap_or_default();
+        let index = if next {
+            (index + 1) % Self::ALL.len()
+        } else {
+            (index + Self::ALL.len() - 1) % Self::ALL.len()
+        };
+
+        Self::ALL[index]
+    }
+}
+
 pub struct PendingTab {
     visible: bool,
+    focus: Focus,
+    /// file list whose selected file the diff shows, `Files` or `Shelved`
+    diff_source: Focus,
+    changelists: ChangelistComponent,
+    files: FileListComponent,
+    shelved: FileListComponent,
     diff: DiffComponent,
+    user: Option<String>,
+    client: Option<String>,
+    options: SharedOptions,
+    changelists_job: AsyncSingleJob<AsyncChangelistsJob>,
+    files_job: AsyncSingleJob<AsyncFilesJob>,
+    /// opened files last requested, `None` without a selected changelist
+    files_spec: Option<FileSpec>,
+    shelved_job: AsyncSingleJob<AsyncFilesJob>,
+    /// shelved files last requested, `None` if there are none
+    shelved_spec: Option<FileSpec>,
+    diff_job: AsyncSingleJob<AsyncDiffJob>,
+    diff_params: Option<DiffParams>,
+    /// shelved diffs, described once per changelist
+    diff_cache: DiffCache,
+    hex_dump_job: AsyncSingleJob<AsyncChangedBytesJob>,
+    discard_job: AsyncSingleJob<AsyncDiscardHunkJob>,
+    runner: Arc<dyn P4Runner>,
     key_config: SharedKeyConfig,
-    theme: SharedTheme,
 }
 
 impl DrawableComponent for PendingTab {
     fn draw<B: Backend>(&self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
-        let rects = std::rc::Rc::new([rect]);
-
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-            .split(rects[0]);
+            .split(rect);
 
+        // percentages leave a gap for heights not divisible by three
+        let third = chunks[0].height / 3;
         let left_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
                 [
-                    Constraint::Percentage(34),
-                    Constraint::Percentage(33),
-                    Constraint::Percentage(33),
+                    Constraint::Length(third),
+                    Constraint::Length(third),
+                    Constraint::Min(0),
                 ]
                 .as_ref(),
             )
             .split(chunks[0]);
 
-        // TODO: replace with components!
+        self.changelists.draw(f, left_chunks[0])?;
+        self.files.draw(f, left_chunks[1])?;
+        self.shelved.draw(f, left_chunks[2])?;
+        self.diff.draw(f, chunks[1])?;
 
-        let cl = Block::default()
-            .title(Span::raw("Changelist"))
-            .borders(Borders::ALL);
+        Ok(())
+    }
+}
 
-        f.render_widget(cl, left_chunks[0]);
+impl PendingTab {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        runner: Arc<dyn P4Runner>,
+        sender: &Sender<AsyncP4Notification>,
+        sender_app: &Sender<AsyncAppNotification>,
+        connection: &ConnectionSettings,
+        queue: Queue,
+        options: SharedOptions,
+        key_config: SharedKeyConfig,
+        theme: SharedTheme,
+    ) -> Self {
+        let mut files = FileListComponent::new(
+            strings::opened_files_title(None),
+            key_config.clone(),
+            theme.clone(),
+        );
+        files.focus(true);
 
-        let unshelved = Block::default()
-            .title(Span::raw("Unshelved Files"))
-            .borders(Borders::ALL);
+        Self {
+            visible: true,
+            focus: Focus::Files,
+            diff_source: Focus::Files,
+            changelists: ChangelistComponent::new(
+                ChangelistStatus::Pending,
+                key_config.clone(),
+                theme.clone(),
+            ),
+            files,
+            shelved: FileListComponent::new(
+                strings::shelved_files_title(None),
+                key_config.clone(),
+                theme.clone(),
+            ),
+            diff: DiffComponent::new(
+                sender_app,
+                queue,
+                options.clone(),
+                key_config.clone(),
+                theme,
+                false,
+            ),
+            user: connection.user.as_ref().map(|s| s.value.clone()),
+            client: connection.client.as_ref().map(|s| s.value.clone()),
+            options,
+            changelists_job: AsyncSingleJob::new(sender.clone()),
+            files_job: AsyncSingleJob::new(sender.clone()),
+            files_spec: None,
+            shelved_job: AsyncSingleJob::new(sender.clone()),
+            shelved_spec: None,
+            diff_job: AsyncSingleJob::new(sender.clone()),
+            diff_params: None,
+            diff_cache: DiffCache::new(),
+            hex_dump_job: AsyncSingleJob::new(sender.clone()),
+            discard_job: AsyncSingleJob::new(sender.clone()),
+            runner,
+            key_config,
+        }
+    }
 
-        f.render_widget(unshelved, left_chunks[1]);
+    /// Reloads the changelists, followed by the files of the selected one.
+    pub fn update(&mut self) {
+        // changelists may have been reshelved since
+        self.diff_cache.clear();
+        self.changelists_job.spawn(AsyncChangelistsJob::new(
+            self.runner.clone(),
+            ChangelistsParams::Pending {
+                user: self.user.clone(),
+                client: self.client.clone(),
+            },
+        ));
+    }
 
-        let shelved = Block::default()
-            .title(Span::raw("Shelved Files"))
-            .borders(Borders::ALL);
+    /// Reloads the opened and shelved files of the selected changelist.
+    fn update_files(&mut self) {
+        let selected = self.changelists.get_selected();
+        let number = selected.map(|c| c.number);
+        let files_spec = number.map(FileSpec::Opened);
+        let shelved_spec = selected
+            .filter(|c| c.has_shelved)
+            .map(|c| FileSpec::Shelved(c.number));
 
-        f.render_widget(shelved, left_chunks[2]);
+        self.files.set_title(strings::opened_files_title(number));
+        self.shelved.set_title(strings::shelved_files_title(number));
 
-        self.diff.draw(f, chunks[1])?;
+        // files of another changelist must not linger while loading
+        if files_spec != self.files_spec || shelved_spec != self.shelved_spec {
+            self.files_job.cancel();
+            self.shelved_job.cancel();
+            self.files.set_files(Vec::new());
+            self.shelved.set_files(Vec::new());
+            self.update_diff();
+        }
+
+        if let Some(spec) = &files_spec {
+            self.files_job
+                .spawn(AsyncFilesJob::new(self.runner.clone(), spec.clone()));
+        }
+        if let Some(spec) = &shelved_spec {
+            self.shelved_job
+                .spawn(AsyncFilesJob::new(self.runner.clone(), spec.clone()));
+        }
+
+        self.files_spec = files_spec;
+        self.shelved_spec = shelved_spec;
+    }
+
+    /// Picks up the output of the diff formatter.
+    pub fn update_app_async(&mut self, ev: AsyncAppNotification) {
+        self.diff.update_async(ev);
+    }
+
+    /// Runs the diff formatter for the width the diff was drawn with.
+    pub fn update_formatter(&mut self) {
+        self.diff.update_formatter();
+    }
+
+    pub fn update_async(&mut self, ev: AsyncP4Notification) -> Result<()> {
+        match ev {
+            AsyncP4Notification::Changelists => self.update_changelists()?,
+            AsyncP4Notification::Files => {
+                self.update_file_list(Focus::Files)?;
+                self.update_file_list(Focus::Shelved)?;
+            }
+            AsyncP4Notification::Diff => self.update_diff_result()?,
+            AsyncP4Notification::ChangedBytes => self.update_hex_dump()?,
+            AsyncP4Notification::DiscardHunk => self.update_discarded()?,
+            AsyncP4Notification::Describe | AsyncP4Notification::Login => {}
+        }
 
         Ok(())
     }
-}
 
-impl PendingTab {
-    pub fn new(key_config: SharedKeyConfig, theme: SharedTheme) -> Self {
-        Self {
-            visible: true,
-            diff: DiffComponent::new(key_config.clone(), theme.clone()),
-            key_config,
-            theme,
+    fn update_changelists(&mut self) -> Result<()> {
+        let Some(result) = self
+            .changelists_job
+            .take_last()
+            .and_then(|job| job.result())
+        else {
+            return Ok(());
+        };
+
+        // the default changelist is not listed by `p4 changes`
+        let mut changelists = vec![Changelist {
+            status: ChangelistStatus::Pending,
+            user: self.user.clone().unwrap_or_default(),
+            client: self.client.clone().unwrap_or_default(),
+            ..Changelist::new()
+        }];
+        changelists.extend(result?.1);
+        self.changelists.set_changelists(changelists);
+
+        self.update_files();
+
+        Ok(())
+    }
+
+    /// Picks up the files loaded for the `Files` or `Shelved` list.
+    fn update_file_list(&mut self, list: Focus) -> Result<()> {
+        let (job, spec, component) = if list == Focus::Shelved {
+            (&self.shelved_job, &self.shelved_spec, &mut self.shelved)
+        } else {
+            (&self.files_job, &self.files_spec, &mut self.files)
+        };
+
+        let Some(result) = job.take_last().and_then(|job| job.result()) else {
+            return Ok(());
+        };
+        let (result_spec, files) = result?;
+
+        // drop results for a changelist that is no longer selected
+        if spec.as_ref() == Some(&result_spec) {
+            component.set_files(files);
+
+            if self.diff_source == list {
+                // the file may have changed since its diff was loaded
+                self.diff_params = None;
+                self.update_diff();
+            }
         }
+
+        Ok(())
+    }
+
+    fn update_diff_result(&mut self) -> Result<()> {
+        if let Some(result) = self.diff_job.take_last().and_then(|job| job.result()) {
+            let (params, diff) = result?;
+
+            // drop results for a file that is no longer selected
+            if self.diff_params.as_ref() == Some(&params) {
+                let file_type = self
+                    .diff_list()
+                    .selected_file()
+                    .and_then(|f| f.current_filetype().cloned());
+                self.diff.set_immutable(params.source != DiffSource::Opened);
+                self.diff.update(params.path, file_type, diff);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn diff_list(&self) -> &FileListComponent {
+        if self.diff_source == Focus::Shelved {
+            &self.shelved
+        } else {
+            &self.files
+        }
+    }
+
+    /// Requests the diff of the selected file unless it is already shown
+    /// with the current options.
+    pub fn update_diff(&mut self) {
+        let Some(file) = self.diff_list().selected_file() else {
+            self.diff_job.cancel();
+            self.diff_params = None;
+            self.diff.clear(false);
+            return;
+        };
+
+        let params = DiffParams {
+            path: file.depot_path.clone(),
+            source: match self.changelists.get_selected() {
+                Some(c) if self.diff_source == Focus::Shelved => DiffSource::Shelved(c.number),
+                _ => DiffSource::Opened,
+            },
+            options: self.options.borrow().diff_options(),
+        };
+
+        if self.diff_params.as_ref() != Some(&params) {
+            // the diff of the previous file is of no use anymore
+            self.diff_job.cancel();
+            self.diff.set_pending(&params.path);
+            self.diff_job.spawn(
+                AsyncDiffJob::new(self.runner.clone(), params.clone())
+                    .with_cache(self.diff_cache.clone()),
+            );
+            self.diff_params = Some(params);
+        }
+    }
+
+    /// Loads the first changed region of `path` into the diff view.
+    pub fn load_hex_dump(&mut self, path: P4Path) {
+        self.hex_dump_job.cancel();
+        self.hex_dump_job.spawn(AsyncChangedBytesJob::new(
+            self.runner.clone(),
+            path,
+            HEX_DUMP_MAX_BYTES,
+        ));
+    }
+
+    fn update_hex_dump(&mut self) -> Result<()> {
+        if let Some(result) = self.hex_dump_job.take_last().and_then(|job| job.result()) {
+            let (path, bytes) = result?;
+            // ignored unless the file is still shown
+            self.diff.set_hex_dump(&path, bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Reverts `hunk` in the local copy of `path`.
+    pub fn discard_hunk(&mut self, path: P4Path, hunk: Hunk) {
+        self.discard_job
+            .spawn(AsyncDiscardHunkJob::new(self.runner.clone(), path, hunk));
     }
 
-    pub fn update(&mut self) -> Result<()> {
+    fn update_discarded(&mut self) -> Result<()> {
+        if let Some(result) = self.discard_job.take_last().and_then(|job| job.result()) {
+            // reloaded even if it failed, the file may have changed anyway
+            self.update();
+            result?;
+        }
+
         Ok(())
     }
+
+    fn set_focus(&mut self, focus: Focus) {
+        self.focus = focus;
+        self.changelists.focus(focus == Focus::Changelists);
+        self.files.focus(focus == Focus::Files);
+        self.shelved.focus(focus == Focus::Shelved);
+        self.diff.focus(focus == Focus::Diff);
+
+        if matches!(focus, Focus::Files | Focus::Shelved) && self.diff_source != focus {
+            self.diff_source = focus;
+            self.update_diff();
+        }
+    }
+
+    fn switch_focus(&mut self, ev: &Event) -> bool {
+        let Event::Key(e) = ev else {
+            return false;
+        };
+
+        let focus = if key_match(e, self.key_config.keys.focus_next) {
+            self.focus.cycle(true)
+        } else if key_match(e, self.key_config.keys.focus_prev) {
+            self.focus.cycle(false)
+        } else if self.focus != Focus::Diff && key_match(e, self.key_config.keys.move_right) {
+            Focus::Diff
+        } else if self.focus == Focus::Diff && key_match(e, self.key_config.keys.move_left) {
+            self.diff_source
+        } else {
+            return false;
+        };
+
+        self.set_focus(focus);
+        true
+    }
 }
 
 impl Component for PendingTab {
     fn commands(&self, out: &mut Vec<CommandInfo>, force_all: bool) -> CommandBlocking {
+        if self.visible || force_all {
+            out.push(CommandInfo::new(
+                strings::commands::focus_next(&self.key_config),
+                true,
+                true,
+            ));
+            out.push(CommandInfo::new(
+                strings::commands::diff_focus_right(&self.key_config),
+                self.diff_list().selected_file().is_some(),
+                self.focus != Focus::Diff || force_all,
+            ));
+            out.push(CommandInfo::new(
+                strings::commands::diff_focus_left(&self.key_config),
+                true,
+                self.diff.focused() || force_all,
+            ));
+
+            self.diff.commands(out, force_all);
+        }
+
         visibility_blocking(self)
     }
 
     fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.visible {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if self.changelists.event(ev)?.is_consumed() {
+            self.update_files();
+            return Ok(EventState::Consumed);
+        }
+
+        if self.files.event(ev)?.is_consumed() || self.shelved.event(ev)?.is_consumed() {
+            self.update_diff();
+            return Ok(EventState::Consumed);
+        }
+
+        if self.diff.event(ev)?.is_consumed() || self.switch_focus(ev) {
+            return Ok(EventState::Consumed);
+        }
+
         Ok(EventState::NotConsumed)
     }
 
@@ -100,6 +480,10 @@ impl Component for PendingTab {
         self.visible
     }
 
+    fn is_input_active(&self) -> bool {
+        self.visible && self.diff.is_input_active()
+    }
+
     fn hide(&mut self) {
         self.visible = false;
     }
@@ -107,7 +491,7 @@ impl Component for PendingTab {
     fn show(&mut self) -> Result<()> {
         self.visible = true;
 
-        self.update()?;
+        self.update();
 
         Ok(())
     }
@@ -1,93 +1,425 @@
+use std::sync::Arc;
+
 use crate::{
     components::{
-        visibility_blocking, CommandBlocking, CommandInfo, Component, DrawableComponent, EventState,
+        visibility_blocking, ChangelistComponent, ChangelistDetailsComponent, CommandBlocking,
+        CommandInfo, Component, DiffComponent, DrawableComponent, EventState, FileListComponent,
     },
-    keys::SharedKeyConfig,
+    keys::{key_match, SharedKeyConfig},
+    options::SharedOptions,
+    queue::Queue,
+    strings,
     ui::style::SharedTheme,
+    AsyncAppNotification,
 };
 
 use anyhow::Result;
+use asyncp4::{
+    asyncjob::AsyncSingleJob,
+    sync::{self, ChangelistStatus, P4Runner},
+    AsyncChangelistsJob, AsyncDescribeJob, AsyncDiffJob, AsyncP4Notification, ChangelistsParams,
+    DiffCache, DiffParams, DiffSource,
+};
+use crossbeam_channel::Sender;
 use crossterm::event::Event;
 use ratatui::{
     backend::Backend,
-    layout::{Direction, Layout, Rect},
-    prelude::Constraint,
-    text::Span,
-    widgets::{Block, Borders},
+    layout::{Constraint, Direction, Layout, Rect},
     Frame,
 };
 
+/// Changelists loaded per `p4 changes` call.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Changelists,
+    Details,
+    Files,
+    Diff,
+}
+
+impl Focus {
+    /// Order the panes are cycled through in.
+    const ALL: [Self; 4] = [Self::Changelists, Self::Details, Self::Files, Self::Diff];
+
+    fn cycle(self, next: bool) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|f| *f == self)
+            .unwrap_or_default();
+        let index = if next {
+            (index + 1) % Self::ALL.len()
+        } else {
+            (index + Self::ALL.len() - 1) % Self::ALL.len()
+        };
+
+        Self::ALL[index]
+    }
+}
+
 pub struct SubmittedTab {
     visible: bool,
+    focus: Focus,
+    changelists: ChangelistComponent,
+    details: ChangelistDetailsComponent,
+    files: FileListComponent,
+    diff: DiffComponent,
+    /// older changelists than the loaded ones may exist
+    has_more: bool,
+    /// page of changelists being loaded
+    page_params: Option<ChangelistsParams>,
+    changelists_job: AsyncSingleJob<AsyncChangelistsJob>,
+    /// changelist shown in the details and file list
+    described: Option<u32>,
+    describe_job: AsyncSingleJob<AsyncDescribeJob>,
+    options: SharedOptions,
+    diff_job: AsyncSingleJob<AsyncDiffJob>,
+    diff_params: Option<DiffParams>,
+    /// diffs of the described changelist, loaded once for all its files
+    diff_cache: DiffCache,
+    runner: Arc<dyn P4Runner>,
     key_config: SharedKeyConfig,
-    theme: SharedTheme,
 }
 
 impl DrawableComponent for SubmittedTab {
     fn draw<B: Backend>(&self, f: &mut Frame<B>, rect: Rect) -> Result<()> {
-        // let rects = std::rc::Rc::new([rect]);
-        //
-        // let chunks = Layout::default()
-        //     .direction(Direction::Horizontal)
-        //     .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-        //     .split(rects[0]);
-        //
-        // let left_chunks = Layout::default()
-        //     .direction(Direction::Vertical)
-        //     .constraints(
-        //         [
-        //             Constraint::Percentage(34),
-        //             Constraint::Percentage(33),
-        //             Constraint::Percentage(33),
-        //         ]
-        //         .as_ref(),
-        //     )
-        //     .split(chunks[0]);
-        //
-        // // TODO: replace with components!
-        //
-        // let cl = Block::default()
-        //     .title(Span::raw("Changelist"))
-        //     .borders(Borders::ALL);
-        //
-        // f.render_widget(cl, left_chunks[0]);
-        //
-        // let unshelved = Block::default()
-        //     .title(Span::raw("Unshelved Files"))
-        //     .borders(Borders::ALL);
-        //
-        // f.render_widget(unshelved, left_chunks[1]);
-        //
-        // let shelved = Block::default()
-        //     .title(Span::raw("Shelved Files"))
-        //     .borders(Borders::ALL);
-        //
-        // f.render_widget(shelved, left_chunks[2]);
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(rect);
+
+        let left_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(60), Constraint::Min(0)].as_ref())
+            .split(chunks[0]);
+
+        let right_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(30), Constraint::Min(0)].as_ref())
+            .split(chunks[1]);
+
+        self.changelists.draw(f, left_chunks[0])?;
+        self.details.draw(f, left_chunks[1])?;
+        self.files.draw(f, right_chunks[0])?;
+        self.diff.draw(f, right_chunks[1])?;
 
         Ok(())
     }
 }
 
 impl SubmittedTab {
-    pub fn new(key_config: SharedKeyConfig, theme: SharedTheme) -> Self {
+    pub fn new(
+        runner: Arc<dyn P4Runner>,
+        sender: &Sender<AsyncP4Notification>,
+        sender_app: &Sender<AsyncAppNotification>,
+        queue: Queue,
+        options: SharedOptions,
+        key_config: SharedKeyConfig,
+        theme: SharedTheme,
+    ) -> Self {
+        let mut changelists = ChangelistComponent::new(
+            ChangelistStatus::Submitted,
+            key_config.clone(),
+            theme.clone(),
+        );
+        changelists.focus(true);
+
         Self {
-            visible: true,
+            visible: false,
+            focus: Focus::Changelists,
+            changelists,
+            details: ChangelistDetailsComponent::new(key_config.clone(), theme.clone()),
+            files: FileListComponent::new(
+                strings::submitted_files_title(),
+                key_config.clone(),
+                theme.clone(),
+            ),
+            diff: DiffComponent::new(
+                sender_app,
+                queue,
+                options.clone(),
+                key_config.clone(),
+                theme,
+                true,
+            ),
+            has_more: true,
+            page_params: None,
+            changelists_job: AsyncSingleJob::new(sender.clone()),
+            described: None,
+            describe_job: AsyncSingleJob::new(sender.clone()),
+            options,
+            diff_job: AsyncSingleJob::new(sender.clone()),
+            diff_params: None,
+            diff_cache: DiffCache::new(),
+            runner,
             key_config,
-            theme,
         }
     }
 
-    pub fn update(&mut self) -> Result<()> {
+    /// Loads the newest changelists the first time the tab is shown.
+    pub fn update(&mut self) {
+        if self.changelists.get_changelists().is_empty() && self.page_params.is_none() {
+            self.request_page(None);
+        }
+
+        self.update_details();
+    }
+
+    /// Picks up the output of the diff formatter.
+    pub fn update_app_async(&mut self, ev: AsyncAppNotification) {
+        self.diff.update_async(ev);
+    }
+
+    /// Runs the diff formatter for the width the diff was drawn with.
+    pub fn update_formatter(&mut self) {
+        self.diff.update_formatter();
+    }
+
+    pub fn update_async(&mut self, ev: AsyncP4Notification) -> Result<()> {
+        match ev {
+            AsyncP4Notification::Changelists => self.update_page()?,
+            AsyncP4Notification::Describe => self.update_described()?,
+            AsyncP4Notification::Diff => self.update_diff_result()?,
+            AsyncP4Notification::Files
+            | AsyncP4Notification::ChangedBytes
+            | AsyncP4Notification::DiscardHunk
+            | AsyncP4Notification::Login => {}
+        }
+
+        Ok(())
+    }
+
+    fn request_page(&mut self, before: Option<u32>) {
+        let params = ChangelistsParams::Submitted {
+            max: PAGE_SIZE,
+            before,
+        };
+        self.changelists_job.spawn(AsyncChangelistsJob::new(
+            self.runner.clone(),
+            params.clone(),
+        ));
+        self.page_params = Some(params);
+    }
+
+    fn update_page(&mut self) -> Result<()> {
+        let Some(result) = self
+            .changelists_job
+            .take_last()
+            .and_then(|job| job.result())
+        else {
+            return Ok(());
+        };
+
+        let (params, page) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                // allow the page to be requested again
+                self.page_params = None;
+                return Err(e.into());
+            }
+        };
+        if self.page_params.as_ref() != Some(&params) {
+            return Ok(());
+        }
+
+        self.page_params = None;
+        self.has_more = page.len() == PAGE_SIZE;
+        if matches!(params, ChangelistsParams::Submitted { before: None, .. }) {
+            self.changelists.set_changelists(page);
+        } else {
+            self.changelists.extend(page);
+        }
+
+        self.load_more();
+        self.update_details();
+
+        Ok(())
+    }
+
+    /// Loads the next page once the selection gets close to the end of
+    /// the loaded changelists.
+    fn load_more(&mut self) {
+        if !self.has_more || self.page_params.is_some() || !self.changelists.is_near_end() {
+            return;
+        }
+
+        let lowest = self.changelists.get_changelists().last().map(|c| c.number);
+        self.request_page(lowest);
+    }
+
+    /// Describes the selected changelist unless it is already shown.
+    fn update_details(&mut self) {
+        let selected = self.changelists.get_selected();
+        let number = selected.map(|c| c.number);
+        if number == self.described {
+            return;
+        }
+
+        // `p4 changes -l` already has the full description, only the
+        // files have to wait for `p4 describe`
+        self.details.set_changelist(selected.cloned());
+        self.files.set_files(Vec::new());
+        self.described = number;
+        self.update_diff();
+
+        // skipping through changelists must not wait for each description
+        self.describe_job.cancel();
+        if let Some(number) = number {
+            self.describe_job
+                .spawn(AsyncDescribeJob::new(self.runner.clone(), number));
+        }
+    }
+
+    fn update_described(&mut self) -> Result<()> {
+        let Some(result) = self.describe_job.take_last().and_then(|job| job.result()) else {
+            return Ok(());
+        };
+
+        let (number, (changelist, files)) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                // describe again on the next selection change or update
+                self.described = None;
+                return Err(e.into());
+            }
+        };
+
+        // drop results for a changelist that is no longer selected
+        if self.described == Some(number) {
+            self.details.set_changelist(Some(changelist));
+            self.files.set_files(files);
+            self.update_diff();
+        }
+
         Ok(())
     }
+
+    fn update_diff_result(&mut self) -> Result<()> {
+        if let Some(result) = self.diff_job.take_last().and_then(|job| job.result()) {
+            let (params, diff) = result?;
+
+            // drop results for a file that is no longer selected
+            if self.diff_params.as_ref() == Some(&params) {
+                let file_type = self
+                    .files
+                    .selected_file()
+                    .and_then(sync::File::current_filetype)
+                    .cloned();
+                self.diff.update(params.path, file_type, diff);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Requests the diff of the selected file unless it is already shown
+    /// with the current options.
+    pub fn update_diff(&mut self) {
+        let (Some(file), Some(changelist)) = (self.files.selected_file(), self.described) else {
+            self.diff_job.cancel();
+            self.diff_params = None;
+            self.diff.clear(false);
+            return;
+        };
+
+        let params = DiffParams {
+            path: file.depot_path.clone(),
+            source: DiffSource::Submitted(changelist),
+            options: self.options.borrow().diff_options(),
+        };
+
+        if self.diff_params.as_ref() != Some(&params) {
+            self.diff_job.cancel();
+            self.diff.set_pending(&params.path);
+            self.diff_job.spawn(
+                AsyncDiffJob::new(self.runner.clone(), params.clone())
+                    .with_cache(self.diff_cache.clone()),
+            );
+            self.diff_params = Some(params);
+        }
+    }
+
+    fn set_focus(&mut self, focus: Focus) {
+        self.focus = focus;
+        self.changelists.focus(focus == Focus::Changelists);
+        self.details.focus(focus == Focus::Details);
+        self.files.focus(focus == Focus::Files);
+        self.diff.focus(focus == Focus::Diff);
+    }
+
+    fn switch_focus(&mut self, ev: &Event) -> bool {
+        let Event::Key(e) = ev else {
+            return false;
+        };
+
+        let focus = if key_match(e, self.key_config.keys.focus_next) {
+            self.focus.cycle(true)
+        } else if key_match(e, self.key_config.keys.focus_prev) {
+            self.focus.cycle(false)
+        } else if self.focus != Focus::Diff && key_match(e, self.key_config.keys.move_right) {
+            Focus::Diff
+        } else if self.focus == Focus::Diff && key_match(e, self.key_config.keys.move_left) {
+            Focus::Files
+        } else {
+            return false;
+        };
+
+        self.set_focus(focus);
+        true
+    }
 }
 
 impl Component for SubmittedTab {
     fn commands(&self, out: &mut Vec<CommandInfo>, force_all: bool) -> CommandBlocking {
+        if self.visible || force_all {
+            out.push(CommandInfo::new(
+                strings::commands::focus_next(&self.key_config),
+                true,
+                true,
+            ));
+            out.push(CommandInfo::new(
+                strings::commands::diff_focus_right(&self.key_config),
+                self.files.selected_file().is_some(),
+                self.focus != Focus::Diff || force_all,
+            ));
+            out.push(CommandInfo::new(
+                strings::commands::diff_focus_left(&self.key_config),
+                true,
+                self.diff.focused() || force_all,
+            ));
+
+            self.details.commands(out, force_all);
+            self.diff.commands(out, force_all);
+        }
+
         visibility_blocking(self)
     }
 
     fn event(&mut self, ev: &Event) -> Result<EventState> {
+        if !self.visible {
+            return Ok(EventState::NotConsumed);
+        }
+
+        if self.changelists.event(ev)?.is_consumed() {
+            self.load_more();
+            self.update_details();
+            return Ok(EventState::Consumed);
+        }
+
+        if self.details.event(ev)?.is_consumed() {
+            return Ok(EventState::Consumed);
+        }
+
+        if self.files.event(ev)?.is_consumed() {
+            self.update_diff();
+            return Ok(EventState::Consumed);
+        }
+
+        if self.diff.event(ev)?.is_consumed() || self.switch_focus(ev) {
+            return Ok(EventState::Consumed);
+        }
+
         Ok(EventState::NotConsumed)
     }
 
@@ -95,6 +427,10 @@ impl Component for SubmittedTab {
         self.visible
     }
 
+    fn is_input_active(&self) -> bool {
+        self.visible && self.diff.is_input_active()
+    }
+
     fn hide(&mut self) {
         self.visible = false;
     }
@@ -102,7 +438,7 @@ impl Component for SubmittedTab {
     fn show(&mut self) -> Result<()> {
         self.visible = true;
 
-        self.update()?;
+        self.update();
 
         Ok(())
     }
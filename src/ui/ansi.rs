@@ -0,0 +1,185 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{07}';
+
+/// Turns text colored with ANSI escape sequences into styled lines. Only
+/// SGR sequences are applied, any other escape sequence is dropped.
+pub fn to_lines(text: &[u8]) -> Vec<Line<'static>> {
+    let text = String::from_utf8_lossy(text);
+    let mut style = Style::default();
+
+    text.lines()
+        .map(|line| {
+            let mut spans = Vec::new();
+            let mut current = String::new();
+            let mut chars = line.chars().peekable();
+
+            while let Some(c) = chars.next() {
+                match c {
+                    ESC => match chars.next() {
+                        Some('[') => {
+                            let mut params = String::new();
+                            // parameters end with a final byte in `@..=~`
+                            let final_byte = chars.find(|c| {
+                                let done = ('@'..='~').contains(c);
+                                if !done {
+                                    params.push(*c);
+                                }
+                                done
+                            });
+
+                            if final_byte == Some('m') {
+                                let next = apply_sgr(style, &params);
+                                if next != style && !current.is_empty() {
+                                    spans.push(Span::styled(std::mem::take(&mut current), style));
+                                }
+                                style = next;
+                            }
+                        }
+                        // e.g. hyperlinks, ended by BEL or `ESC \`
+                        Some(']') => {
+                            while let Some(c) = chars.next() {
+                                if c == BEL || (c == ESC && chars.next_if_eq(&'\\').is_some()) {
+                                    break;
+                                }
+                            }
+                        }
+                        _ => {}
+                    },
+                    '\t' => current.push_str("    "),
+                    c if c.is_control() => {}
+                    c => current.push(c),
+                }
+            }
+
+            if !current.is_empty() {
+                spans.push(Span::styled(current, style));
+            }
+
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let mut params = params
+        .split([';', ':'])
+        .map(|p| p.parse::<u8>().unwrap_or_default());
+
+    while let Some(param) = params.next() {
+        match param {
+            0 => style = Style::default(),
+            1 => style = style.add_modifier(Modifier::BOLD),
+            2 => style = style.add_modifier(Modifier::DIM),
+            3 => style = style.add_modifier(Modifier::ITALIC),
+            4 => style = style.add_modifier(Modifier::UNDERLINED),
+            5 => style = style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style = style.add_modifier(Modifier::REVERSED),
+            9 => style = style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style = style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style = style.remove_modifier(Modifier::ITALIC),
+            24 => style = style.remove_modifier(Modifier::UNDERLINED),
+            25 => style = style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style = style.remove_modifier(Modifier::REVERSED),
+            29 => style = style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg = Some(basic_color(param - 30)),
+            38 => style.fg = extended_color(&mut params),
+            39 => style.fg = None,
+            40..=47 => style.bg = Some(basic_color(param - 40)),
+            48 => style.bg = extended_color(&mut params),
+            49 => style.bg = None,
+            90..=97 => style.fg = Some(bright_color(param - 90)),
+            100..=107 => style.bg = Some(bright_color(param - 100)),
+            _ => {}
+        }
+    }
+
+    style
+}
+
+/// `5;n` for one of 256 colors or `2;r;g;b`.
+fn extended_color(params: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match params.next()? {
+        5 => params.next().map(Color::Indexed),
+        2 => Some(Color::Rgb(params.next()?, params.next()?, params.next()?)),
+        _ => None,
+    }
+}
+
+const fn basic_color(index: u8) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        _ => Color::Gray,
+    }
+}
+
+const fn bright_color(index: u8) -> Color {
+    match index {
+        0 => Color::DarkGray,
+        1 => Color::LightRed,
+        2 => Color::LightGreen,
+        3 => Color::LightYellow,
+        4 => Color::LightBlue,
+        5 => Color::LightMagenta,
+        6 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_sgr() {
+        let lines = to_lines(
+            b"\x1b[1;32m+added\x1b[0m plain\n\x1b[38;5;208mx\x1b[48;2;1;2;3my\x1b[39;49mz",
+        );
+
+        assert_eq!(
+            lines,
+            vec![
+                Line::from(vec![
+                    Span::styled(
+                        "+added",
+                        Style::default()
+                            .fg(Color::Green)
+                            .add_modifier(Modifier::BOLD)
+                    ),
+                    Span::raw(" plain"),
+                ]),
+                Line::from(vec![
+                    Span::styled("x", Style::default().fg(Color::Indexed(208))),
+                    Span::styled(
+                        "y",
+                        Style::default()
+                            .fg(Color::Indexed(208))
+                            .bg(Color::Rgb(1, 2, 3))
+                    ),
+                    Span::raw("z"),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_sequences_dropped() {
+        let lines = to_lines(b"\x1b]8;;file:///a.c\x1b\\a.c\x1b]8;;\x1b\\\x1b[K\tb\r\n\x1b[31m\n");
+
+        assert_eq!(
+            lines,
+            vec![Line::from(vec![Span::raw("a.c    b")]), Line::default()]
+        );
+    }
+}
@@ -1,5 +1,7 @@
+pub mod ansi;
 mod scrollbar;
 pub mod style;
+pub mod syntax_text;
 
 pub use scrollbar::{draw_scrollbar, Orientation};
 
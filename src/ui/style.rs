@@ -1,6 +1,7 @@
 use std::{fs::File, io::Write, path::PathBuf, rc::Rc};
 
 use anyhow::Result;
+use asyncp4::sync::{DiffLineType, FileAction};
 use ratatui::style::{Color, Modifier, Style};
 use ron::ser::{to_string_pretty, PrettyConfig};
 use serde::{Deserialize, Serialize};
@@ -8,7 +9,7 @@ use struct_patch::Patch;
 
 pub type SharedTheme = Rc<Theme>;
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, Patch)]
+#[derive(Serialize, Deserialize, Debug, Clone, Patch)]
 #[patch_derive(Serialize, Deserialize)]
 pub struct Theme {
     selected_tab: Color,
@@ -20,13 +21,23 @@ pub struct Theme {
     disabled_fg: Color,
     diff_line_add: Color,
     diff_line_delete: Color,
+    diff_word_add: Color,
+    diff_word_delete: Color,
+    diff_syntax_add_bg: Color,
+    diff_syntax_delete_bg: Color,
+    diff_whitespace: Color,
+    diff_search_match: Color,
+    diff_search_current: Color,
     diff_file_added: Color,
     diff_file_removed: Color,
     diff_file_moved: Color,
     diff_file_modified: Color,
-    // commit_hash: Color,
-    // commit_time: Color,
-    // commit_author: Color,
+    changelist_number: Color,
+    changelist_time: Color,
+    changelist_user: Color,
+    changelist_client: Color,
+    /// name of a syntect default theme used for syntax highlighting
+    syntax: String,
     // danger_fg: Color,
     // push_gauge_bg: Color,
     // push_gauge_fg: Color,
@@ -124,15 +135,15 @@ impl Theme {
     //
     //     self.apply_select(style, selected)
     // }
-    //
-    // fn apply_select(&self, style: Style, selected: bool) -> Style {
-    //     if selected {
-    //         style.bg(self.selection_bg).fg(self.selection_fg)
-    //     } else {
-    //         style
-    //     }
-    // }
-    //
+
+    fn apply_select(&self, style: Style, selected: bool) -> Style {
+        if selected {
+            style.bg(self.selection_bg).fg(self.selection_fg)
+        } else {
+            style
+        }
+    }
+
     // pub fn option(&self, on: bool) -> Style {
     //     if on {
     //         Style::default().fg(self.diff_line_add)
@@ -148,23 +159,87 @@ impl Theme {
     //         Style::default().fg(self.disabled_fg)
     //     }
     // }
-    //
-    // pub fn diff_line(&self, typ: DiffLineType, selected: bool) -> Style {
-    //     let style = match typ {
-    //         DiffLineType::Add => Style::default().fg(self.diff_line_add),
-    //         DiffLineType::Delete => Style::default().fg(self.diff_line_delete),
-    //         DiffLineType::Header => Style::default()
-    //             .fg(self.disabled_fg)
-    //             .add_modifier(Modifier::BOLD),
-    //         DiffLineType::None => Style::default().fg(if selected {
-    //             self.command_fg
-    //         } else {
-    //             Color::Reset
-    //         }),
-    //     };
-    //
-    //     self.apply_select(style, selected)
-    // }
+
+    pub fn diff_line(&self, typ: DiffLineType, selected: bool) -> Style {
+        let style = match typ {
+            DiffLineType::Add => Style::default().fg(self.diff_line_add),
+            DiffLineType::Delete => Style::default().fg(self.diff_line_delete),
+            DiffLineType::Header => Style::default()
+                .fg(self.disabled_fg)
+                .add_modifier(Modifier::BOLD),
+            DiffLineType::None => Style::default().fg(if selected {
+                self.command_fg
+            } else {
+                Color::Reset
+            }),
+        };
+
+        self.apply_select(style, selected)
+    }
+
+    /// Changed words within a paired add/delete line.
+    pub fn diff_word(&self, typ: DiffLineType, changed: bool) -> Style {
+        let color = match typ {
+            DiffLineType::Add => self.diff_word_add,
+            DiffLineType::Delete => self.diff_word_delete,
+            _ => return self.diff_line(typ, false),
+        };
+
+        if changed {
+            Style::default()
+                .fg(color)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+        } else {
+            self.diff_line(typ, false)
+        }
+    }
+
+    /// Syntax highlighting on the background of an added or deleted line.
+    pub fn diff_syntax(&self, typ: DiffLineType, syntax: Style) -> Style {
+        match typ {
+            DiffLineType::Add => syntax.bg(self.diff_syntax_add_bg),
+            DiffLineType::Delete => syntax.bg(self.diff_syntax_delete_bg),
+            _ => syntax,
+        }
+    }
+
+    /// Whitespace made visible within text of `style`.
+    pub fn diff_whitespace(&self, style: Style) -> Style {
+        style.fg(self.diff_whitespace)
+    }
+
+    /// A search match, `current` being the one jumped to.
+    pub fn diff_search_match(&self, current: bool) -> Style {
+        let bg = if current {
+            self.diff_search_current
+        } else {
+            self.diff_search_match
+        };
+
+        Style::default().fg(Color::Black).bg(bg)
+    }
+
+    pub fn get_syntax(&self) -> &str {
+        &self.syntax
+    }
+
+    pub fn diff_line_number(&self) -> Style {
+        Style::default().fg(self.disabled_fg)
+    }
+
+    pub fn file_action(&self, action: Option<&FileAction>, selected: bool) -> Style {
+        let color = match action {
+            Some(FileAction::Add | FileAction::Branch | FileAction::Import) => self.diff_file_added,
+            Some(FileAction::Delete | FileAction::Purge | FileAction::Archive) => {
+                self.diff_file_removed
+            }
+            Some(FileAction::MoveAdd | FileAction::MoveDelete) => self.diff_file_moved,
+            Some(FileAction::Edit | FileAction::Integrate) => self.diff_file_modified,
+            Some(FileAction::Unknown(_)) | None => Color::Reset,
+        };
+
+        self.apply_select(Style::default().fg(color), selected)
+    }
 
     // pub fn text_danger(&self) -> Style {
     //     Style::default().fg(self.danger_fg)
@@ -183,10 +258,26 @@ impl Theme {
         })
     }
 
-    // pub fn commit_hash(&self, selected: bool) -> Style {
-    //     self.apply_select(Style::default().fg(self.commit_hash), selected)
-    // }
-    //
+    pub fn changelist_number(&self, selected: bool) -> Style {
+        self.apply_select(Style::default().fg(self.changelist_number), selected)
+    }
+
+    pub fn changelist_time(&self, selected: bool) -> Style {
+        self.apply_select(Style::default().fg(self.changelist_time), selected)
+    }
+
+    pub fn changelist_user(&self, selected: bool) -> Style {
+        self.apply_select(Style::default().fg(self.changelist_user), selected)
+    }
+
+    pub fn changelist_client(&self, selected: bool) -> Style {
+        self.apply_select(Style::default().fg(self.changelist_client), selected)
+    }
+
+    pub fn changelist_description(&self, selected: bool) -> Style {
+        self.apply_select(Style::default(), selected)
+    }
+
     // pub fn log_marker(&self, selected: bool) -> Style {
     //     let mut style = Style::default()
     //         .fg(self.commit_author)
@@ -197,14 +288,6 @@ impl Theme {
     //     style
     // }
     //
-    // pub fn commit_time(&self, selected: bool) -> Style {
-    //     self.apply_select(Style::default().fg(self.commit_time), selected)
-    // }
-    //
-    // pub fn commit_author(&self, selected: bool) -> Style {
-    //     self.apply_select(Style::default().fg(self.commit_author), selected)
-    // }
-    //
     // pub fn commit_hash_in_blame(&self, is_blamed_commit: bool) -> Style {
     //     if is_blamed_commit {
     //         Style::default()
@@ -236,7 +319,7 @@ impl Theme {
     // This is supposed to be called when theme.ron doesn't already exists.
     fn save_patch(&self, theme_path: &PathBuf) -> Result<()> {
         let mut file = File::create(theme_path)?;
-        let patch = self.into_patch_by_diff(Self::default());
+        let patch = self.clone().into_patch_by_diff(Self::default());
         let data = to_string_pretty(&patch, PrettyConfig::default())?;
 
         file.write_all(data.as_bytes())?;
@@ -275,13 +358,22 @@ impl Default for Theme {
             disabled_fg: Color::DarkGray,
             diff_line_add: Color::Green,
             diff_line_delete: Color::Red,
+            diff_word_add: Color::LightGreen,
+            diff_word_delete: Color::LightRed,
+            diff_syntax_add_bg: Color::Rgb(0x1e, 0x3a, 0x1e),
+            diff_syntax_delete_bg: Color::Rgb(0x4a, 0x1e, 0x1e),
+            diff_whitespace: Color::DarkGray,
+            diff_search_match: Color::Yellow,
+            diff_search_current: Color::LightRed,
             diff_file_added: Color::LightGreen,
             diff_file_removed: Color::LightRed,
             diff_file_moved: Color::LightMagenta,
             diff_file_modified: Color::Yellow,
-            // commit_hash: Color::Magenta,
-            // commit_time: Color::LightCyan,
-            // commit_author: Color::Green,
+            changelist_number: Color::Magenta,
+            changelist_time: Color::LightCyan,
+            changelist_user: Color::Green,
+            changelist_client: Color::LightBlue,
+            syntax: "base16-eighties.dark".to_string(),
             // danger_fg: Color::Red,
             // push_gauge_bg: Color::Blue,
             // push_gauge_fg: Color::Reset,
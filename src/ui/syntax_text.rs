@@ -0,0 +1,325 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    ffi::OsStr,
+    hash::{Hash, Hasher},
+    ops::Range,
+    path::Path,
+    rc::Rc,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use asyncp4::{
+    asyncjob::{AsyncJob, RunParams},
+    sync::{DiffLine, FileBaseType, FileDiff, FileType},
+};
+use ratatui::style::{Color, Modifier, Style};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{FontStyle, ThemeSet},
+    parsing::{SyntaxReference, SyntaxSet},
+};
+
+use crate::AsyncAppNotification;
+
+/// Diffs with more content than this are shown as plain text.
+const MAX_HIGHLIGHT_BYTES: usize = 512 * 1024;
+/// Number of highlighted diffs kept by [`SyntaxCache`].
+const CACHE_SIZE: usize = 32;
+
+/// Syntax styles of a line as byte ranges of its content.
+pub type LineHighlights = Vec<(Range<usize>, Style)>;
+
+/// Highlights of every line of a [`FileDiff`], indexed like its hunks.
+pub type DiffHighlights = Vec<Vec<LineHighlights>>;
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+/// Highlighted diffs keyed by path, content and syntax theme, so a file
+/// revision is only highlighted once. Files that are not highlighted are
+/// kept as `None`.
+#[derive(Default)]
+pub struct SyntaxCache {
+    entries: HashMap<u64, Option<Rc<DiffHighlights>>>,
+}
+
+impl SyntaxCache {
+    pub fn key(
+        path: &str,
+        file_type: Option<&FileType>,
+        diff: &FileDiff,
+        syntax_theme: &str,
+    ) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (path, file_type, diff, syntax_theme).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// `None` if `key` is not cached yet.
+    pub fn get(&self, key: u64) -> Option<Option<Rc<DiffHighlights>>> {
+        self.entries.get(&key).cloned()
+    }
+
+    pub fn insert(
+        &mut self,
+        key: u64,
+        highlights: Option<DiffHighlights>,
+    ) -> Option<Rc<DiffHighlights>> {
+        if self.entries.len() >= CACHE_SIZE {
+            self.entries.clear();
+        }
+
+        let highlights = highlights.map(Rc::new);
+        self.entries.insert(key, highlights.clone());
+
+        highlights
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HighlightParams {
+    /// the [`SyntaxCache`] key of the diff
+    pub key: u64,
+    pub path: String,
+    pub file_type: Option<FileType>,
+    pub diff: FileDiff,
+    pub syntax_theme: String,
+}
+
+enum JobState {
+    Request(HighlightParams),
+    Response(u64, Option<DiffHighlights>),
+}
+
+/// Runs [`highlight_diff`] off the UI thread.
+#[derive(Clone)]
+pub struct AsyncHighlightJob {
+    state: Arc<Mutex<Option<JobState>>>,
+}
+
+impl AsyncHighlightJob {
+    pub fn new(params: HighlightParams) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(Some(JobState::Request(params)))),
+        }
+    }
+
+    /// The highlights along with the cache key they were requested for.
+    pub fn result(&self) -> Option<(u64, Option<DiffHighlights>)> {
+        if let Ok(mut state) = self.state.lock() {
+            if let Some(JobState::Response(key, highlights)) = state.take() {
+                return Some((key, highlights));
+            }
+        }
+
+        None
+    }
+}
+
+impl AsyncJob for AsyncHighlightJob {
+    type Notification = AsyncAppNotification;
+    type Progress = ();
+
+    fn run(
+        &mut self,
+        _params: RunParams<Self::Notification, Self::Progress>,
+    ) -> asyncp4::Result<Self::Notification> {
+        let mut state = self.state.lock()?;
+
+        *state = state.take().map(|state| match state {
+            JobState::Request(request) => {
+                let highlights = highlight_diff(
+                    &request.path,
+                    request.file_type.as_ref(),
+                    &request.diff,
+                    &request.syntax_theme,
+                );
+                JobState::Response(request.key, highlights)
+            }
+            response => response,
+        });
+
+        Ok(AsyncAppNotification::Highlighted)
+    }
+}
+
+/// Highlights both sides of `diff` in the language of `path`. `None` for
+/// binary or very large files, unknown languages and unknown themes.
+pub fn highlight_diff(
+    path: &str,
+    file_type: Option<&FileType>,
+    diff: &FileDiff,
+    syntax_theme: &str,
+) -> Option<DiffHighlights> {
+    let lines = || diff.hunks.iter().flat_map(|h| h.lines.iter());
+
+    let size = lines().map(|l| l.content.len()).sum::<usize>();
+    if diff.binary || size > MAX_HIGHLIGHT_BYTES {
+        return None;
+    }
+
+    let first_line = lines()
+        .find(|l| l.position != Default::default())
+        .map(|l| l.content.as_ref());
+    let syntax = find_syntax(path, file_type, first_line)?;
+
+    let Some(theme) = theme_set().themes.get(syntax_theme) else {
+        log::warn!("unknown syntax theme: {syntax_theme}");
+        return None;
+    };
+
+    let mut highlights = diff
+        .hunks
+        .iter()
+        .map(|h| vec![LineHighlights::new(); h.lines.len()])
+        .collect::<DiffHighlights>();
+
+    // each side is highlighted on its own so multi-line constructs continue
+    // across lines; context lines take the highlighting of the new side
+    let sides: [fn(&DiffLine) -> bool; 2] = [
+        |l| l.position.old_lineno.is_some(),
+        |l| l.position.new_lineno.is_some(),
+    ];
+    for on_side in sides {
+        let mut highlighter = HighlightLines::new(syntax, theme);
+
+        for (hunk_index, hunk) in diff.hunks.iter().enumerate() {
+            for (line_index, line) in hunk.lines.iter().enumerate() {
+                if on_side(line) {
+                    highlights[hunk_index][line_index] =
+                        highlight_line(&mut highlighter, &line.content);
+                }
+            }
+        }
+    }
+
+    Some(highlights)
+}
+
+fn find_syntax(
+    path: &str,
+    file_type: Option<&FileType>,
+    first_line: Option<&str>,
+) -> Option<&'static SyntaxReference> {
+    // only text filetypes have a language
+    if let Some(file_type) = file_type {
+        if !matches!(
+            file_type.base,
+            FileBaseType::Text | FileBaseType::Unicode | FileBaseType::UTF8 | FileBaseType::UTF16
+        ) {
+            return None;
+        }
+    }
+
+    let syntax_set = syntax_set();
+    let path = Path::new(path);
+
+    path.extension()
+        .or_else(|| path.file_name())
+        .and_then(OsStr::to_str)
+        .and_then(|ext| syntax_set.find_syntax_by_extension(ext))
+        // e.g. scripts starting with `#!/bin/sh`
+        .or_else(|| first_line.and_then(|l| syntax_set.find_syntax_by_first_line(l)))
+}
+
+fn highlight_line(highlighter: &mut HighlightLines, content: &str) -> LineHighlights {
+    let line = format!("{content}\n");
+
+    let Ok(ranges) = highlighter.highlight_line(&line, syntax_set()) else {
+        return LineHighlights::new();
+    };
+
+    let mut start = 0;
+    ranges
+        .into_iter()
+        .filter_map(|(style, text)| {
+            let range = start..(start + text.len()).min(content.len());
+            start += text.len();
+
+            (!range.is_empty()).then(|| (range, to_style(style)))
+        })
+        .collect()
+}
+
+fn to_style(style: syntect::highlighting::Style) -> Style {
+    let fg = style.foreground;
+    let mut result = Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b));
+
+    if style.font_style.contains(FontStyle::BOLD) {
+        result = result.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        result = result.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        result = result.add_modifier(Modifier::UNDERLINED);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use asyncp4::sync::{DiffLinePosition, DiffLineType, Hunk};
+
+    fn diff(lines: &[&str]) -> FileDiff {
+        let lines = lines
+            .iter()
+            .zip(1..)
+            .map(|(content, n)| DiffLine {
+                content: (*content).into(),
+                line_type: DiffLineType::Add,
+                position: DiffLinePosition {
+                    old_lineno: None,
+                    new_lineno: Some(n),
+                },
+                ..DiffLine::default()
+            })
+            .collect();
+
+        FileDiff {
+            hunks: vec![Hunk {
+                header_hash: 0,
+                lines,
+            }],
+            ..FileDiff::default()
+        }
+    }
+
+    #[test]
+    fn test_highlight_diff() {
+        let diff = diff(&["fn main() {", "    let x = 1;", "}"]);
+
+        let highlights =
+            highlight_diff("//depot/main.rs", None, &diff, "base16-ocean.dark").unwrap();
+
+        let line = &highlights[0][1];
+        assert!(line.len() > 1);
+        assert_eq!(line.last().unwrap().0.end, "    let x = 1;".len());
+    }
+
+    #[test]
+    fn test_plain_text_fallback() {
+        let diff = diff(&["some text"]);
+
+        assert!(
+            highlight_diff("//depot/notes.unknown", None, &diff, "base16-ocean.dark").is_none()
+        );
+        assert!(highlight_diff("//depot/main.rs", None, &diff, "no such theme").is_none());
+        assert!(highlight_diff(
+            "//depot/main.rs",
+            Some(&FileType::new(FileBaseType::Binary)),
+            &diff,
+            "base16-ocean.dark"
+        )
+        .is_none());
+    }
+}